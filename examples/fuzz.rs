@@ -0,0 +1,145 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Differential fuzzer of `KelvinMap` against `BTreeMap`.
+//!
+//! Usage: `cargo run --release --example fuzz -- [runs] [ops] [seed]`
+//!
+//! Every run applies a random sequence of operations to both maps, checking
+//! the results after each of them. A failing sequence is shrunk before it is
+//! reported, so it can be turned into a regression test.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ops::Range;
+use std::process;
+
+use dusk_kelvin_map::{HashedMap, StoreHasher};
+use microkelvin::Keyed;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Keys are drawn from a small space so the operations collide often
+const KEY_SPACE: u64 = 256;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Insert(u64, u64),
+    Remove(u64),
+    Get(u64),
+    Range(Range<u64>),
+}
+
+impl Op {
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        let key = rng.gen_range(0..KEY_SPACE);
+
+        match rng.gen_range(0..8) {
+            0..=2 => Op::Insert(key, rng.gen()),
+            3..=4 => Op::Remove(key),
+            5..=6 => Op::Get(key),
+            _ => Op::Range(key..rng.gen_range(key..=KEY_SPACE)),
+        }
+    }
+}
+
+/// Apply the operations to both maps, returning the index of the first
+/// operation with a mismatching result, if any
+fn run(ops: &[Op]) -> Option<usize> {
+    let mut map: HashedMap<u64, u64> = HashedMap::default();
+    let mut reference = BTreeMap::new();
+
+    for (i, op) in ops.iter().enumerate() {
+        let matches = match op {
+            Op::Insert(k, v) => {
+                map.insert(*k, *v).ok() == Some(reference.insert(*k, *v))
+            }
+            Op::Remove(k) => map.remove(k).ok() == Some(reference.remove(k)),
+            Op::Get(k) => match map.get(k) {
+                Ok(v) => v.map(|v| *v) == reference.get(k).copied(),
+                Err(_) => false,
+            },
+            Op::Range(range) => {
+                let expected: Vec<(u64, u64)> = reference
+                    .range(range.clone())
+                    .map(|(k, v)| (*k, *v))
+                    .collect();
+
+                let root = map.root_hash::<StoreHasher>();
+                map.prove_range::<StoreHasher, _>(range.clone())
+                    .ok()
+                    .and_then(|proof| {
+                        proof.verify::<StoreHasher, _>(&root, range).map(
+                            |leaves| {
+                                leaves
+                                    .into_iter()
+                                    .map(|l| (*l.key(), *l.value()))
+                                    .collect::<Vec<_>>()
+                            },
+                        )
+                    })
+                    .map(|entries| entries == expected)
+                    .unwrap_or(false)
+            }
+        };
+
+        if !matches || map.len() != reference.len() {
+            return Some(i);
+        }
+    }
+
+    None
+}
+
+/// Remove operations from a failing sequence while it keeps failing
+fn shrink(mut ops: Vec<Op>) -> Vec<Op> {
+    if let Some(i) = run(&ops) {
+        ops.truncate(i + 1);
+    }
+
+    let mut i = 0;
+    while i < ops.len() {
+        let mut candidate = ops.clone();
+        candidate.remove(i);
+
+        if run(&candidate).is_some() {
+            ops = candidate;
+        } else {
+            i += 1;
+        }
+    }
+
+    ops
+}
+
+fn arg(idx: usize, default: u64) -> u64 {
+    env::args()
+        .nth(idx)
+        .map(|a| a.parse().expect("Arguments are expected to be integers"))
+        .unwrap_or(default)
+}
+
+fn main() {
+    let runs = arg(1, 100);
+    let len = arg(2, 1_000) as usize;
+    let seed = arg(3, 0);
+
+    for run_seed in seed..seed.saturating_add(runs) {
+        let mut rng = StdRng::seed_from_u64(run_seed);
+        let ops: Vec<Op> = (0..len).map(|_| Op::random(&mut rng)).collect();
+
+        if run(&ops).is_some() {
+            let ops = shrink(ops);
+
+            eprintln!("Mismatch found with seed {}, shrunk to:", run_seed);
+            ops.iter().for_each(|op| eprintln!("    {:?}", op));
+
+            process::exit(1);
+        }
+    }
+
+    println!("{} runs of {} operations passed", runs, len);
+}
@@ -0,0 +1,287 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::fmt;
+use std::ops::Bound;
+
+use canonical::{Canon, CanonError};
+use dusk_kelvin_map::{
+    commit_all, HashedMap, KelvinMap, Map, MapAnnotation, MapError, RangeProof,
+    StoreHasher,
+};
+use microkelvin::Keyed;
+
+/// Identifier of an account
+pub type Account = u64;
+
+/// Allowances are keyed by `(owner, spender)`, so the allowances granted by
+/// an owner are a contiguous range of keys
+type AllowanceKey = (Account, Account);
+
+#[derive(Debug)]
+pub enum LedgerError {
+    Map(MapError),
+    InsufficientBalance,
+    InsufficientAllowance,
+    Overflow,
+}
+
+impl From<CanonError> for LedgerError {
+    fn from(e: CanonError) -> Self {
+        LedgerError::Map(e.into())
+    }
+}
+
+impl From<MapError> for LedgerError {
+    fn from(e: MapError) -> Self {
+        LedgerError::Map(e)
+    }
+}
+
+/// Amount mapped to `k`, zero if absent
+fn amount_of<K, A>(map: &KelvinMap<K, u64, A>, k: &K) -> Result<u64, CanonError>
+where
+    K: Canon + Ord,
+    A: MapAnnotation<K, u64>,
+{
+    Ok(map.get(k)?.map(|a| *a).unwrap_or(0))
+}
+
+/// Map `k` to `amount`, removing it when zero so only the non-zero amounts
+/// are committed
+fn set_amount<K, A>(
+    map: &mut KelvinMap<K, u64, A>,
+    k: K,
+    amount: u64,
+) -> Result<(), CanonError>
+where
+    K: Canon + Ord,
+    A: MapAnnotation<K, u64>,
+{
+    match amount {
+        0 => map.remove(&k)?,
+        _ => map.insert(k, amount)?,
+    };
+
+    Ok(())
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerError::Map(e) => write!(f, "map error: {}", e),
+            LedgerError::InsufficientBalance => {
+                write!(f, "insufficient balance")
+            }
+            LedgerError::InsufficientAllowance => {
+                write!(f, "insufficient allowance")
+            }
+            LedgerError::Overflow => write!(f, "amount overflow"),
+        }
+    }
+}
+
+/// Page of holders, with the cursor to resume after it, if any
+pub type Page = (Vec<(Account, u64)>, Option<Account>);
+
+/// Fungible token ledger.
+///
+/// Balances are committed with [`HashedMap::root_hash`] so any balance can be
+/// proven to a light client. Empty balances and allowances are removed, so
+/// the commitment only depends on the non-zero entries.
+#[derive(Debug, Default, Clone)]
+pub struct Ledger {
+    balances: HashedMap<Account, u64>,
+    allowances: Map<AllowanceKey, u64>,
+    supply: u64,
+}
+
+impl Ledger {
+    /// Total amount of tokens minted
+    pub fn supply(&self) -> u64 {
+        self.supply
+    }
+
+    /// Number of accounts holding tokens
+    pub fn holders(&self) -> usize {
+        self.balances.len()
+    }
+
+    /// Balance of `account`
+    pub fn balance(&self, account: Account) -> Result<u64, LedgerError> {
+        Ok(amount_of(&self.balances, &account)?)
+    }
+
+    /// Amount `spender` is allowed to transfer on behalf of `owner`
+    pub fn allowance(
+        &self,
+        owner: Account,
+        spender: Account,
+    ) -> Result<u64, LedgerError> {
+        Ok(amount_of(&self.allowances, &(owner, spender))?)
+    }
+
+    fn set_balance(
+        &mut self,
+        account: Account,
+        amount: u64,
+    ) -> Result<(), LedgerError> {
+        Ok(set_amount(&mut self.balances, account, amount)?)
+    }
+
+    /// Create `amount` new tokens owned by `account`
+    pub fn mint(
+        &mut self,
+        account: Account,
+        amount: u64,
+    ) -> Result<(), LedgerError> {
+        let supply = self
+            .supply
+            .checked_add(amount)
+            .ok_or(LedgerError::Overflow)?;
+        let balance = self.balance(account)? + amount;
+
+        self.set_balance(account, balance)?;
+        self.supply = supply;
+
+        Ok(())
+    }
+
+    /// Move `amount` tokens from `from` to `to`
+    pub fn transfer(
+        &mut self,
+        from: Account,
+        to: Account,
+        amount: u64,
+    ) -> Result<(), LedgerError> {
+        let balance = self.balance(from)?;
+        if balance < amount {
+            return Err(LedgerError::InsufficientBalance);
+        }
+
+        // No balance exceeds the supply, so the credit can't overflow
+        self.set_balance(from, balance - amount)?;
+        let credited = self.balance(to)? + amount;
+        self.set_balance(to, credited)
+    }
+
+    /// Allow `spender` to transfer up to `amount` tokens of `owner`
+    pub fn approve(
+        &mut self,
+        owner: Account,
+        spender: Account,
+        amount: u64,
+    ) -> Result<(), LedgerError> {
+        Ok(set_amount(&mut self.allowances, (owner, spender), amount)?)
+    }
+
+    /// Transfer `amount` tokens of `owner` to `to`, spending the allowance
+    /// granted to `spender`
+    pub fn transfer_from(
+        &mut self,
+        spender: Account,
+        owner: Account,
+        to: Account,
+        amount: u64,
+    ) -> Result<(), LedgerError> {
+        let allowance = self.allowance(owner, spender)?;
+        if allowance < amount {
+            return Err(LedgerError::InsufficientAllowance);
+        }
+
+        let balance = self.balance(owner)?;
+        if balance < amount {
+            return Err(LedgerError::InsufficientBalance);
+        }
+
+        let mut balances = self.balances.stage();
+        let mut allowances = self.allowances.stage();
+
+        set_amount(&mut balances, owner, balance - amount)?;
+        let credited = amount_of(&balances, &to)? + amount;
+        set_amount(&mut balances, to, credited)?;
+        set_amount(&mut allowances, (owner, spender), allowance - amount)?;
+
+        // The balances and the allowances are updated together, or not at all
+        Ok(commit_all(&mut [&mut balances, &mut allowances])?)
+    }
+
+    /// Allowances granted by `owner`, in spender order
+    pub fn allowances_of(
+        &self,
+        owner: Account,
+    ) -> Result<Vec<(Account, u64)>, LedgerError> {
+        let range = (owner, Account::MIN)..=(owner, Account::MAX);
+
+        let allowances = self
+            .allowances
+            .range(range)?
+            .map(|entry| entry.map(|((_, spender), a)| (*spender, *a)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(allowances)
+    }
+
+    /// Up to `limit` holders following the account `after`, in account order
+    pub fn holders_page(
+        &self,
+        after: Option<Account>,
+        limit: usize,
+    ) -> Result<Page, LedgerError> {
+        let start = match after {
+            Some(a) => Bound::Excluded(a),
+            None => Bound::Unbounded,
+        };
+
+        let page: Vec<(Account, u64)> = self
+            .balances
+            .range((start, Bound::Unbounded))?
+            .take(limit)
+            .map(|entry| entry.map(|(k, b)| (*k, *b)))
+            .collect::<Result<_, _>>()?;
+
+        let next = match page.last() {
+            Some((last, _)) if page.len() == limit => Some(*last),
+            _ => None,
+        };
+
+        Ok((page, next))
+    }
+
+    /// Commitment to the balances of the ledger
+    pub fn root(&self) -> [u8; 32] {
+        self.balances.root_hash::<StoreHasher>()
+    }
+
+    /// Proof of the balance of `account`, including a zero balance
+    pub fn prove_balance(
+        &self,
+        account: Account,
+    ) -> Result<RangeProof<Account, u64>, LedgerError> {
+        Ok(self
+            .balances
+            .prove_range::<StoreHasher, _>(account..=account)?)
+    }
+}
+
+/// Verify a balance proven against the `root` of a ledger, returning `None`
+/// if the proof is invalid
+pub fn verify_balance(
+    root: &[u8; 32],
+    account: Account,
+    proof: &RangeProof<Account, u64>,
+) -> Option<u64> {
+    let leaves = proof.verify::<StoreHasher, _>(root, &(account..=account))?;
+
+    Some(
+        leaves
+            .into_iter()
+            .map(|l| (*Keyed::<Account>::key(l), *l.value()))
+            .find(|(k, _)| *k == account)
+            .map(|(_, balance)| balance)
+            .unwrap_or(0),
+    )
+}
@@ -0,0 +1,117 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Token ledger built on the public API of the map.
+//!
+//! Usage: `cargo run --example ledger -- [accounts]`
+//!
+//! Balances are kept in a [`HashedMap`](dusk_kelvin_map::HashedMap) so they
+//! can be proven against the committed root, and allowances in a map keyed by
+//! `(owner, spender)` so the allowances of an owner are listed with a single
+//! range walk. Holders are listed in pages resumed from the last account, and
+//! the writes spending an allowance are staged and committed to both maps
+//! together.
+//!
+//! Rough edges found while writing it, to be addressed in the crate:
+//!
+//! - The leaves returned by a verified proof only expose their key through
+//!   the `Keyed` trait of microkelvin, which needs explicit type annotations.
+//!
+//! The scenario run by `main` is also run by `cargo test`, so any API change
+//! breaking the ledger is caught by the test suite.
+
+mod ledger;
+
+use std::env;
+
+use ledger::{verify_balance, Ledger, LedgerError};
+
+/// Run a scenario on a ledger of `accounts` accounts, checking its results
+fn scenario(accounts: u64) -> Result<Ledger, LedgerError> {
+    let mut ledger = Ledger::default();
+
+    for account in 0..accounts {
+        ledger.mint(account, 1_000 + account)?;
+    }
+    let supply = ledger.supply();
+
+    // Pay the odd accounts out of the even ones
+    for account in (0..accounts).step_by(2) {
+        ledger.transfer(account, account + 1, 500)?;
+    }
+    assert!(matches!(
+        ledger.transfer(0, 1, 10_000),
+        Err(LedgerError::InsufficientBalance)
+    ));
+
+    // Account 1 delegates to every other account, one of them spends it all
+    for spender in 2..accounts.min(6) {
+        ledger.approve(1, spender, 100)?;
+    }
+    ledger.transfer_from(2, 1, 0, 100)?;
+    assert!(matches!(
+        ledger.transfer_from(2, 1, 0, 1),
+        Err(LedgerError::InsufficientAllowance)
+    ));
+    let allowances = ledger.allowances_of(1)?;
+    assert!(allowances
+        .iter()
+        .all(|(spender, a)| *spender > 2 && *a == 100));
+
+    // Drain the first account, so it is no longer a holder
+    let drained = ledger.balance(0)?;
+    ledger.transfer(0, 1, drained)?;
+    assert_eq!(accounts as usize - 1, ledger.holders());
+
+    // Walk the holders in pages, resuming after the last account of each
+    let mut total = 0;
+    let mut cursor = None;
+    loop {
+        let (page, next) = ledger.holders_page(cursor, 7)?;
+        total += page.iter().map(|(_, b)| b).sum::<u64>();
+
+        match next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(supply, total);
+
+    // Prove a balance and the absence of the drained one to a light client
+    let root = ledger.root();
+    let proof = ledger.prove_balance(1)?;
+    assert_eq!(Some(ledger.balance(1)?), verify_balance(&root, 1, &proof));
+    let proof = ledger.prove_balance(0)?;
+    assert_eq!(Some(0), verify_balance(&root, 0, &proof));
+
+    Ok(ledger)
+}
+
+fn main() -> Result<(), LedgerError> {
+    let accounts = env::args()
+        .nth(1)
+        .map(|a| {
+            a.parse()
+                .expect("The argument is expected to be an integer")
+        })
+        .unwrap_or(32);
+
+    let ledger = scenario(accounts)?;
+
+    println!(
+        "{} tokens held by {} accounts, committed to {:02x?}",
+        ledger.supply(),
+        ledger.holders(),
+        ledger.root()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn ledger() {
+    scenario(32).expect("Failed to run the ledger scenario");
+}
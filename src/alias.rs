@@ -0,0 +1,93 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::diff::{skip_left, skip_right, split_key};
+use crate::{KelvinMap, MapAnnotation};
+
+use core::ops::{Bound, RangeBounds};
+
+use canonical::{Canon, CanonError};
+use microkelvin::MaxKey;
+
+/// Check if every key in `(lower, upper]` is within `range`
+fn covers<K, R>(range: &R, lower: Option<&K>, upper: &K) -> bool
+where
+    K: Ord,
+    R: RangeBounds<K>,
+{
+    let start = match (range.start_bound(), lower) {
+        (Bound::Unbounded, _) => true,
+        (Bound::Included(s), Some(l)) | (Bound::Excluded(s), Some(l)) => l >= s,
+        (_, None) => false,
+    };
+
+    let end = match range.end_bound() {
+        Bound::Unbounded => true,
+        Bound::Included(e) => upper <= e,
+        Bound::Excluded(e) => upper < e,
+    };
+
+    start && end
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Create a map with the entries of `self` within `range`.
+    ///
+    /// The sub-trees entirely within the range are shared with `self` rather
+    /// than copied, so they keep their persisted ids, and are only copied
+    /// when either map writes to them.
+    pub fn alias_range<R>(&self, range: R) -> Result<Self, CanonError>
+    where
+        R: RangeBounds<K>,
+    {
+        self.alias_within(&range, None)
+    }
+
+    /// Alias the entries within `range` of a sub-tree whose keys are greater
+    /// than `lower`
+    fn alias_within<R>(
+        &self,
+        range: &R,
+        lower: Option<&K>,
+    ) -> Result<Self, CanonError>
+    where
+        R: RangeBounds<K>,
+    {
+        match self {
+            KelvinMap::Empty => Ok(KelvinMap::Empty),
+            KelvinMap::Leaf(l) if range.contains(l._key()) => Ok(self.clone()),
+            KelvinMap::Leaf(_) => Ok(KelvinMap::Empty),
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let max: &MaxKey<K> = r.annotation().borrow();
+                if let MaxKey::Maximum(max) = max {
+                    if covers(range, lower, max) {
+                        return Ok(self.clone());
+                    }
+                }
+
+                let split = split_key(l);
+
+                let left = match split {
+                    Some(s) if skip_left(range, s) => KelvinMap::Empty,
+                    _ => l.val()?.alias_within(range, lower)?,
+                };
+
+                let right = match split {
+                    Some(s) if skip_right(range, s) => KelvinMap::Empty,
+                    _ => r.val()?.alias_within(range, split)?,
+                };
+
+                Ok(KelvinMap::node(left, right))
+            }
+        }
+    }
+}
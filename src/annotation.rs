@@ -4,13 +4,14 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use crate::{KelvinMap, Leaf};
+use crate::{KelvinMap, Leaf, MapError};
 
 use canonical::Canon;
 use canonical_derive::Canon;
-use microkelvin::{Annotation, Cardinality, Combine, MaxKey};
+use microkelvin::{Annotation, Cardinality, Combine, Compound, MaxKey};
 
 use core::borrow::Borrow;
+use core::cmp::Ordering;
 
 /// Trait requirement to be an annotation of `KelvinMap`.
 ///
@@ -22,6 +23,27 @@ where
     Self: Canon + Annotation<Leaf<K, V>> + Combine<KelvinMap<K, V, Self>, Self>,
     Self: Borrow<MaxKey<K>> + Borrow<Cardinality>,
 {
+    /// Compare the maximum key of the annotated sub-tree against `key`.
+    ///
+    /// Annotations over concrete key types may override it to skip the
+    /// generic `MaxKey` plumbing.
+    fn cmp_max_key(&self, key: &K) -> Ordering {
+        let max: &MaxKey<K> = self.borrow();
+
+        match max {
+            MaxKey::Maximum(max) => max.cmp(key),
+            MaxKey::NegativeInfinity => Ordering::Less,
+        }
+    }
+
+    /// Report a failure recorded while combining the annotation.
+    ///
+    /// `Combine::combine` can't fail, so annotations that can overflow or
+    /// otherwise fail flag it in their state, and surface it here for
+    /// [`KelvinMap::insert_checked`] and [`KelvinMap::remove_checked`].
+    fn check(&self) -> Result<(), MapError> {
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Default, Canon)]
@@ -90,3 +112,68 @@ where
     V: Canon,
 {
 }
+
+#[derive(Debug, Clone, Default, Canon)]
+/// Minimum working annotation for maps keyed by `u64`.
+///
+/// Equivalent to [`MapAnnotationDefault<u64>`], but combines the maximum keys
+/// of the children, and compares them against the searched keys, with plain
+/// integer comparisons rather than borrowing them through [`MaxKey`].
+pub struct MapAnnotationU64 {
+    cardinality: Cardinality,
+    max: MaxKey<u64>,
+}
+
+impl Borrow<MaxKey<u64>> for MapAnnotationU64 {
+    fn borrow(&self) -> &MaxKey<u64> {
+        &self.max
+    }
+}
+
+impl Borrow<Cardinality> for MapAnnotationU64 {
+    fn borrow(&self) -> &Cardinality {
+        &self.cardinality
+    }
+}
+
+impl<V> Annotation<Leaf<u64, V>> for MapAnnotationU64 {
+    fn from_leaf(leaf: &Leaf<u64, V>) -> Self {
+        let cardinality = Cardinality::from_leaf(leaf);
+        let max = MaxKey::Maximum(*leaf._key());
+
+        Self { cardinality, max }
+    }
+}
+
+impl<V> Combine<KelvinMap<u64, V, MapAnnotationU64>, MapAnnotationU64>
+    for MapAnnotationU64
+where
+    V: Canon,
+{
+    fn combine(node: &KelvinMap<u64, V, MapAnnotationU64>) -> Self {
+        let cardinality = Cardinality::combine(node);
+        let max = node
+            .children()
+            .filter_map(|child| match child.annotation().max {
+                MaxKey::Maximum(m) => Some(m),
+                MaxKey::NegativeInfinity => None,
+            })
+            .max()
+            .map(MaxKey::Maximum)
+            .unwrap_or_default();
+
+        Self { cardinality, max }
+    }
+}
+
+impl<V> MapAnnotation<u64, V> for MapAnnotationU64
+where
+    V: Canon,
+{
+    fn cmp_max_key(&self, key: &u64) -> Ordering {
+        match self.max {
+            MaxKey::Maximum(max) => max.cmp(key),
+            MaxKey::NegativeInfinity => Ordering::Less,
+        }
+    }
+}
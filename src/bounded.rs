@@ -0,0 +1,340 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::map::cmp_max_key;
+use crate::{KelvinMap, Leaf, MapAnnotation};
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::ops::{Bound, RangeBounds};
+
+use canonical::{Canon, CanonError};
+use microkelvin::{Branch, BranchMut, Child, Keyed, Step, Walk, Walker};
+
+/// Walker pointing to the first leaf whose key is within a lower and an
+/// upper bound.
+///
+/// The sub-trees whose max key is below the lower bound are pruned from their
+/// annotation, and the walk is aborted as soon as a leaf above the upper
+/// bound is reached. Lookups, ranges and counts all check their bounds here.
+pub(crate) struct BoundedWalker<'a, K> {
+    lower: Bound<&'a K>,
+    upper: Bound<&'a K>,
+    /// The max key of the current sub-tree is known to be the lower bound
+    rightmost: bool,
+}
+
+impl<'a, K> BoundedWalker<'a, K>
+where
+    K: Canon + Ord,
+{
+    /// Walker pointing to the leaf of `key`
+    pub(crate) fn key(key: &'a K) -> Self {
+        Self {
+            lower: Bound::Included(key),
+            upper: Bound::Included(key),
+            rightmost: false,
+        }
+    }
+
+    /// Walker pointing to the first leaf within `range`
+    pub(crate) fn range<R>(range: &'a R) -> Self
+    where
+        R: RangeBounds<K>,
+    {
+        Self {
+            lower: range.start_bound(),
+            upper: range.end_bound(),
+            rightmost: false,
+        }
+    }
+
+    /// Check if `key` is before the lower bound
+    pub(crate) fn is_below(&self, key: &K) -> bool {
+        match self.lower {
+            Bound::Included(s) => key < s,
+            Bound::Excluded(s) => key <= s,
+            Bound::Unbounded => false,
+        }
+    }
+
+    /// Check if `key` is after the upper bound
+    pub(crate) fn is_above(&self, key: &K) -> bool {
+        match self.upper {
+            Bound::Included(e) => key > e,
+            Bound::Excluded(e) => key >= e,
+            Bound::Unbounded => false,
+        }
+    }
+
+    /// Remove the leaves within bounds from the sub-tree, appending their
+    /// entries to `taken` in key order
+    fn take<V, A>(
+        &self,
+        map: &mut KelvinMap<K, V, A>,
+        taken: &mut Vec<(K, V)>,
+    ) -> Result<(), CanonError>
+    where
+        V: Canon,
+        A: MapAnnotation<K, V>,
+    {
+        match map {
+            KelvinMap::Empty => Ok(()),
+
+            KelvinMap::Leaf(l) => {
+                if !self.is_below(l._key()) && !self.is_above(l._key()) {
+                    taken.push((l._key().clone(), l.value().clone()));
+                    *map = KelvinMap::Empty;
+                }
+
+                Ok(())
+            }
+
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children_mut();
+                // The keys of the left child are up to its max key, and the
+                // keys of the right child are after it
+                let left = match self.lower {
+                    Bound::Included(s) => cmp_max_key(l, s).is_ge(),
+                    Bound::Excluded(s) => cmp_max_key(l, s).is_gt(),
+                    Bound::Unbounded => true,
+                };
+                let right = match self.upper {
+                    Bound::Included(e) | Bound::Excluded(e) => {
+                        cmp_max_key(l, e).is_lt()
+                    }
+                    Bound::Unbounded => true,
+                };
+
+                if left {
+                    self.take(&mut *l.val_mut()?, taken)?;
+                }
+                if right {
+                    self.take(&mut *r.val_mut()?, taken)?;
+                }
+
+                map.collapse()?;
+                map.refresh_len();
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Step over a node of the tree, towards the first leaf within bounds.
+    ///
+    /// Once a sub-tree is found whose max key equals an inclusive lower
+    /// bound, `rightmost` is set and the following steps descend its
+    /// rightmost path directly.
+    pub(crate) fn step<V, A>(
+        &mut self,
+        walk: &Walk<KelvinMap<K, V, A>, A>,
+    ) -> Step
+    where
+        V: Canon,
+        A: MapAnnotation<K, V>,
+    {
+        if self.rightmost {
+            return rightmost_step(walk);
+        }
+
+        for ofs in 0..2 {
+            match walk.child(ofs) {
+                Child::Leaf(l) if self.is_below(l.key()) => (),
+                Child::Leaf(l) if self.is_above(l.key()) => return Step::Abort,
+                Child::Leaf(_) => return Step::Found(ofs),
+
+                Child::Node(n) => match self.lower {
+                    Bound::Unbounded => return Step::Into(ofs),
+                    Bound::Included(s) => match cmp_max_key(n, s) {
+                        Ordering::Less => (),
+                        Ordering::Equal if self.is_above(s) => {
+                            return Step::Abort
+                        }
+                        Ordering::Equal => {
+                            self.rightmost = true;
+                            return Step::Into(ofs);
+                        }
+                        Ordering::Greater => return Step::Into(ofs),
+                    },
+                    Bound::Excluded(s) => {
+                        if cmp_max_key(n, s).is_gt() {
+                            return Step::Into(ofs);
+                        }
+                    }
+                },
+
+                Child::Empty => (),
+                Child::EndOfNode => return Step::Abort,
+            }
+        }
+
+        Step::Abort
+    }
+}
+
+impl<'a, K, V, A> Walker<KelvinMap<K, V, A>, A> for BoundedWalker<'a, K>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn walk(&mut self, walk: Walk<KelvinMap<K, V, A>, A>) -> Step {
+        self.step(&walk)
+    }
+}
+
+/// Walker pointing to the leaf with the greatest key
+pub(crate) struct RightmostWalker;
+
+impl<K, V, A> Walker<KelvinMap<K, V, A>, A> for RightmostWalker
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn walk(&mut self, walk: Walk<KelvinMap<K, V, A>, A>) -> Step {
+        rightmost_step(&walk)
+    }
+}
+
+/// Step along the rightmost path of a sub-tree whose max key is known to be
+/// within bounds, so no comparison is needed until the leaf is reached
+fn rightmost_step<K, V, A>(walk: &Walk<KelvinMap<K, V, A>, A>) -> Step
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    match (walk.child(0), walk.child(1)) {
+        (_, Child::Node(_)) => Step::Into(1),
+        (_, Child::Leaf(_)) => Step::Found(1),
+        (Child::Node(_), Child::EndOfNode | Child::Empty) => Step::Into(0),
+        (Child::Leaf(_), Child::EndOfNode | Child::Empty) => Step::Found(0),
+        _ => Step::Abort,
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Iterate the entries of the map within `range`, in key order
+    ///
+    /// The first entry is reached in a single walk, pruning the sub-trees
+    /// below the range, and the iteration stops at the first key above it.
+    pub fn range<'a, R>(
+        &'a self,
+        range: R,
+    ) -> Result<
+        impl Iterator<Item = Result<(&'a K, &'a V), CanonError>> + 'a,
+        CanonError,
+    >
+    where
+        R: RangeBounds<K> + 'a,
+    {
+        let first = Branch::walk(self, BoundedWalker::range(&range))?;
+
+        Ok(first
+            .into_iter()
+            .flatten()
+            .map(|leaf| leaf.map(|l| (l.key(), l.value())))
+            .take_while(move |entry| match entry {
+                Ok((k, _)) => !BoundedWalker::range(&range).is_above(k),
+                Err(_) => true,
+            }))
+    }
+
+    /// Iterate the entries of the map within `range` in key order, with
+    /// mutable references to the values
+    ///
+    /// See [`KelvinMap::range`] and [`KelvinMap::iter_mut`].
+    pub fn range_mut<'a, R>(
+        &'a mut self,
+        range: R,
+    ) -> Result<
+        impl Iterator<Item = Result<(&'a K, &'a mut V), CanonError>> + 'a,
+        CanonError,
+    >
+    where
+        R: RangeBounds<K> + 'a,
+    {
+        let first = BranchMut::walk(self, BoundedWalker::range(&range))?;
+
+        Ok(first
+            .into_iter()
+            .flatten()
+            .map(|leaf| leaf.map(Leaf::parts_mut))
+            .take_while(move |entry| match entry {
+                Ok((k, _)) => !BoundedWalker::range(&range).is_above(k),
+                Err(_) => true,
+            }))
+    }
+
+    /// Number of keys of the map within `range`
+    ///
+    /// Only the paths to the two boundaries are traversed.
+    pub fn count_range<R>(&self, range: R) -> Result<u64, CanonError>
+    where
+        R: RangeBounds<K>,
+    {
+        let bounds = BoundedWalker::range(&range);
+
+        let below = self.count_prefix(&|k: &K| bounds.is_below(k))?;
+        let within = self.count_prefix(&|k: &K| !bounds.is_above(k))?;
+
+        Ok(within.saturating_sub(below))
+    }
+
+    /// Returns references to the entry with the greatest key less than or
+    /// equal to `k`, if any
+    ///
+    /// The position of the entry is computed from the cardinality of the
+    /// sub-trees along the path to `k`, and then reached with
+    /// [`KelvinMap::nth`].
+    pub fn get_floor(&self, k: &K) -> Result<Option<(&K, &V)>, CanonError> {
+        let position = self.count_prefix(&|key: &K| key <= k)?;
+        if position == 0 {
+            return Ok(None);
+        }
+
+        self.nth(position as usize - 1)
+    }
+
+    /// Returns references to the entry with the smallest key greater than or
+    /// equal to `k`, if any
+    pub fn get_ceiling(&self, k: &K) -> Result<Option<(&K, &V)>, CanonError> {
+        let bounds = (Bound::Included(k), Bound::Unbounded);
+        let ceiling = Branch::walk(self, BoundedWalker::range(&bounds))?;
+
+        Ok(ceiling
+            .into_iter()
+            .flatten()
+            .next()
+            .transpose()?
+            .map(|l| (l.key(), l.value())))
+    }
+
+    /// Remove the entries of the map within `range`, returning them in key
+    /// order
+    ///
+    /// The entries are collected and removed in a single ordered pass over
+    /// the tree, visiting only the sub-trees overlapping the range, followed
+    /// by a single naive balancing of the root.
+    pub fn take_range<R>(&mut self, range: R) -> Result<Vec<(K, V)>, CanonError>
+    where
+        R: RangeBounds<K>,
+    {
+        let mut taken = Vec::new();
+
+        BoundedWalker::range(&range).take(self, &mut taken)?;
+        self.balance()?;
+
+        Ok(taken)
+    }
+}
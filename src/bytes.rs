@@ -0,0 +1,67 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+use canonical::{Canon, CanonError, EncodeToVec};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Canonical encoding of a value of the map, created by
+/// [`KelvinMap::get_bytes`]
+///
+/// The encoding is held in a buffer that is never reallocated, so the
+/// pointer returned by [`ValBytes::as_ptr`] stays valid, and points to
+/// [`ValBytes::len`] bytes, for as long as the `ValBytes` is alive. This
+/// allows the host to copy the value straight into the linear memory of a
+/// module.
+pub struct ValBytes(Vec<u8>);
+
+impl ValBytes {
+    /// Pointer to the first byte of the encoding
+    pub fn as_ptr(&self) -> *const u8 {
+        self.0.as_ptr()
+    }
+
+    /// Length of the encoding, in bytes
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check if the encoding is empty, as for zero-sized values
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Take the buffer holding the encoding
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Deref for ValBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Returns the canonical encoding of the value corresponding to the key
+    ///
+    /// Will return `Ok(None)` if no correspondent key was found.
+    pub fn get_bytes(&self, k: &K) -> Result<Option<ValBytes>, CanonError> {
+        Ok(self.get(k)?.map(|v| ValBytes(v.encode_to_vec())))
+    }
+}
@@ -0,0 +1,67 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation, MapError};
+
+use canonical::Canon;
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Surface any failure recorded while combining the annotations of the
+    /// map, as reported by [`MapAnnotation::check`]
+    pub fn check_annotation(&self) -> Result<(), MapError> {
+        A::combine(self).check()
+    }
+
+    /// Include a key -> value mapping to the map, failing if combining the
+    /// annotations fails.
+    ///
+    /// Returns the previously mapped value, if any. On failure the write is
+    /// rolled back, so the map keeps its previous mappings.
+    pub fn insert_checked(&mut self, k: K, v: V) -> Result<Option<V>, MapError>
+    where
+        K: Clone,
+    {
+        let old = self.insert(k.clone(), v)?;
+
+        if let Err(e) = self.check_annotation() {
+            match old {
+                Some(old) => self.insert(k, old)?,
+                None => self.remove(&k)?,
+            };
+
+            return Err(e);
+        }
+
+        Ok(old)
+    }
+
+    /// Remove a key -> value mapping from the map, failing if combining the
+    /// annotations fails.
+    ///
+    /// Returns the previously mapped value, if any. On failure the removal
+    /// is rolled back, so the map keeps its previous mappings.
+    pub fn remove_checked(&mut self, k: &K) -> Result<Option<V>, MapError>
+    where
+        K: Clone,
+    {
+        let old = self.remove(k)?;
+
+        if let Err(e) = self.check_annotation() {
+            if let Some(old) = old {
+                self.insert(k.clone(), old)?;
+            }
+
+            return Err(e);
+        }
+
+        Ok(old)
+    }
+}
@@ -0,0 +1,109 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use alloc::vec::Vec;
+
+use canonical::{Canon, CanonError, Id};
+use canonical_derive::Canon;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Canon)]
+/// Large value split in chunks of canonical blobs.
+///
+/// Only the identifiers of the chunks are part of the encoding of the value,
+/// so decoding a `ChunkedValue` from the map is cheap regardless of its size.
+/// The bytes of a chunk are fetched from the store on demand.
+pub struct ChunkedValue {
+    len: u64,
+    chunks: Vec<Id>,
+}
+
+impl ChunkedValue {
+    /// Split `bytes` in chunks of, at most, `chunk_size` bytes
+    ///
+    /// # Panics
+    ///
+    /// If `chunk_size` is zero
+    pub fn new(bytes: &[u8], chunk_size: usize) -> Self {
+        let chunks = bytes
+            .chunks(chunk_size)
+            .map(|chunk| Id::new(&Vec::from(chunk)))
+            .collect();
+
+        Self {
+            len: bytes.len() as u64,
+            chunks,
+        }
+    }
+
+    /// Total length of the value, in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Check if the value is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of chunks of the value
+    pub fn chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Fetch the bytes of the chunk `idx`
+    ///
+    /// Will return `Ok(None)` if the chunk doesn't exist.
+    pub fn chunk(&self, idx: usize) -> Result<Option<Vec<u8>>, CanonError> {
+        self.chunks.get(idx).map(|id| id.reify()).transpose()
+    }
+
+    /// Fetch and concatenate all the chunks of the value
+    ///
+    /// The buffer grows as the chunks are fetched, rather than trusting the
+    /// decoded length of the value. Will return
+    /// [`CanonError::InvalidEncoding`] if the chunks don't add up to it.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CanonError> {
+        let mut bytes = Vec::new();
+
+        for id in self.chunks.iter() {
+            bytes.extend(id.reify::<Vec<u8>>()?);
+
+            if bytes.len() as u64 > self.len {
+                return Err(CanonError::InvalidEncoding);
+            }
+        }
+
+        if bytes.len() as u64 != self.len {
+            return Err(CanonError::InvalidEncoding);
+        }
+
+        Ok(bytes)
+    }
+}
+
+impl<K, A> KelvinMap<K, ChunkedValue, A>
+where
+    K: Canon + Ord,
+    A: MapAnnotation<K, ChunkedValue>,
+{
+    /// Returns the bytes of the chunk `idx` of the value corresponding to the
+    /// key
+    ///
+    /// Only the requested chunk is fetched from the store. Will return
+    /// `Ok(None)` if no correspondent key or chunk was found.
+    pub fn get_chunk(
+        &self,
+        k: &K,
+        idx: usize,
+    ) -> Result<Option<Vec<u8>>, CanonError> {
+        match self.get(k)? {
+            Some(value) => value.chunk(idx),
+            None => Ok(None),
+        }
+    }
+}
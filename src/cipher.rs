@@ -0,0 +1,167 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::Map;
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use canonical::{Canon, CanonError, EncodeToVec, Source};
+use canonical_derive::Canon;
+
+/// Encryption of the values of a [`CipherMap`], keyed by the host.
+///
+/// The encoded key of the map is passed as associated data, so a ciphertext
+/// is only valid under the key it was sealed for.
+pub trait ValueCipher {
+    /// Encrypt the canonical encoding of a value
+    fn encrypt(&self, key: &[u8], plaintext: &[u8]) -> Vec<u8>;
+
+    /// Decrypt a ciphertext created by [`ValueCipher::encrypt`], failing if
+    /// it was not sealed for `key` with the same cipher key
+    fn decrypt(
+        &self,
+        key: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, CanonError>;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Canon)]
+/// Encrypted value of a [`CipherMap`], as persisted in the tree
+pub struct Sealed(Vec<u8>);
+
+impl Sealed {
+    /// Bytes of the ciphertext
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Decrypted value of a [`CipherMap`], dereferencing to the plaintext.
+///
+/// The tree only holds the ciphertexts, so the guard owns the value decrypted
+/// out of them rather than borrowing a leaf.
+pub struct Decrypted<V>(V);
+
+impl<V> Decrypted<V> {
+    /// Take the decrypted value out of the guard
+    pub fn into_inner(self) -> V {
+        self.0
+    }
+}
+
+impl<V> Deref for Decrypted<V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Map storing its values encrypted at rest.
+///
+/// Only the [`Sealed`] map is persisted and committed to; the cipher, holding
+/// the key provided by the host, is not. Values are decrypted on access into
+/// [`Decrypted`] guards.
+pub struct CipherMap<K, V, C>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+    C: ValueCipher,
+{
+    sealed: Map<K, Sealed>,
+    cipher: C,
+    _marker: PhantomData<V>,
+}
+
+impl<K, V, C> CipherMap<K, V, C>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+    C: ValueCipher,
+{
+    /// Create a new empty map encrypting its values with `cipher`
+    pub fn new(cipher: C) -> Self {
+        Self::from_sealed(Map::default(), cipher)
+    }
+
+    /// Open a persisted map of sealed values with `cipher`
+    pub fn from_sealed(sealed: Map<K, Sealed>, cipher: C) -> Self {
+        Self {
+            sealed,
+            cipher,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Underlying map of the sealed values, to be persisted
+    pub fn sealed(&self) -> &Map<K, Sealed> {
+        &self.sealed
+    }
+
+    /// Discard the cipher and return the map of the sealed values
+    pub fn into_sealed(self) -> Map<K, Sealed> {
+        self.sealed
+    }
+
+    /// Number of entries of the map
+    pub fn len(&self) -> usize {
+        self.sealed.len()
+    }
+
+    /// Check if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.sealed.is_empty()
+    }
+
+    /// Check if the map contains a mapping for the key, without decrypting
+    /// its value
+    pub fn contains_key(&self, k: &K) -> Result<bool, CanonError> {
+        self.sealed.contains_key(k)
+    }
+
+    /// Returns the decrypted value corresponding to the key
+    pub fn get(&self, k: &K) -> Result<Option<Decrypted<V>>, CanonError> {
+        match self.sealed.get(k)? {
+            Some(sealed) => self.open(k, &sealed).map(|v| Some(Decrypted(v))),
+            None => Ok(None),
+        }
+    }
+
+    /// Include a key -> value mapping to the map, encrypting the value.
+    ///
+    /// Returns the decrypted previous value, if any.
+    pub fn insert(&mut self, k: K, v: V) -> Result<Option<V>, CanonError> {
+        let sealed = self.seal(&k, &v);
+
+        match self.sealed.insert(k.clone(), sealed)? {
+            Some(previous) => self.open(&k, &previous).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove a key -> value mapping from the map, returning the decrypted
+    /// removed value, if any
+    pub fn remove(&mut self, k: &K) -> Result<Option<V>, CanonError> {
+        match self.sealed.remove(k)? {
+            Some(removed) => self.open(k, &removed).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn seal(&self, k: &K, v: &V) -> Sealed {
+        Sealed(self.cipher.encrypt(&k.encode_to_vec(), &v.encode_to_vec()))
+    }
+
+    fn open(&self, k: &K, sealed: &Sealed) -> Result<V, CanonError> {
+        let bytes = self.cipher.decrypt(&k.encode_to_vec(), &sealed.0)?;
+
+        V::decode(&mut Source::new(&bytes))
+    }
+}
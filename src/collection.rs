@@ -0,0 +1,166 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{
+    ConfiguredMap, KelvinLinkedMap, KelvinMap, MapAnnotation, MapError,
+};
+
+use alloc::boxed::Box;
+
+use canonical::{Canon, CanonError};
+
+/// Iterator over the entries of a [`KelvinCollection`]
+pub type CollectionIter<'a, K, V, E> =
+    Box<dyn Iterator<Item = Result<(K, V), E>> + 'a>;
+
+/// Map-like collection, for code generic over the concrete collection
+/// backing its state.
+///
+/// The values are returned as copies, so every implementation can be used
+/// behind the same signatures regardless of how it stores them.
+pub trait KelvinCollection<K, V> {
+    /// Error of the operations of the collection
+    type Error: From<CanonError>;
+
+    /// Returns the value corresponding to the key, if present
+    fn get(&self, k: &K) -> Result<Option<V>, Self::Error>;
+
+    /// Include a key -> value mapping, returning the previous value of the
+    /// key, if any
+    fn insert(&mut self, k: K, v: V) -> Result<Option<V>, Self::Error>;
+
+    /// Remove the mapping of the key, returning its value, if any
+    fn remove(&mut self, k: &K) -> Result<Option<V>, Self::Error>;
+
+    /// Returns the number of entries of the collection
+    fn len(&self) -> usize;
+
+    /// Check if the collection is empty
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate the entries of the collection, in the natural order of the
+    /// collection
+    fn iter<'a>(
+        &'a self,
+    ) -> Result<CollectionIter<'a, K, V, Self::Error>, Self::Error>
+    where
+        K: 'a,
+        V: 'a;
+}
+
+impl<K, V, A> KelvinCollection<K, V> for KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    type Error = CanonError;
+
+    fn get(&self, k: &K) -> Result<Option<V>, CanonError> {
+        Ok(KelvinMap::get(self, k)?.map(|v| v.clone()))
+    }
+
+    fn insert(&mut self, k: K, v: V) -> Result<Option<V>, CanonError> {
+        KelvinMap::insert(self, k, v)
+    }
+
+    fn remove(&mut self, k: &K) -> Result<Option<V>, CanonError> {
+        KelvinMap::remove(self, k)
+    }
+
+    fn len(&self) -> usize {
+        KelvinMap::len(self)
+    }
+
+    fn iter<'a>(
+        &'a self,
+    ) -> Result<CollectionIter<'a, K, V, CanonError>, CanonError>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        Ok(Box::new(
+            KelvinMap::iter(self)?
+                .map(|entry| entry.map(|(k, v)| (k.clone(), v.clone()))),
+        ))
+    }
+}
+
+impl<K, V, A> KelvinCollection<K, V> for ConfiguredMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    type Error = MapError;
+
+    fn get(&self, k: &K) -> Result<Option<V>, MapError> {
+        Ok(ConfiguredMap::get(self, k)?.map(|v| v.clone()))
+    }
+
+    fn insert(&mut self, k: K, v: V) -> Result<Option<V>, MapError> {
+        ConfiguredMap::insert(self, k, v)
+    }
+
+    fn remove(&mut self, k: &K) -> Result<Option<V>, MapError> {
+        ConfiguredMap::remove(self, k)
+    }
+
+    fn len(&self) -> usize {
+        KelvinMap::len(self)
+    }
+
+    fn iter<'a>(
+        &'a self,
+    ) -> Result<CollectionIter<'a, K, V, MapError>, MapError>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        let iter = KelvinCollection::iter(&**self)?;
+
+        Ok(Box::new(iter.map(|entry| entry.map_err(MapError::from))))
+    }
+}
+
+impl<K, V> KelvinCollection<K, V> for KelvinLinkedMap<K, V>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+{
+    type Error = CanonError;
+
+    fn get(&self, k: &K) -> Result<Option<V>, CanonError> {
+        Ok(KelvinLinkedMap::get(self, k)?.map(|v| v.clone()))
+    }
+
+    fn insert(&mut self, k: K, v: V) -> Result<Option<V>, CanonError> {
+        KelvinLinkedMap::insert(self, k, v)
+    }
+
+    fn remove(&mut self, k: &K) -> Result<Option<V>, CanonError> {
+        KelvinLinkedMap::remove(self, k)
+    }
+
+    fn len(&self) -> usize {
+        KelvinLinkedMap::len(self)
+    }
+
+    fn iter<'a>(
+        &'a self,
+    ) -> Result<CollectionIter<'a, K, V, CanonError>, CanonError>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        Ok(Box::new(
+            KelvinLinkedMap::iter_by_insertion(self)?
+                .map(|entry| entry.map(|(k, v)| (k.clone(), v))),
+        ))
+    }
+}
@@ -0,0 +1,243 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation, MapError, ShrinkReport};
+
+use core::ops::Deref;
+
+use canonical::{Canon, CanonError, Sink, Source};
+use canonical_derive::Canon;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Canon)]
+/// Defines when the naive balancing of the map is performed
+pub enum BalancePolicy {
+    /// Balance the map before every insert / remove
+    #[default]
+    OnWrite,
+    /// Never balance the map implicitly
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Canon)]
+/// Tuning knobs of a [`ConfiguredMap`], persisted alongside its root
+pub struct MapConfig {
+    /// Maximum difference of cardinality between the left and right children
+    /// of the root before a balancing step is performed
+    pub balance_tolerance: u64,
+    /// Maximum depth of the tree, enforced by [`ConfiguredMap::validate`]
+    /// and [`ConfiguredMap::shrink`]
+    pub max_depth: u32,
+    /// Maximum encoded length, in bytes, of a node expected to be inlined in
+    /// the identifier of its parent instead of stored on its own
+    pub inline_threshold: u32,
+    /// Balancing policy applied on writes
+    pub policy: BalancePolicy,
+    /// Maximum encoded length, in bytes, of an inserted value, if any
+    pub max_value_size: Option<u32>,
+    /// Forbid `insert` from replacing the value of an existing key, which
+    /// then requires an explicit [`ConfiguredMap::overwrite`]
+    pub strict: bool,
+}
+
+impl MapConfig {
+    /// Default difference of cardinality tolerated between the children of
+    /// the root
+    pub const DEFAULT_BALANCE_TOLERANCE: u64 = 1;
+    /// Default maximum depth of the tree
+    pub const DEFAULT_MAX_DEPTH: u32 = 128;
+    /// Largest inlined payload supported by the storage backend
+    pub const MAX_INLINE_THRESHOLD: u32 = 32;
+
+    /// Check the configuration knobs are within their supported bounds
+    pub fn validate(&self) -> Result<(), MapError> {
+        if self.balance_tolerance == 0
+            || self.max_depth == 0
+            || self.inline_threshold > Self::MAX_INLINE_THRESHOLD
+        {
+            return Err(MapError::InvalidConfig);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        Self {
+            balance_tolerance: Self::DEFAULT_BALANCE_TOLERANCE,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            inline_threshold: Self::MAX_INLINE_THRESHOLD,
+            policy: BalancePolicy::default(),
+            max_value_size: None,
+            strict: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`KelvinMap`] bundled with the [`MapConfig`] that drives its mutations.
+///
+/// The configuration is encoded before the root of the map, and validated
+/// again when the map is decoded.
+///
+/// Read access to the underlying map is provided via `Deref`.
+pub struct ConfiguredMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    config: MapConfig,
+    map: KelvinMap<K, V, A>,
+}
+
+impl<K, V, A> ConfiguredMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Create a new empty map with the provided configuration
+    pub fn new(config: MapConfig) -> Result<Self, MapError> {
+        config.validate()?;
+
+        Ok(Self {
+            config,
+            map: KelvinMap::default(),
+        })
+    }
+
+    /// Configuration of the map
+    pub fn config(&self) -> &MapConfig {
+        &self.config
+    }
+
+    /// Discard the configuration and return the underlying map
+    pub fn into_inner(self) -> KelvinMap<K, V, A> {
+        self.map
+    }
+
+    /// Returns a reference to the value corresponding to the key
+    ///
+    /// See [`KelvinMap::get`].
+    pub fn get<'a>(
+        &'a self,
+        k: &K,
+    ) -> Result<Option<impl Deref<Target = V> + 'a>, CanonError> {
+        self.map.get(k)
+    }
+
+    /// Verify the whole tree as [`KelvinMap::validate`], along with its
+    /// depth.
+    ///
+    /// Will return [`MapError::TooDeep`] if the tree is deeper than the
+    /// configured maximum depth.
+    pub fn validate(&self) -> Result<(), MapError> {
+        self.map.validate()?;
+
+        if self.map.depth()? > self.config.max_depth as usize {
+            return Err(MapError::TooDeep);
+        }
+
+        Ok(())
+    }
+
+    /// Compact the tree as [`KelvinMap::shrink`], also rebuilding the
+    /// sub-trees deeper than the configured maximum depth
+    pub fn shrink(&mut self) -> Result<ShrinkReport, CanonError> {
+        self.map.shrink_within(self.config.max_depth as usize)
+    }
+
+    fn balance(&mut self) -> Result<(), CanonError> {
+        match self.config.policy {
+            BalancePolicy::OnWrite => {
+                self.map.balance_with(self.config.balance_tolerance)
+            }
+            BalancePolicy::Manual => Ok(()),
+        }
+    }
+
+    /// Include a key -> value mapping to the map, balancing according to the
+    /// configuration.
+    ///
+    /// Returns the previously mapped value, if any. Will return
+    /// [`MapError::ValueTooLarge`], leaving the map untouched, if the encoded
+    /// value exceeds the configured maximum size, and
+    /// [`MapError::KeyExists`] if the key is already mapped in strict mode.
+    pub fn insert(&mut self, k: K, v: V) -> Result<Option<V>, MapError> {
+        if self.config.strict && self.map.contains_key(&k)? {
+            return Err(MapError::KeyExists);
+        }
+
+        self.overwrite(k, v)
+    }
+
+    /// Include a key -> value mapping to the map, replacing the value of an
+    /// existing key even in strict mode.
+    ///
+    /// Returns the previously mapped value, if any. Will return
+    /// [`MapError::ValueTooLarge`], leaving the map untouched, if the encoded
+    /// value exceeds the configured maximum size.
+    pub fn overwrite(&mut self, k: K, v: V) -> Result<Option<V>, MapError> {
+        if let Some(max) = self.config.max_value_size {
+            if v.encoded_len() > max as usize {
+                return Err(MapError::ValueTooLarge);
+            }
+        }
+
+        self.balance()?;
+
+        Ok(self.map._insert(Leaf::new(k, v))?)
+    }
+
+    /// Remove a key -> value mapping from the map, balancing according to the
+    /// configuration.
+    ///
+    /// Returns the previously mapped value, if any.
+    pub fn remove(&mut self, k: &K) -> Result<Option<V>, MapError> {
+        self.balance()?;
+
+        Ok(self.map._remove(k)?)
+    }
+}
+
+impl<K, V, A> Deref for ConfiguredMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    type Target = KelvinMap<K, V, A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, A> Canon for ConfiguredMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn encode(&self, sink: &mut Sink) {
+        self.config.encode(sink);
+        self.map.encode(sink);
+    }
+
+    fn decode(source: &mut Source) -> Result<Self, CanonError> {
+        let config = MapConfig::decode(source)?;
+        config.validate().map_err(|_| CanonError::InvalidEncoding)?;
+
+        let map = KelvinMap::decode(source)?;
+
+        Ok(Self { config, map })
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.config.encoded_len() + self.map.encoded_len()
+    }
+}
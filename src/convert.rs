@@ -0,0 +1,40 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use canonical::{Canon, CanonError};
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Create a copy of the map under the annotation type `A2`, such as a
+    /// [`MapAnnotationHashed`](crate::MapAnnotationHashed) or a
+    /// [`MapAnnotationSum`](crate::MapAnnotationSum).
+    ///
+    /// The shape of the tree and its leaves are kept, and only the
+    /// annotations are recomputed, in a single traversal.
+    pub fn convert_annotation<A2>(
+        &self,
+    ) -> Result<KelvinMap<K, V, A2>, CanonError>
+    where
+        A2: MapAnnotation<K, V>,
+    {
+        match self {
+            KelvinMap::Empty => Ok(KelvinMap::Empty),
+            KelvinMap::Leaf(l) => Ok(KelvinMap::Leaf(l.clone())),
+            KelvinMap::Node(n) => {
+                let l = n.left().val()?.convert_annotation()?;
+                let r = n.right().val()?.convert_annotation()?;
+
+                Ok(KelvinMap::node(l, r))
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+//! Helpers that deterministically break the invariants of a map at its root,
+//! to test the handling of corrupted state.
+//!
+//! Every helper returns `Ok(false)`, leaving the map untouched, if the root is
+//! not a node.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use alloc::vec::Vec;
+use core::mem;
+
+use canonical::{Canon, CanonError, EncodeToVec, Source};
+
+/// Store the annotation of the right child of the root along its left child,
+/// so the cached annotation no longer matches the left child
+pub fn flip_annotation<K, V, A>(
+    map: &mut KelvinMap<K, V, A>,
+) -> Result<bool, CanonError>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    let (l, r) = match map {
+        KelvinMap::Node(n) => n.children(),
+        _ => return Ok(false),
+    };
+
+    // The annotation is encoded after the reference to the child
+    let left = l.encode_to_vec();
+    let repr_len = left.len() - l.annotation().encoded_len();
+
+    let mut bytes = Vec::with_capacity(1 + left.len() + r.encoded_len());
+    bytes.push(2);
+    bytes.extend_from_slice(&left[..repr_len]);
+    bytes.extend(r.annotation().encode_to_vec());
+    bytes.extend(r.encode_to_vec());
+
+    *map = KelvinMap::decode(&mut Source::new(&bytes))?;
+
+    Ok(true)
+}
+
+/// Swap the children of the root, so its keys are no longer in ascending
+/// order
+pub fn swap_children<K, V, A>(
+    map: &mut KelvinMap<K, V, A>,
+) -> Result<bool, CanonError>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    match map {
+        KelvinMap::Node(n) => {
+            let (l, r) = n.children_mut();
+            mem::swap(l, r);
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Insert a copy of the largest leaf of the left child of the root as the
+/// smallest leaf of its right child, so the key is mapped twice
+pub fn duplicate_key<K, V, A>(
+    map: &mut KelvinMap<K, V, A>,
+) -> Result<bool, CanonError>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    let (l, r) = match map {
+        KelvinMap::Node(n) => n.children(),
+        _ => return Ok(false),
+    };
+
+    let leaf = match l.val()?.max_key_leaf()? {
+        Some(leaf) => KelvinMap::Leaf(leaf),
+        None => return Ok(false),
+    };
+
+    let right = KelvinMap::node(leaf, r.val()?.clone());
+    let left = l.val()?.clone();
+    *map = KelvinMap::node(left, right);
+
+    Ok(true)
+}
@@ -0,0 +1,169 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::map::{ValRef, WalkExt};
+use crate::{KelvinMap, MapAnnotation};
+
+use core::ops::{BitAnd, BitOr, Deref, Not};
+
+use canonical::{Canon, CanonError};
+use microkelvin::{Branch, MaxKey, Step, Walk, Walker};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Result of a constant-time comparison, either `0` or `1`.
+///
+/// It should only be converted into a `bool` once the sensitive computation
+/// is done.
+pub struct Choice(u8);
+
+impl Choice {
+    /// Underlying value, either `0` or `1`
+    pub fn unwrap_u8(&self) -> u8 {
+        self.0
+    }
+}
+
+impl From<Choice> for bool {
+    fn from(c: Choice) -> Self {
+        c.0 == 1
+    }
+}
+
+impl BitAnd for Choice {
+    type Output = Choice;
+
+    fn bitand(self, rhs: Choice) -> Choice {
+        Choice(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for Choice {
+    type Output = Choice;
+
+    fn bitor(self, rhs: Choice) -> Choice {
+        Choice(self.0 | rhs.0)
+    }
+}
+
+impl Not for Choice {
+    type Output = Choice;
+
+    fn not(self) -> Choice {
+        Choice(self.0 ^ 1)
+    }
+}
+
+fn byte_eq(a: u8, b: u8) -> Choice {
+    Choice((((a ^ b) as u16).wrapping_sub(1) >> 8) as u8 & 1)
+}
+
+fn byte_gt(a: u8, b: u8) -> Choice {
+    Choice(((b as u16).wrapping_sub(a as u16) >> 8) as u8 & 1)
+}
+
+fn bytes_eq(a: &[u8], b: &[u8]) -> Choice {
+    a.iter()
+        .zip(b.iter())
+        .fold(Choice(1), |eq, (a, b)| eq & byte_eq(*a, *b))
+}
+
+/// Big-endian comparison of two byte slices of the same length
+fn bytes_gt(a: &[u8], b: &[u8]) -> Choice {
+    let (gt, _) = a
+        .iter()
+        .zip(b.iter())
+        .fold((Choice(0), Choice(1)), |(gt, eq), (a, b)| {
+            (gt | (eq & byte_gt(*a, *b)), eq & byte_eq(*a, *b))
+        });
+
+    gt
+}
+
+/// Fixed-size key that can be compared in constant time.
+///
+/// The comparisons must be consistent with the `Ord` implementation of the
+/// key.
+pub trait ConstantTimeKey: Ord {
+    /// Constant-time equality
+    fn ct_eq(&self, other: &Self) -> Choice;
+
+    /// Constant-time `self > other`
+    fn ct_gt(&self, other: &Self) -> Choice;
+}
+
+impl<const N: usize> ConstantTimeKey for [u8; N] {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        bytes_eq(self, other)
+    }
+
+    fn ct_gt(&self, other: &Self) -> Choice {
+        bytes_gt(self, other)
+    }
+}
+
+impl ConstantTimeKey for u64 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        bytes_eq(&self.to_be_bytes(), &other.to_be_bytes())
+    }
+
+    fn ct_gt(&self, other: &Self) -> Choice {
+        bytes_gt(&self.to_be_bytes(), &other.to_be_bytes())
+    }
+}
+
+/// Walker that always descends to a leaf, selecting the path with
+/// constant-time comparisons
+struct ConstantTimeWalker<'a, K>(&'a K);
+
+impl<'a, K, V, A> Walker<KelvinMap<K, V, A>, A> for ConstantTimeWalker<'a, K>
+where
+    K: Canon + ConstantTimeKey,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn walk(&mut self, walk: Walk<KelvinMap<K, V, A>, A>) -> Step {
+        let go_right = match (walk.as_leaf(0), walk.as_node_annotation(0)) {
+            (Some(l), _) => self.0.ct_gt(l._key()),
+            (_, Some(a)) => match a.borrow() {
+                MaxKey::Maximum(max) => self.0.ct_gt(max),
+                MaxKey::NegativeInfinity => Choice(1),
+            },
+            (None, None) => return Step::Abort,
+        };
+
+        let ofs = go_right.unwrap_u8() as usize;
+        match (walk.as_leaf(ofs), walk.as_node_annotation(ofs)) {
+            (Some(l), _) if bool::from(self.0.ct_eq(l._key())) => {
+                Step::Found(ofs)
+            }
+            (_, Some(_)) => Step::Into(ofs),
+            _ => Step::Abort,
+        }
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + ConstantTimeKey,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Returns a reference to the value corresponding to the key, comparing
+    /// keys in constant time.
+    ///
+    /// Unlike [`KelvinMap::get`], the walk never aborts early: it always
+    /// descends to a leaf, so the time of the lookup depends on the depth of
+    /// the reached leaf but not on the result of the key comparisons.
+    ///
+    /// Will return `Ok(None)` if no correspondent key was found.
+    pub fn get_ct<'a>(
+        &'a self,
+        k: &K,
+    ) -> Result<Option<impl Deref<Target = V> + 'a>, CanonError> {
+        Branch::walk(self, ConstantTimeWalker(k))
+            .map(|result| result.map(|branch| ValRef(branch)))
+    }
+}
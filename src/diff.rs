@@ -0,0 +1,312 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation, MapHash, MapHasher};
+
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp;
+use core::ops::{Bound, RangeBounds};
+
+use canonical::{Canon, CanonError};
+use microkelvin::{Annotated, Combine, MaxKey};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Change of an entry between two versions of a map
+pub enum Change<'a, K, V> {
+    /// The key is only present in the new version
+    Added(&'a K, &'a V),
+    /// The key is only present in the old version
+    Removed(&'a K, &'a V),
+    /// The key is present in both versions, mapped to the old and the new
+    /// values respectively
+    Modified(&'a K, &'a V, &'a V),
+}
+
+/// Largest key of the left child of a node, splitting its key space
+pub(crate) fn split_key<K, V, A>(
+    left: &Annotated<KelvinMap<K, V, A>, A>,
+) -> Option<&K>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    let max: &MaxKey<K> = left.annotation().borrow();
+
+    match max {
+        MaxKey::Maximum(k) => Some(k),
+        MaxKey::NegativeInfinity => None,
+    }
+}
+
+/// Check if the keys up to `split` (inclusive) are all out of `range`
+pub(crate) fn skip_left<K, R>(range: &R, split: &K) -> bool
+where
+    K: Ord,
+    R: RangeBounds<K>,
+{
+    match range.start_bound() {
+        Bound::Included(s) => s > split,
+        Bound::Excluded(s) => s >= split,
+        Bound::Unbounded => false,
+    }
+}
+
+/// Check if the keys after `split` (exclusive) are all out of `range`
+pub(crate) fn skip_right<K, R>(range: &R, split: &K) -> bool
+where
+    K: Ord,
+    R: RangeBounds<K>,
+{
+    match range.end_bound() {
+        Bound::Included(e) | Bound::Excluded(e) => e <= split,
+        Bound::Unbounded => false,
+    }
+}
+
+/// Restrict the end `bound` of a range to the keys up to `split` (inclusive)
+fn bound_left<'a, K>(bound: Bound<&'a K>, split: &'a K) -> Bound<&'a K>
+where
+    K: Ord,
+{
+    match bound {
+        Bound::Included(e) | Bound::Excluded(e) if e <= split => bound,
+        _ => Bound::Included(split),
+    }
+}
+
+/// Restrict the start `bound` of a range to the keys after `split`
+/// (exclusive)
+fn bound_right<'a, K>(bound: Bound<&'a K>, split: &'a K) -> Bound<&'a K>
+where
+    K: Ord,
+{
+    match bound {
+        Bound::Included(s) if s > split => bound,
+        Bound::Excluded(s) if s >= split => bound,
+        _ => Bound::Excluded(split),
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Push clones of the leaves within `range` in key order, skipping the
+    /// sub-trees out of it
+    pub(crate) fn collect_range<R>(
+        &self,
+        range: &R,
+        leaves: &mut Vec<Leaf<K, V>>,
+    ) -> Result<(), CanonError>
+    where
+        R: RangeBounds<K>,
+    {
+        match self {
+            KelvinMap::Empty => Ok(()),
+            KelvinMap::Leaf(l) => {
+                if range.contains(l._key()) {
+                    leaves.push(l.clone());
+                }
+
+                Ok(())
+            }
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let split = split_key(l);
+
+                if !split.map(|s| skip_left(range, s)).unwrap_or(false) {
+                    l.val()?.collect_range(range, leaves)?;
+                }
+
+                if !split.map(|s| skip_right(range, s)).unwrap_or(false) {
+                    r.val()?.collect_range(range, leaves)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Visit the differing leaves of `old` and `new` within `range`, in key
+    /// order, until `f` returns `false`.
+    ///
+    /// Pairs of nodes with the same hash annotation hold the same entries, and
+    /// are skipped. Otherwise, the larger side is split at the largest key of
+    /// its left child, and each of its children is compared against the other
+    /// side restricted to the same key range, so the sub-trees shared by both
+    /// versions are skipped even if the trees are shaped differently.
+    ///
+    /// Returns `Ok(false)` if the visit was interrupted by `f`.
+    pub(crate) fn diff_range<H, F>(
+        old: &Self,
+        new: &Self,
+        range: (Bound<&K>, Bound<&K>),
+        f: &mut F,
+    ) -> Result<bool, CanonError>
+    where
+        A: Borrow<MapHash<H>>,
+        H: MapHasher,
+        F: FnMut(Option<&Leaf<K, V>>, Option<&Leaf<K, V>>) -> bool,
+    {
+        let split_old = match (old, new) {
+            (KelvinMap::Node(o), KelvinMap::Node(n)) => {
+                if MapHash::<H>::combine(old) == MapHash::<H>::combine(new) {
+                    return Ok(true);
+                }
+
+                o.cardinality() >= n.cardinality()
+            }
+            (KelvinMap::Node(_), _) => true,
+            (_, KelvinMap::Node(_)) => false,
+            _ => return Ok(Self::diff_leaves::<H, _>(old, new, &range, f)),
+        };
+
+        let node = if split_old { old } else { new };
+        let (l, r) = match node {
+            KelvinMap::Node(n) => n.children(),
+            _ => return Ok(true),
+        };
+
+        let split = match split_key(l) {
+            Some(s) => s,
+            None => {
+                let r = r.val()?;
+                return if split_old {
+                    Self::diff_range(&*r, new, range, f)
+                } else {
+                    Self::diff_range(old, &*r, range, f)
+                };
+            }
+        };
+
+        if !skip_left(&range, split) {
+            let range = (range.0, bound_left(range.1, split));
+            let l = l.val()?;
+            let proceed = if split_old {
+                Self::diff_range(&*l, new, range, f)?
+            } else {
+                Self::diff_range(old, &*l, range, f)?
+            };
+
+            if !proceed {
+                return Ok(false);
+            }
+        }
+
+        if !skip_right(&range, split) {
+            let range = (bound_right(range.0, split), range.1);
+            let r = r.val()?;
+            return if split_old {
+                Self::diff_range(&*r, new, range, f)
+            } else {
+                Self::diff_range(old, &*r, range, f)
+            };
+        }
+
+        Ok(true)
+    }
+
+    /// Leaf of a single entry map, if within `range`
+    fn leaf_within<R>(&self, range: &R) -> Option<&Leaf<K, V>>
+    where
+        R: RangeBounds<K>,
+    {
+        match self {
+            KelvinMap::Leaf(l) if range.contains(l._key()) => Some(l),
+            _ => None,
+        }
+    }
+
+    /// Visit the differing leaves within `range` of two maps that are either
+    /// empty or a single leaf
+    fn diff_leaves<H, F>(
+        old: &Self,
+        new: &Self,
+        range: &(Bound<&K>, Bound<&K>),
+        f: &mut F,
+    ) -> bool
+    where
+        H: MapHasher,
+        F: FnMut(Option<&Leaf<K, V>>, Option<&Leaf<K, V>>) -> bool,
+    {
+        match (old.leaf_within(range), new.leaf_within(range)) {
+            (Some(o), Some(n)) => match o._key().cmp(n._key()) {
+                cmp::Ordering::Less => f(Some(o), None) && f(None, Some(n)),
+                cmp::Ordering::Greater => f(None, Some(n)) && f(Some(o), None),
+                cmp::Ordering::Equal if o.hash::<H>() == n.hash::<H>() => true,
+                cmp::Ordering::Equal => f(Some(o), Some(n)),
+            },
+            (Some(o), None) => f(Some(o), None),
+            (None, Some(n)) => f(None, Some(n)),
+            (None, None) => true,
+        }
+    }
+
+    /// Check if any entry within `range` was added, removed or modified
+    /// between the `old` and the `new` versions of a map.
+    ///
+    /// The sub-trees left untouched between the versions are detected via
+    /// their [`MapHash`] annotation and skipped, so only the modified paths
+    /// are traversed.
+    pub fn changed_in_range<H, R>(
+        old: &Self,
+        new: &Self,
+        range: R,
+    ) -> Result<bool, CanonError>
+    where
+        A: Borrow<MapHash<H>>,
+        H: MapHasher,
+        R: RangeBounds<K>,
+    {
+        if MapHash::<H>::combine(old) == MapHash::<H>::combine(new) {
+            return Ok(false);
+        }
+
+        let range = (range.start_bound(), range.end_bound());
+        Self::diff_range::<H, _>(old, new, range, &mut |_, _| false)
+            .map(|complete| !complete)
+    }
+
+    /// Visit the entries that were added, removed or modified between the
+    /// `old` and the `new` versions of a map, in key order.
+    ///
+    /// As with [`KelvinMap::changed_in_range`], the sub-trees shared by both
+    /// versions are skipped, so the cost grows with the size of the change
+    /// and the depth of the maps rather than the size of the maps.
+    pub fn for_each_change<H, F>(
+        old: &Self,
+        new: &Self,
+        mut f: F,
+    ) -> Result<(), CanonError>
+    where
+        A: Borrow<MapHash<H>>,
+        H: MapHasher,
+        F: FnMut(Change<'_, K, V>),
+    {
+        if MapHash::<H>::combine(old) == MapHash::<H>::combine(new) {
+            return Ok(());
+        }
+
+        let range = (Bound::Unbounded, Bound::Unbounded);
+        Self::diff_range::<H, _>(old, new, range, &mut |o, n| {
+            match (o, n) {
+                (Some(o), Some(n)) => {
+                    f(Change::Modified(o._key(), o.value(), n.value()))
+                }
+                (Some(o), None) => f(Change::Removed(o._key(), o.value())),
+                (None, Some(n)) => f(Change::Added(n._key(), n.value())),
+                (None, None) => (),
+            }
+
+            true
+        })
+        .map(|_| ())
+    }
+}
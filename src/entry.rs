@@ -0,0 +1,219 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::bounded::BoundedWalker;
+use crate::map::ValRefMut;
+use crate::{KelvinMap, MapAnnotation};
+
+use core::ops::DerefMut;
+
+use canonical::{Canon, CanonError};
+use microkelvin::BranchMut;
+
+/// Mutable reference to the value of `k`, which is known to be in the map
+fn value_mut<'a, K, V, A>(
+    map: &'a mut KelvinMap<K, V, A>,
+    k: &K,
+) -> Result<ValRefMut<'a, K, V, A>, CanonError>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    BranchMut::walk(map, BoundedWalker::key(k))?
+        .map(ValRefMut)
+        .ok_or(CanonError::NotFound)
+}
+
+/// View into a single key of a map, which may be either vacant or occupied,
+/// created by [`KelvinMap::entry`].
+///
+/// An occupied entry keeps the path walked by the lookup creating it, so its
+/// value is read and written without walking the tree again. Inserting into a
+/// vacant entry walks the tree once more, and an occupied entry is removed
+/// with [`KelvinMap::remove`] once dropped.
+pub enum Entry<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// The key is mapped to a value
+    Occupied(OccupiedEntry<'a, K, V, A>),
+    /// The key is not in the map
+    Vacant(VacantEntry<'a, K, V, A>),
+}
+
+/// Key of a map mapped to a value
+pub struct OccupiedEntry<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    value: ValRefMut<'a, K, V, A>,
+    key: K,
+}
+
+/// Key of a map not mapped to any value
+pub struct VacantEntry<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    map: &'a mut KelvinMap<K, V, A>,
+    key: K,
+}
+
+impl<'a, K, V, A> Entry<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Key of the entry
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(e) => e.key(),
+            Entry::Vacant(e) => e.key(),
+        }
+    }
+
+    /// Map the key to `default` if vacant, and return a mutable reference to
+    /// its value
+    pub fn or_insert(
+        self,
+        default: V,
+    ) -> Result<impl DerefMut<Target = V> + 'a, CanonError> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Map the key to the result of `default` if vacant, and return a
+    /// mutable reference to its value
+    pub fn or_insert_with<F>(
+        self,
+        default: F,
+    ) -> Result<impl DerefMut<Target = V> + 'a, CanonError>
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(e) => Ok(e.value),
+            Entry::Vacant(e) => e.insert_value(default()),
+        }
+    }
+
+    /// Map the key to the default value if vacant, and return a mutable
+    /// reference to its value
+    pub fn or_default(
+        self,
+    ) -> Result<impl DerefMut<Target = V> + 'a, CanonError>
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Call `f` with the value of the key, if occupied
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(e) = &mut self {
+            f(e.get_mut());
+        }
+
+        self
+    }
+}
+
+impl<'a, K, V, A> OccupiedEntry<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Key of the entry
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns a reference to the value of the entry
+    pub fn get(&self) -> &V {
+        &self.value
+    }
+
+    /// Returns a mutable reference to the value of the entry
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.value
+    }
+
+    /// Convert the entry into a mutable reference to its value, bound to the
+    /// lifetime of the map
+    pub fn into_mut(self) -> impl DerefMut<Target = V> + 'a {
+        self.value
+    }
+
+    /// Replace the value of the entry, returning the previous one
+    pub fn insert(&mut self, value: V) -> V {
+        core::mem::replace(self.get_mut(), value)
+    }
+}
+
+impl<'a, K, V, A> VacantEntry<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Key of the entry
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Take the key of the entry
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Map the key to `value`, and return a mutable reference to it
+    pub fn insert(
+        self,
+        value: V,
+    ) -> Result<impl DerefMut<Target = V> + 'a, CanonError> {
+        self.insert_value(value)
+    }
+
+    fn insert_value(
+        self,
+        value: V,
+    ) -> Result<ValRefMut<'a, K, V, A>, CanonError> {
+        self.map.insert(self.key.clone(), value)?;
+
+        value_mut(self.map, &self.key)
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Returns the entry of `k`, to read or write it in place
+    pub fn entry(&mut self, k: K) -> Result<Entry<K, V, A>, CanonError> {
+        // The lookup only reads the tree, so the mutable path is taken once
+        // the key is known to be present
+        if !self.contains_key(&k)? {
+            return Ok(Entry::Vacant(VacantEntry { map: self, key: k }));
+        }
+
+        let value = value_mut(self, &k)?;
+
+        Ok(Entry::Occupied(OccupiedEntry { value, key: k }))
+    }
+}
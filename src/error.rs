@@ -0,0 +1,82 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use core::fmt;
+
+use canonical::CanonError;
+
+#[derive(Debug, Clone)]
+/// Errors of the map operations that can fail for reasons other than the
+/// storage backend
+pub enum MapError {
+    /// The storage backend failed to fetch or decode a node
+    Canon(CanonError),
+    /// The provided [`MapConfig`](crate::MapConfig) is not valid
+    InvalidConfig,
+    /// The cached annotation of a node doesn't match its children
+    InvalidAnnotation,
+    /// The keys of the tree are not in strictly ascending order
+    InvalidOrder,
+    /// A node of the tree has an empty child
+    EmptyChild,
+    /// An aggregate annotation overflowed
+    Overflow,
+    /// The key is out of the key space of the map, or of the range of a
+    /// partial view of it
+    OutOfRange,
+    /// The encoded value exceeds the maximum size configured for the map
+    ValueTooLarge,
+    /// The current root registered for a contract is not the expected one
+    StaleRoot,
+    /// Several keys map to the same value where values must be unique
+    DuplicateValue,
+    /// The key is already mapped, and the map forbids implicit overwrites
+    KeyExists,
+    /// The start of a key range is after its end
+    InvalidRange,
+    /// No unused key is left to be allocated
+    KeysExhausted,
+    /// The tree is deeper than the maximum depth configured for the map
+    TooDeep,
+}
+
+impl From<CanonError> for MapError {
+    fn from(e: CanonError) -> Self {
+        MapError::Canon(e)
+    }
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Canon(e) => write!(f, "storage backend error: {:?}", e),
+            MapError::InvalidConfig => write!(f, "invalid map configuration"),
+            MapError::InvalidAnnotation => {
+                write!(f, "node annotation doesn't match its children")
+            }
+            MapError::InvalidOrder => {
+                write!(f, "keys are not in strictly ascending order")
+            }
+            MapError::EmptyChild => write!(f, "node with an empty child"),
+            MapError::Overflow => write!(f, "aggregate annotation overflow"),
+            MapError::OutOfRange => write!(f, "key out of range"),
+            MapError::ValueTooLarge => {
+                write!(f, "value exceeds the size limit")
+            }
+            MapError::StaleRoot => write!(f, "registered root is stale"),
+            MapError::DuplicateValue => {
+                write!(f, "value mapped by several keys")
+            }
+            MapError::KeyExists => write!(f, "key already mapped"),
+            MapError::InvalidRange => write!(f, "inverted key range"),
+            MapError::KeysExhausted => write!(f, "key space exhausted"),
+            MapError::TooDeep => write!(f, "tree exceeds the depth limit"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MapError {}
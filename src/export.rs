@@ -0,0 +1,64 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use core::fmt;
+
+use canonical::{Canon, CanonError};
+
+#[derive(Debug)]
+/// Failure of [`KelvinMap::export_with`]
+pub enum ExportError<E> {
+    /// The storage backend failed to fetch or decode a node
+    Canon(CanonError),
+    /// The sink function failed to write an entry
+    Sink(E),
+}
+
+impl<E> fmt::Display for ExportError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Canon(e) => {
+                write!(f, "storage backend error: {:?}", e)
+            }
+            ExportError::Sink(e) => write!(f, "export sink error: {}", e),
+        }
+    }
+}
+
+impl<E> std::error::Error for ExportError<E> where E: fmt::Debug + fmt::Display {}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Stream all the entries of the map in key order to `f`, returning the
+    /// number of exported entries.
+    ///
+    /// The format is up to the sink, which can write the entries as CSV or
+    /// JSON lines to any writer. The export stops at the first failure.
+    pub fn export_with<F, E>(&self, mut f: F) -> Result<u64, ExportError<E>>
+    where
+        F: FnMut(&K, &V) -> Result<(), E>,
+    {
+        let mut exported = 0;
+
+        for entry in self.iter().map_err(ExportError::Canon)? {
+            let (k, v) = entry.map_err(ExportError::Canon)?;
+
+            f(k, v).map_err(ExportError::Sink)?;
+            exported += 1;
+        }
+
+        Ok(exported)
+    }
+}
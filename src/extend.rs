@@ -0,0 +1,115 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation, MapError};
+
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+use canonical::{Canon, CanonError};
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Include all the key -> value mappings of `iter` to the map, in order.
+    ///
+    /// Will stop at the first failure of the backing store, leaving the
+    /// mappings included until then in the map.
+    pub fn try_extend<I>(&mut self, iter: I) -> Result<(), CanonError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        iter.into_iter()
+            .try_for_each(|(k, v)| self.insert(k, v).map(|_| ()))
+    }
+
+    /// Build a balanced map out of key -> value mappings sorted by key, in
+    /// linear time.
+    ///
+    /// The leaves are paired bottom-up instead of inserted one by one, so no
+    /// balancing step is ever performed. Will return
+    /// [`MapError::InvalidOrder`] if the keys are not strictly ascending.
+    pub fn from_sorted_iter<I>(iter: I) -> Result<Self, MapError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        let mut leaves: Vec<Leaf<K, V>> =
+            Vec::with_capacity(iter.size_hint().0);
+
+        for (k, v) in iter {
+            if leaves.last().filter(|l| l._key() >= &k).is_some() {
+                return Err(MapError::InvalidOrder);
+            }
+
+            leaves.push(Leaf::new(k, v));
+        }
+
+        let len = leaves.len();
+        Ok(Self::from_sorted_leaves(&mut leaves.into_iter(), len))
+    }
+}
+
+/// Include all the key -> value mappings of the iterator to the map.
+///
+/// # Panics
+///
+/// Panics if the backing store fails to fetch a node, which can't happen for
+/// a map built in memory. Use [`KelvinMap::try_extend`] for maps backed by a
+/// store.
+impl<K, V, A> Extend<(K, V)> for KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.try_extend(iter)
+            .expect("Failed to fetch a node of the map from the store");
+    }
+}
+
+/// Build a balanced map out of the key -> value mappings of the iterator.
+///
+/// Sorted input is built bottom-up as is; unsorted input is sorted first. As
+/// with successive inserts, the last value of a repeated key wins.
+impl<K, V, A> FromIterator<(K, V)> for KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut leaves: Vec<Leaf<K, V>> =
+            iter.into_iter().map(|(k, v)| Leaf::new(k, v)).collect();
+
+        if !leaves.windows(2).all(|w| w[0]._key() < w[1]._key()) {
+            // Stable, so the repeated keys keep their insertion order
+            leaves.sort_by(|a, b| a._key().cmp(b._key()));
+
+            let mut unique: Vec<Leaf<K, V>> = Vec::with_capacity(leaves.len());
+            for leaf in leaves {
+                match unique.last_mut() {
+                    Some(last) if last._key() == leaf._key() => *last = leaf,
+                    _ => unique.push(leaf),
+                }
+            }
+            leaves = unique;
+        }
+
+        let len = leaves.len();
+        Self::from_sorted_leaves(&mut leaves.into_iter(), len)
+    }
+}
@@ -0,0 +1,150 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use alloc::vec::Vec;
+use core::ptr;
+
+use canonical::{Canon, CanonError};
+
+/// Sub-tree on the path of a [`Finger`], with the bounds of its keys
+struct Frame<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    node: KelvinMap<K, V, A>,
+    /// Max key of the preceding sibling sub-tree, if any
+    after: Option<K>,
+    /// Max key of the sub-tree
+    max: Option<K>,
+}
+
+impl<K, V, A> Frame<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn new(node: KelvinMap<K, V, A>, after: Option<K>) -> Self {
+        let max = node.max_key();
+        Self { node, after, max }
+    }
+
+    /// Check if `k` is within the key bounds of the sub-tree
+    fn covers(&self, k: &K) -> bool {
+        let above = self.after.as_ref().map(|a| a < k).unwrap_or(true);
+        let below = self.max.as_ref().map(|m| k <= m).unwrap_or(false);
+
+        above && below
+    }
+}
+
+/// Path from the root of a map to its last looked up key, created by
+/// [`KelvinMap::finger`].
+///
+/// A lookup from a finger only walks up the path until a sub-tree covering
+/// the key is found, and descends from there, so lookups of nearby keys
+/// share most of their path.
+pub struct Finger<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    map: &'a KelvinMap<K, V, A>,
+    path: Vec<Frame<K, V, A>>,
+}
+
+impl<'a, K, V, A> Finger<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Number of sub-trees on the path, including the root
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    fn reset(&mut self, map: &'a KelvinMap<K, V, A>) {
+        self.map = map;
+        self.path.clear();
+        self.path.push(Frame::new(map.clone(), None));
+    }
+
+    fn seek(&mut self, k: &K) -> Result<Option<V>, CanonError> {
+        while self.path.len() > 1
+            && !self.path.last().map(|f| f.covers(k)).unwrap_or(false)
+        {
+            self.path.pop();
+        }
+
+        loop {
+            let frame = match self.path.last() {
+                Some(frame) if frame.covers(k) => frame,
+                _ => return Ok(None),
+            };
+
+            let next = match &frame.node {
+                KelvinMap::Leaf(l) if l._key() == k => {
+                    return Ok(Some(l.value().clone()))
+                }
+                KelvinMap::Node(n) => {
+                    let (l, r) = n.children();
+                    let l = l.val()?.clone();
+
+                    if l.max_key().map(|m| k <= &m).unwrap_or(false) {
+                        Frame::new(l, frame.after.clone())
+                    } else {
+                        Frame::new(r.val()?.clone(), l.max_key())
+                    }
+                }
+                _ => return Ok(None),
+            };
+
+            self.path.push(next);
+        }
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Create a [`Finger`] positioned at the root of the map
+    pub fn finger(&self) -> Finger<'_, K, V, A> {
+        let mut finger = Finger {
+            map: self,
+            path: Vec::new(),
+        };
+
+        finger.reset(self);
+        finger
+    }
+
+    /// Returns a copy of the value corresponding to the key, starting the
+    /// walk from the path of the previous lookup of `hint`
+    ///
+    /// Batches of nearly sorted keys are looked up with a fraction of the
+    /// node traversals of [`KelvinMap::get`]. A finger created from another
+    /// map is reset to the root of this one.
+    pub fn get_from<'a>(
+        &'a self,
+        hint: &mut Finger<'a, K, V, A>,
+        k: &K,
+    ) -> Result<Option<V>, CanonError> {
+        if !ptr::eq(hint.map, self) {
+            hint.reset(self);
+        }
+
+        hint.seek(k)
+    }
+}
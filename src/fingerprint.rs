@@ -0,0 +1,91 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{
+    Digest, KelvinMap, MapAnnotation, MapHash, MapHasher, SCHEMA_DOMAIN,
+};
+
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+
+use canonical::{Canon, EncodeToVec};
+use canonical_derive::Canon;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Canon)]
+/// Compact reference to a specific state of a map, suitable for consensus
+/// messages and cross-contract calls.
+///
+/// Two fingerprints are equal if the maps have the same contents and were
+/// fingerprinted under the same schema.
+pub struct MapFingerprint {
+    root: Digest,
+    len: u64,
+    schema: Digest,
+}
+
+impl MapFingerprint {
+    /// Commitment to the contents of the map, as in
+    /// [`KelvinMap::root_hash`]
+    pub fn root(&self) -> &Digest {
+        &self.root
+    }
+
+    /// Number of entries of the map
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Check if the fingerprinted map is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Hash of the schema the map was fingerprinted under
+    pub fn schema(&self) -> &Digest {
+        &self.schema
+    }
+}
+
+/// Hash of a caller-supplied schema description, such as a name and version
+/// of the key and value types:
+///
+/// `H(SCHEMA_DOMAIN || canon(schema))`
+pub fn schema_hash<H, T>(schema: &T) -> Digest
+where
+    H: MapHasher,
+    T: Canon,
+{
+    let mut bytes = Vec::with_capacity(1 + schema.encoded_len());
+
+    bytes.push(SCHEMA_DOMAIN);
+    bytes.extend(schema.encode_to_vec());
+
+    H::hash(&bytes)
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Fingerprint of the current state of the map under `schema`.
+    ///
+    /// It is computed out of the cached annotations of the root, so no
+    /// traversal is performed.
+    pub fn fingerprint<H, T>(&self, schema: &T) -> MapFingerprint
+    where
+        A: Borrow<MapHash<H>>,
+        H: MapHasher,
+        T: Canon,
+    {
+        MapFingerprint {
+            root: self.root_hash::<H>(),
+            len: self.len() as u64,
+            schema: schema_hash::<H, T>(schema),
+        }
+    }
+}
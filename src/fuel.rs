@@ -0,0 +1,210 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::map::cmp_max_key;
+use crate::zip::Cursor;
+use crate::{KelvinMap, MapAnnotation};
+
+use canonical::{Canon, CanonError};
+use canonical_derive::Canon;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Outcome of a fuel-limited walk
+pub enum Fueled<T, R> {
+    /// The walk completed within the budget
+    Complete(T),
+    /// The budget ran out before the walk completed
+    Exhausted {
+        /// Token to resume the walk from where it stopped
+        resume_token: R,
+    },
+}
+
+impl<T, R> Fueled<T, R> {
+    /// Check if the walk completed within the budget
+    pub fn is_complete(&self) -> bool {
+        matches!(self, Fueled::Complete(_))
+    }
+}
+
+/// Outcome of a fuel-limited lookup
+type FueledGet<K, V, A> = Fueled<Option<V>, GetToken<K, V, A>>;
+
+/// Sub-tree where a fuel-limited [`KelvinMap::get_with_fuel`] stopped
+///
+/// Unlike [`IterToken`], the token keeps the sub-tree as of the first call,
+/// since a single lookup has no position to be expressed as a key.
+pub struct GetToken<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    tree: KelvinMap<K, V, A>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Canon)]
+/// Position of a fuel-limited [`KelvinMap::iter_with_fuel`], as the last key
+/// visited
+///
+/// The token holds no reference to the nodes of the map, so it can be
+/// persisted, and resumed against a map written to meanwhile: the iteration
+/// continues with the keys greater than the last visited one, as of the
+/// state of the map at the time of the resumption. No entry is visited
+/// twice, and the entries present all along are never skipped.
+pub struct IterToken<K> {
+    after: Option<K>,
+}
+
+impl<K, V, A> GetToken<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Resume the lookup of `k` with another `fuel` budget.
+    ///
+    /// The token must be resumed with the key it was created for.
+    pub fn resume(
+        self,
+        k: &K,
+        fuel: u64,
+    ) -> Result<Fueled<Option<V>, Self>, CanonError> {
+        let mut tree = self.tree;
+        let mut fuel = fuel;
+
+        loop {
+            let next = match &tree {
+                KelvinMap::Empty => return Ok(Fueled::Complete(None)),
+                KelvinMap::Leaf(l) if l._key() == k => {
+                    return Ok(Fueled::Complete(Some(l.value().clone())))
+                }
+                KelvinMap::Leaf(_) => return Ok(Fueled::Complete(None)),
+
+                KelvinMap::Node(..) if fuel == 0 => {
+                    let resume_token = Self { tree };
+                    return Ok(Fueled::Exhausted { resume_token });
+                }
+                KelvinMap::Node(n) if cmp_max_key(n.left(), k).is_ge() => {
+                    n.left().val()?.clone()
+                }
+                KelvinMap::Node(n) => n.right().val()?.clone(),
+            };
+
+            fuel -= 1;
+            tree = next;
+        }
+    }
+}
+
+impl<K> IterToken<K>
+where
+    K: Canon + Ord,
+{
+    /// Token resuming the iteration after `after`, or from the start if
+    /// `None`
+    pub fn new(after: Option<K>) -> Self {
+        Self { after }
+    }
+
+    /// Last key visited, if any
+    pub fn after(&self) -> Option<&K> {
+        self.after.as_ref()
+    }
+
+    /// Check if `key` was not visited yet
+    fn pending(&self, key: &K) -> bool {
+        self.after.as_ref().map(|after| key > after).unwrap_or(true)
+    }
+
+    /// Resume the iteration of `map` with another `fuel` budget, calling `f`
+    /// for the next entries in key order.
+    ///
+    /// The nodes along the boundary of the visited keys are descended again,
+    /// so the budget must exceed the depth of the tree for the iteration to
+    /// make progress.
+    pub fn resume<V, A, F>(
+        mut self,
+        map: &KelvinMap<K, V, A>,
+        fuel: u64,
+        mut f: F,
+    ) -> Result<Fueled<(), Self>, CanonError>
+    where
+        V: Canon,
+        A: MapAnnotation<K, V>,
+        F: FnMut(K, V),
+    {
+        let mut cursor = Cursor::new(map);
+        let mut fuel = fuel;
+
+        loop {
+            let visited = match cursor.top() {
+                None => return Ok(Fueled::Complete(())),
+                Some(KelvinMap::Leaf(l)) => !self.pending(l._key()),
+                Some(node) => node
+                    .max_key()
+                    .map(|max| !self.pending(&max))
+                    .unwrap_or(true),
+            };
+
+            match cursor.top() {
+                _ if visited => {
+                    cursor.pop();
+                }
+                Some(KelvinMap::Node(..)) if fuel == 0 => {
+                    return Ok(Fueled::Exhausted { resume_token: self })
+                }
+                Some(KelvinMap::Node(..)) => {
+                    fuel -= 1;
+                    cursor.descend()?;
+                }
+                _ => {
+                    if let Some(leaf) = cursor.next_leaf()? {
+                        let (k, v) = leaf.into_parts();
+                        self.after = Some(k.clone());
+                        f(k, v);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Look up the value of `k`, descending at most `fuel` nodes.
+    ///
+    /// If the budget runs out, the returned token resumes the lookup from
+    /// the last reached sub-tree, which is kept as of this call regardless
+    /// of later writes to `self`.
+    pub fn get_with_fuel(
+        &self,
+        k: &K,
+        fuel: u64,
+    ) -> Result<FueledGet<K, V, A>, CanonError> {
+        GetToken { tree: self.clone() }.resume(k, fuel)
+    }
+
+    /// Call `f` for the entries of the map in key order, descending at most
+    /// `fuel` nodes.
+    ///
+    /// If the budget runs out, the returned token resumes the iteration after
+    /// the last visited key. See [`IterToken`] for the consistency model.
+    pub fn iter_with_fuel<F>(
+        &self,
+        fuel: u64,
+        f: F,
+    ) -> Result<Fueled<(), IterToken<K>>, CanonError>
+    where
+        F: FnMut(K, V),
+    {
+        IterToken::new(None).resume(self, fuel, f)
+    }
+}
@@ -0,0 +1,220 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation, MapError};
+
+use alloc::vec::Vec;
+use core::ops::{Bound, RangeBounds, RangeInclusive};
+
+use canonical::{Canon, CanonError};
+use microkelvin::{Cardinality, MaxKey};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Defines which unused key is allocated by
+/// [`KelvinMap::insert_next_with`]
+pub enum KeyAllocation {
+    /// The key following the greatest key of the map
+    #[default]
+    AfterMax,
+    /// The smallest unused key, reusing the keys of removed entries
+    FirstFree,
+}
+
+/// Scan of the unoccupied keys up to `end`, in key order
+struct GapScan {
+    /// First key not known to be occupied, if any
+    next_free: Option<u64>,
+    /// `next_free` follows an occupied key, rather than the start of the range
+    anchored: bool,
+    end: u64,
+    min_gap: u64,
+    /// Number of gaps after which the scan stops
+    limit: usize,
+    gaps: Vec<RangeInclusive<u64>>,
+}
+
+impl GapScan {
+    /// First key not known to be occupied, unless the scan is over
+    fn pending(&self) -> Option<u64> {
+        self.next_free.filter(|_| self.gaps.len() < self.limit)
+    }
+
+    /// Record the occupied key `k`
+    fn occupy(&mut self, k: u64) {
+        let next_free = match self.next_free {
+            Some(next_free) if k >= next_free => next_free,
+            _ => return,
+        };
+
+        if k > self.end {
+            self.close(next_free, self.end);
+            self.next_free = None;
+            return;
+        }
+
+        if k > next_free {
+            self.close(next_free, k - 1);
+        }
+
+        self.next_free = k.checked_add(1);
+        self.anchored = true;
+    }
+
+    /// Record the unoccupied keys `start..=end` as a gap, if large enough
+    fn close(&mut self, start: u64, end: u64) {
+        if end >= start && end - start >= self.min_gap {
+            self.gaps.push(start..=end);
+        }
+    }
+
+    /// Check if no gap can be found in a sub-tree of `len` keys up to `max`.
+    ///
+    /// All the keys of a sub-tree following an occupied key are free, and
+    /// its unoccupied keys are at most the keys up to its max not in it.
+    fn skips(&self, max: u64, len: u64) -> bool {
+        match self.next_free {
+            Some(next_free) if self.anchored && max <= self.end => {
+                (max - next_free + 1).saturating_sub(len) <= self.min_gap
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<V, A> KelvinMap<u64, V, A>
+where
+    V: Canon,
+    A: MapAnnotation<u64, V>,
+{
+    /// Returns the maximal intervals of unoccupied keys within `range` with
+    /// more than `min_gap` keys, in key order
+    ///
+    /// The sub-trees whose cardinality leaves no room for such an interval
+    /// between their boundaries are skipped without being fetched.
+    pub fn key_gaps<R>(
+        &self,
+        range: R,
+        min_gap: u64,
+    ) -> Result<Vec<RangeInclusive<u64>>, CanonError>
+    where
+        R: RangeBounds<u64>,
+    {
+        self.scan_key_gaps(range, min_gap, usize::MAX)
+    }
+
+    /// Include `v` under an unused key, allocated after the greatest key of
+    /// the map, returning the allocated key.
+    ///
+    /// Will return [`MapError::KeysExhausted`] if the greatest possible key
+    /// is mapped. See [`KelvinMap::insert_next_with`].
+    pub fn insert_next(&mut self, v: V) -> Result<u64, MapError> {
+        self.insert_next_with(v, KeyAllocation::AfterMax)
+    }
+
+    /// Include `v` under an unused key allocated according to `policy`,
+    /// returning the allocated key.
+    ///
+    /// The key is derived from the map itself, so it can't drift from its
+    /// contents as a separate counter could. Will return
+    /// [`MapError::KeysExhausted`] if no key can be allocated.
+    pub fn insert_next_with(
+        &mut self,
+        v: V,
+        policy: KeyAllocation,
+    ) -> Result<u64, MapError> {
+        let key = match policy {
+            KeyAllocation::AfterMax => match self.max_key() {
+                Some(max) => max.checked_add(1),
+                None => Some(0),
+            },
+            KeyAllocation::FirstFree => self
+                .scan_key_gaps(.., 0, 1)?
+                .first()
+                .map(|gap| *gap.start()),
+        };
+
+        let key = key.ok_or(MapError::KeysExhausted)?;
+        self.insert(key, v)?;
+
+        Ok(key)
+    }
+
+    fn scan_key_gaps<R>(
+        &self,
+        range: R,
+        min_gap: u64,
+        limit: usize,
+    ) -> Result<Vec<RangeInclusive<u64>>, CanonError>
+    where
+        R: RangeBounds<u64>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(s) => Some(*s),
+            Bound::Excluded(s) => s.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(e) => Some(*e),
+            Bound::Excluded(e) => e.checked_sub(1),
+            Bound::Unbounded => Some(u64::MAX),
+        };
+
+        let (start, end) = match (start, end) {
+            (Some(s), Some(e)) if s <= e => (s, e),
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut scan = GapScan {
+            next_free: Some(start),
+            anchored: false,
+            end,
+            min_gap,
+            limit,
+            gaps: Vec::new(),
+        };
+
+        self.scan_gaps(&mut scan)?;
+        if let Some(next_free) = scan.pending() {
+            scan.close(next_free, end);
+        }
+
+        Ok(scan.gaps)
+    }
+
+    fn scan_gaps(&self, scan: &mut GapScan) -> Result<(), CanonError> {
+        match self {
+            KelvinMap::Empty => Ok(()),
+            KelvinMap::Leaf(l) => {
+                scan.occupy(*l._key());
+                Ok(())
+            }
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                for child in [l, r] {
+                    let next_free = match scan.pending() {
+                        Some(next_free) => next_free,
+                        None => break,
+                    };
+
+                    let max: &MaxKey<u64> = child.annotation().borrow();
+                    let max = match max {
+                        MaxKey::Maximum(max) if *max >= next_free => *max,
+                        _ => continue,
+                    };
+
+                    let len: &Cardinality = child.annotation().borrow();
+                    if scan.skips(max, len.into()) {
+                        scan.next_free = max.checked_add(1);
+                    } else {
+                        child.val()?.scan_gaps(scan)?;
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
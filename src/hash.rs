@@ -0,0 +1,348 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation};
+
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::marker::PhantomData;
+
+use canonical::{Canon, CanonError, EncodeToVec, Sink, Source, Store};
+use canonical_derive::Canon;
+use microkelvin::{Annotation, Cardinality, Combine, Compound, MaxKey};
+
+/// Domain separation tag prepended to the encoding of a leaf before hashing
+pub const LEAF_DOMAIN: u8 = 0x00;
+
+/// Domain separation tag prepended to the child hashes of a node before
+/// hashing
+pub const NODE_DOMAIN: u8 = 0x01;
+
+/// Domain separation tag prepended to a caller-supplied domain and a root hash
+/// before hashing
+pub const ROOT_DOMAIN: u8 = 0x02;
+
+/// Domain separation tag prepended to the encoding of a schema description
+/// before hashing
+pub const SCHEMA_DOMAIN: u8 = 0x03;
+
+/// 32 bytes digest produced by a [`MapHasher`]
+pub type Digest = [u8; 32];
+
+/// Hash function used to commit to the contents of a map.
+///
+/// The hasher is a marker type parameter of the annotation, so it is required
+/// to be a canonical, defaultable type.
+pub trait MapHasher: Canon + Default + fmt::Debug {
+    /// Hash an arbitrary sequence of bytes
+    fn hash(bytes: &[u8]) -> Digest;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Canon)]
+/// [`MapHasher`] backed by the hash function of the canonical store
+/// (Blake2b with 32 bytes output on hosted environments).
+pub struct StoreHasher;
+
+impl MapHasher for StoreHasher {
+    fn hash(bytes: &[u8]) -> Digest {
+        Store::hash(bytes)
+    }
+}
+
+impl<K, V> Leaf<K, V>
+where
+    K: Canon,
+    V: Canon,
+{
+    /// Commitment to the key -> value mapping.
+    ///
+    /// The scheme is stable, so it can be recomputed independently by
+    /// external verifiers:
+    ///
+    /// `H(LEAF_DOMAIN || canon(K) || canon(V))`
+    pub fn hash<H>(&self) -> Digest
+    where
+        H: MapHasher,
+    {
+        let mut bytes = Vec::with_capacity(1 + self.encoded_len());
+
+        bytes.push(LEAF_DOMAIN);
+        bytes.extend(self.encode_to_vec());
+
+        H::hash(&bytes)
+    }
+}
+
+/// Commitment to a node out of its number of leaves and the hashes of its
+/// children:
+///
+/// `H(NODE_DOMAIN || canon(len) || child_0 || child_1 ...)`
+pub fn node_hash<'a, H, I>(len: u64, children: I) -> Digest
+where
+    H: MapHasher,
+    I: IntoIterator<Item = &'a Digest>,
+{
+    let mut bytes = Vec::with_capacity(1 + 8 + 2 * 32);
+
+    bytes.push(NODE_DOMAIN);
+    bytes.extend_from_slice(&len.to_le_bytes());
+    children
+        .into_iter()
+        .for_each(|child| bytes.extend_from_slice(child));
+
+    H::hash(&bytes)
+}
+
+/// Bind a root hash to a caller-supplied domain, such as a contract id or an
+/// epoch:
+///
+/// `H(ROOT_DOMAIN || canon(tag) || root)`
+pub fn domain_hash<H, T>(root: &Digest, tag: &T) -> Digest
+where
+    H: MapHasher,
+    T: Canon,
+{
+    let mut bytes = Vec::with_capacity(1 + tag.encoded_len() + 32);
+
+    bytes.push(ROOT_DOMAIN);
+    bytes.extend(tag.encode_to_vec());
+    bytes.extend_from_slice(root);
+
+    H::hash(&bytes)
+}
+
+/// Hash annotation committing to the contents of a sub-tree.
+///
+/// Leaves are hashed with [`Leaf::hash`], and the nodes with [`node_hash`]
+/// over the hashes of their children. The number of leaves of every sub-tree
+/// is committed as well, so positional proofs can be verified.
+pub struct MapHash<H> {
+    digest: Digest,
+    cardinality: u64,
+    _marker: PhantomData<H>,
+}
+
+impl<H> MapHash<H> {
+    fn new(digest: Digest, cardinality: u64) -> Self {
+        Self {
+            digest,
+            cardinality,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The committed digest
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    /// The committed number of leaves of the sub-tree
+    pub fn cardinality(&self) -> u64 {
+        self.cardinality
+    }
+}
+
+impl<H> fmt::Debug for MapHash<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MapHash")
+            .field(&self.digest)
+            .field(&self.cardinality)
+            .finish()
+    }
+}
+
+impl<H> Clone for MapHash<H> {
+    fn clone(&self) -> Self {
+        Self::new(self.digest, self.cardinality)
+    }
+}
+
+impl<H> Default for MapHash<H> {
+    fn default() -> Self {
+        Self::new(Digest::default(), 0)
+    }
+}
+
+impl<H> PartialEq for MapHash<H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.digest == other.digest && self.cardinality == other.cardinality
+    }
+}
+
+impl<H> Eq for MapHash<H> {}
+
+impl<H> Canon for MapHash<H> {
+    fn encode(&self, sink: &mut Sink) {
+        self.digest.encode(sink);
+        self.cardinality.encode(sink);
+    }
+
+    fn decode(source: &mut Source) -> Result<Self, CanonError> {
+        let digest = Digest::decode(source)?;
+        let cardinality = u64::decode(source)?;
+
+        Ok(Self::new(digest, cardinality))
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.digest.encoded_len() + self.cardinality.encoded_len()
+    }
+}
+
+impl<K, V, H> Annotation<Leaf<K, V>> for MapHash<H>
+where
+    K: Canon,
+    V: Canon,
+    H: MapHasher,
+{
+    fn from_leaf(leaf: &Leaf<K, V>) -> Self {
+        Self::new(leaf.hash::<H>(), 1)
+    }
+}
+
+impl<K, V, A, H> Combine<KelvinMap<K, V, A>, A> for MapHash<H>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V> + Borrow<MapHash<H>>,
+    H: MapHasher,
+{
+    fn combine(node: &KelvinMap<K, V, A>) -> Self {
+        let mut cardinality = 0u64;
+        let children: Vec<Digest> = node
+            .children()
+            .map(|child| {
+                let annotation = child.annotation();
+                let hash: &MapHash<H> = (*annotation).borrow();
+                cardinality = cardinality.saturating_add(hash.cardinality);
+                hash.digest
+            })
+            .collect();
+
+        Self::new(node_hash::<H, _>(cardinality, children.iter()), cardinality)
+    }
+}
+
+#[derive(Debug, Clone, Default, Canon)]
+/// [`MapAnnotationDefault`](crate::MapAnnotationDefault) extended with a
+/// [`MapHash`] commitment to the contents of every sub-tree
+pub struct MapAnnotationHashed<K, H>
+where
+    K: Canon + Ord + Default,
+{
+    cardinality: Cardinality,
+    max: MaxKey<K>,
+    hash: MapHash<H>,
+}
+
+impl<K, H> Borrow<MaxKey<K>> for MapAnnotationHashed<K, H>
+where
+    K: Canon + Ord + Default,
+{
+    fn borrow(&self) -> &MaxKey<K> {
+        &self.max
+    }
+}
+
+impl<K, H> Borrow<Cardinality> for MapAnnotationHashed<K, H>
+where
+    K: Canon + Ord + Default,
+{
+    fn borrow(&self) -> &Cardinality {
+        &self.cardinality
+    }
+}
+
+impl<K, H> Borrow<MapHash<H>> for MapAnnotationHashed<K, H>
+where
+    K: Canon + Ord + Default,
+{
+    fn borrow(&self) -> &MapHash<H> {
+        &self.hash
+    }
+}
+
+impl<K, V, H> Annotation<Leaf<K, V>> for MapAnnotationHashed<K, H>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+    H: MapHasher,
+{
+    fn from_leaf(leaf: &Leaf<K, V>) -> Self {
+        let cardinality = Cardinality::from_leaf(leaf);
+        let max = MaxKey::from_leaf(leaf);
+        let hash = MapHash::from_leaf(leaf);
+
+        Self {
+            cardinality,
+            max,
+            hash,
+        }
+    }
+}
+
+impl<K, V, H>
+    Combine<
+        KelvinMap<K, V, MapAnnotationHashed<K, H>>,
+        MapAnnotationHashed<K, H>,
+    > for MapAnnotationHashed<K, H>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+    H: MapHasher,
+{
+    fn combine(node: &KelvinMap<K, V, MapAnnotationHashed<K, H>>) -> Self {
+        let cardinality = Cardinality::combine(node);
+        let max = MaxKey::combine(node);
+        let hash = MapHash::combine(node);
+
+        Self {
+            cardinality,
+            max,
+            hash,
+        }
+    }
+}
+
+impl<K, V, H> MapAnnotation<K, V> for MapAnnotationHashed<K, H>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+    H: MapHasher,
+{
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Commitment to the whole contents of the map, computed with
+    /// [`MapHash`] from the cached annotations of the root.
+    pub fn root_hash<H>(&self) -> Digest
+    where
+        A: Borrow<MapHash<H>>,
+        H: MapHasher,
+    {
+        MapHash::<H>::combine(self).digest
+    }
+
+    /// Commitment to the whole contents of the map bound to `tag`, so the
+    /// same contents produce distinct commitments per context.
+    ///
+    /// Proofs are verified against [`KelvinMap::root_hash`], which is then
+    /// bound with [`domain_hash`].
+    pub fn root_hash_with_domain<H, T>(&self, tag: &T) -> Digest
+    where
+        A: Borrow<MapHash<H>>,
+        H: MapHasher,
+        T: Canon,
+    {
+        domain_hash::<H, T>(&self.root_hash::<H>(), tag)
+    }
+}
@@ -0,0 +1,55 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, Map, MapAnnotation};
+
+use alloc::collections::BTreeMap;
+
+use canonical::{Canon, CanonError};
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Count the keys of the map per bucket, as assigned by `bucket`.
+    ///
+    /// The map is traversed once, and the counts are built into a balanced
+    /// map in bulk. Runs of consecutive keys in the same bucket, as produced
+    /// by range-aligned bucket functions, are counted without a lookup per
+    /// key.
+    pub fn histogram<B, F>(&self, bucket: F) -> Result<Map<B, u64>, CanonError>
+    where
+        B: Canon + Ord + Default,
+        F: Fn(&K) -> B,
+    {
+        let mut counts = BTreeMap::new();
+        let mut run: Option<(B, u64)> = None;
+
+        self.for_each_leaf(&mut |leaf| {
+            let b = bucket(leaf._key());
+
+            match &mut run {
+                Some((current, count)) if *current == b => *count += 1,
+                _ => {
+                    if let Some((current, count)) = run.replace((b, 1)) {
+                        *counts.entry(current).or_insert(0) += count;
+                    }
+                }
+            }
+        })?;
+
+        if let Some((current, count)) = run {
+            *counts.entry(current).or_insert(0) += count;
+        }
+
+        let len = counts.len();
+        let mut leaves = counts.into_iter().map(|(b, c)| Leaf::new(b, c));
+
+        Ok(Map::from_sorted_leaves(&mut leaves, len))
+    }
+}
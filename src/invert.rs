@@ -0,0 +1,72 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation, MapError};
+
+use alloc::vec::Vec;
+
+use canonical::Canon;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Defines which entry is kept when several keys map to the same value in
+/// [`KelvinMap::invert`]
+pub enum DuplicatePolicy {
+    /// Fail with [`MapError::DuplicateValue`]
+    #[default]
+    Reject,
+    /// Keep the lowest key mapping to the value
+    KeepFirst,
+    /// Keep the highest key mapping to the value
+    KeepLast,
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon + Ord,
+    A: MapAnnotation<K, V>,
+{
+    /// Create the reverse map, from the values of `self` to their keys.
+    ///
+    /// The entries are collected in a single traversal and the reverse map
+    /// is built balanced in bulk. Values mapped by several keys are handled
+    /// according to `policy`.
+    pub fn invert<A2>(
+        &self,
+        policy: DuplicatePolicy,
+    ) -> Result<KelvinMap<V, K, A2>, MapError>
+    where
+        A2: MapAnnotation<V, K>,
+    {
+        let mut leaves = Vec::with_capacity(self.len());
+        self.for_each_leaf(&mut |l| {
+            leaves.push(Leaf::new(l.value().clone(), l._key().clone()))
+        })?;
+
+        // Stable, so the keys of a value stay in ascending order
+        leaves.sort_by(|a, b| a._key().cmp(b._key()));
+
+        let mut inverted: Vec<Leaf<V, K>> = Vec::with_capacity(leaves.len());
+        for leaf in leaves {
+            match inverted.last_mut() {
+                Some(last) if last._key() == leaf._key() => match policy {
+                    DuplicatePolicy::Reject => {
+                        return Err(MapError::DuplicateValue)
+                    }
+                    DuplicatePolicy::KeepFirst => (),
+                    DuplicatePolicy::KeepLast => *last = leaf,
+                },
+                _ => inverted.push(leaf),
+            }
+        }
+
+        let len = inverted.len();
+        Ok(KelvinMap::from_sorted_leaves(
+            &mut inverted.into_iter(),
+            len,
+        ))
+    }
+}
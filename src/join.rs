@@ -0,0 +1,126 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::zip::Cursor;
+use crate::{KelvinMap, MapAnnotation};
+
+use canonical::{Canon, CanonError};
+
+/// Entry of the left map joined with the matching entry of the right map
+type JoinEntry<K, V, K2, V2> = ((K, V), (K2, V2));
+
+/// Iterator over the entries of a map joined with the entries of another map
+/// keyed by their values, created by [`KelvinMap::join_on_value`]
+pub struct JoinIter<K, V, A, K2, V2, A2>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    K2: Canon + Ord,
+    V2: Canon,
+    A2: MapAnnotation<K2, V2>,
+{
+    left: Cursor<K, V, A>,
+    right: Cursor<K2, V2, A2>,
+    other: KelvinMap<K2, V2, A2>,
+    extract: fn(&V) -> K2,
+    last: Option<K2>,
+}
+
+impl<K, V, A, K2, V2, A2> JoinIter<K, V, A, K2, V2, A2>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    K2: Canon + Ord,
+    V2: Canon,
+    A2: MapAnnotation<K2, V2>,
+{
+    /// Value of the right map mapped to `key`, advancing the ordered cursor
+    /// while the extracted keys are ascending
+    fn lookup(&mut self, key: &K2) -> Result<Option<V2>, CanonError> {
+        let ascending =
+            self.last.as_ref().map(|last| last <= key).unwrap_or(true);
+
+        if !ascending {
+            return Ok(self.other.get(key)?.map(|v| v.clone()));
+        }
+
+        Ok(match self.right.seek(key)? {
+            Some(leaf) if leaf._key() == key => Some(leaf.value().clone()),
+            _ => None,
+        })
+    }
+
+    fn next_entry(
+        &mut self,
+    ) -> Result<Option<JoinEntry<K, V, K2, V2>>, CanonError> {
+        while let Some(leaf) = self.left.next_leaf()? {
+            let (k, v) = leaf.into_parts();
+            let k2 = (self.extract)(&v);
+
+            let found = self.lookup(&k2)?;
+            if self.last.as_ref().map(|last| last <= &k2).unwrap_or(true) {
+                self.last = Some(k2.clone());
+            }
+
+            if let Some(v2) = found {
+                return Ok(Some(((k, v), (k2, v2))));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<K, V, A, K2, V2, A2> Iterator for JoinIter<K, V, A, K2, V2, A2>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    K2: Canon + Ord,
+    V2: Canon,
+    A2: MapAnnotation<K2, V2>,
+{
+    type Item = Result<JoinEntry<K, V, K2, V2>, CanonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().transpose()
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Join the entries of `self` with the entries of `other` whose key is
+    /// extracted from their value, in the key order of `self`.
+    ///
+    /// While the extracted keys are ascending, `other` is co-traversed in
+    /// order, skipping the sub-trees between consecutive matches. The keys
+    /// that break the order are looked up from the root of `other`, so a
+    /// monotone extractor joins both maps in a single pass.
+    pub fn join_on_value<K2, V2, A2>(
+        &self,
+        other: &KelvinMap<K2, V2, A2>,
+        extract: fn(&V) -> K2,
+    ) -> JoinIter<K, V, A, K2, V2, A2>
+    where
+        K2: Canon + Ord,
+        V2: Canon,
+        A2: MapAnnotation<K2, V2>,
+    {
+        JoinIter {
+            left: Cursor::new(self),
+            right: Cursor::new(other),
+            other: other.clone(),
+            extract,
+            last: None,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation};
+
+use canonical::{Canon, CanonError};
+use microkelvin::MaxKey;
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Returns the entry with the largest key, such as the current value of a
+    /// map keyed by block height or timestamp.
+    ///
+    /// The rightmost path of the tree is traversed, with O(log n) complexity.
+    pub fn latest(&self) -> Result<Option<Leaf<K, V>>, CanonError> {
+        self.max_key_leaf()
+    }
+
+    /// Returns the entry with the largest key lower or equal to `k`, that is
+    /// the value of the map as of `k`.
+    ///
+    /// The max key annotations guide the descent, so at most two paths of the
+    /// tree are traversed.
+    pub fn latest_before(
+        &self,
+        k: &K,
+    ) -> Result<Option<Leaf<K, V>>, CanonError> {
+        match self {
+            KelvinMap::Empty => Ok(None),
+            KelvinMap::Leaf(l) if l._key() <= k => Ok(Some(l.clone())),
+            KelvinMap::Leaf(_) => Ok(None),
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let max_l: &MaxKey<K> = l.annotation().borrow();
+
+                match max_l {
+                    MaxKey::Maximum(max) if max <= k => {
+                        match r.val()?.latest_before(k)? {
+                            Some(leaf) => Ok(Some(leaf)),
+                            None => l.val()?.max_key_leaf(),
+                        }
+                    }
+                    _ => l.val()?.latest_before(k),
+                }
+            }
+        }
+    }
+}
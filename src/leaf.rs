@@ -30,6 +30,16 @@ where
         &self.key
     }
 
+    /// Split the leaf into the stored key and value
+    pub(crate) fn into_parts(self) -> (K, V) {
+        (self.key, self.value)
+    }
+
+    /// Stored key along with a mutable reference to the stored value
+    pub(crate) fn parts_mut(&mut self) -> (&K, &mut V) {
+        (&self.key, &mut self.value)
+    }
+
     /// Stored value of the key -> value mapping
     pub fn value(&self) -> &V {
         &self.value
@@ -5,18 +5,143 @@
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
 #![cfg_attr(not(test), no_std)]
-#![feature(external_doc)]
-#![doc(include = "../README.md")]
+#![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
-#![feature(ordering_helpers)]
 
-pub use annotation::{MapAnnotation, MapAnnotationDefault};
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub use annotation::{MapAnnotation, MapAnnotationDefault, MapAnnotationU64};
+pub use bytes::ValBytes;
+pub use chunked::ChunkedValue;
+pub use cipher::{CipherMap, Decrypted, Sealed, ValueCipher};
+pub use collection::{CollectionIter, KelvinCollection};
+pub use config::{BalancePolicy, ConfiguredMap, MapConfig};
+pub use ct::{Choice, ConstantTimeKey};
+pub use diff::Change;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use error::MapError;
+#[cfg(feature = "std")]
+pub use export::ExportError;
+pub use finger::Finger;
+pub use fingerprint::{schema_hash, MapFingerprint};
+pub use fuel::{Fueled, GetToken, IterToken};
+pub use gaps::KeyAllocation;
+pub use hash::{
+    domain_hash, node_hash, Digest, MapAnnotationHashed, MapHash, MapHasher,
+    StoreHasher, LEAF_DOMAIN, NODE_DOMAIN, ROOT_DOMAIN, SCHEMA_DOMAIN,
+};
+pub use invert::DuplicatePolicy;
+pub use join::JoinIter;
 pub use leaf::Leaf;
-pub use map::KelvinMap;
+pub use linked::KelvinLinkedMap;
+pub use lww::{LwwMap, Stamped};
+pub use map::{KelvinMap, MapNode};
+pub use multiset::{DupCount, KelvinMultiSet, MultiSetAnnotation};
+#[cfg(feature = "poseidon")]
+pub use poseidon::{to_scalars, PoseidonHasher, SCALAR_CHUNK};
+pub use prefix::{Bytes32Iter, Bytes32Map};
+#[cfg(feature = "profiling")]
+pub use profiling::{HotRange, ProfiledMap};
+pub use proof::{NthProof, ProofStep, RangeProof, RangeProofNode};
+pub use range::KeyRange;
+#[cfg(feature = "host")]
+pub use registry::{MapRegistry, RootUpdate};
+pub use repair::RepairReport;
+pub use shrink::ShrinkReport;
+pub use simulate::SimulationReport;
+pub use skeleton::{Skeleton, SkeletonNode};
+pub use snapshot::SnapshotIter;
+pub use sparse::{
+    empty_hash, SparseHash, SparseMap, SparseProof, SparseTree, EMPTY_LEAF,
+    MAX_SPARSE_DEPTH,
+};
+pub use staged::{commit_all, StagedCheck, StagedMap, TwoPhase};
+pub use sum::{
+    Checked, MapAnnotationSum, OverflowPolicy, Saturating, Sum, Weighted,
+};
+pub use sync::{BucketDigest, KeyDigest, RangeRequest, MAX_BUCKET_BITS};
+pub use tombstone::{Slot, TombstoneMap};
+pub use verify::VerifiedMap;
+pub use view::RangeView;
+pub use write_set::{PathSegment, TrackedMap, WriteSet};
+pub use zip::ZipIter;
 
+mod alias;
 mod annotation;
+mod bounded;
+mod bytes;
+mod checked;
+mod chunked;
+mod cipher;
+mod collection;
+mod config;
+mod convert;
+#[cfg(feature = "testing")]
+pub mod corrupt;
+mod ct;
+mod diff;
+mod entry;
+mod error;
+#[cfg(feature = "std")]
+mod export;
+mod extend;
+mod finger;
+mod fingerprint;
+mod fuel;
+mod gaps;
+mod hash;
+mod histogram;
+mod invert;
+mod join;
+mod latest;
 mod leaf;
+mod linked;
+mod lww;
+mod macros;
 mod map;
+mod multiset;
+#[cfg(feature = "persistence")]
+mod persistence;
+#[cfg(feature = "poseidon")]
+mod poseidon;
+mod prefix;
+#[cfg(feature = "profiling")]
+mod profiling;
+mod proof;
+mod range;
+#[cfg(feature = "host")]
+mod registry;
+mod repair;
+mod set;
+mod shard;
+mod shrink;
+mod simulate;
+mod skeleton;
+mod snapshot;
+mod sparse;
+mod staged;
+mod sum;
+mod sync;
+mod tombstone;
+mod top_k;
+mod verify;
+mod view;
+mod write_set;
+mod zip;
 
 /// [`KelvinMap`] default implementation using the minimal [`MapAnnotation`]
 pub type Map<K, V> = KelvinMap<K, V, MapAnnotationDefault<K>>;
+
+/// [`KelvinMap`] with unit values, acting as an ordered set of keys
+pub type Set<K> = KelvinMap<K, (), MapAnnotationDefault<K>>;
+
+/// [`KelvinMap`] specialized for `u64` keys using [`MapAnnotationU64`]
+pub type U64Map<V> = KelvinMap<u64, V, MapAnnotationU64>;
+
+/// [`KelvinMap`] committing to its contents with [`MapAnnotationHashed`]
+pub type HashedMap<K, V> = KelvinMap<K, V, MapAnnotationHashed<K, StoreHasher>>;
+
+/// [`KelvinMap`] tracking the sum of its values with [`MapAnnotationSum`]
+pub type SumMap<K, V, O = Checked> = KelvinMap<K, V, MapAnnotationSum<K, O>>;
@@ -0,0 +1,161 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::bounded::BoundedWalker;
+use crate::{Leaf, Map};
+
+use core::ops::{Deref, DerefMut};
+
+use canonical::{Canon, CanonError};
+use canonical_derive::Canon;
+use microkelvin::{Branch, BranchMut};
+
+/// Value of a linked entry, skipping its insertion sequence
+fn linked_value<K, V>(leaf: &Leaf<K, (u64, V)>) -> &V
+where
+    K: Ord,
+{
+    &leaf.value().1
+}
+
+/// Mutable value of a linked entry, skipping its insertion sequence
+fn linked_value_mut<K, V>(leaf: &mut Leaf<K, (u64, V)>) -> &mut V
+where
+    K: Ord,
+{
+    &mut leaf.value_mut().1
+}
+
+#[derive(Debug, Clone, Default, Canon)]
+/// Map that also records the order in which its keys were inserted, to be
+/// iterated either in key order or in insertion order.
+///
+/// Every entry is tagged with a monotonically increasing insertion sequence,
+/// indexed by a second map from the sequences to the keys. Overwriting the
+/// value of a key keeps its position in the insertion order.
+pub struct KelvinLinkedMap<K, V>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+{
+    entries: Map<K, (u64, V)>,
+    order: Map<u64, K>,
+    next: u64,
+}
+
+impl<K, V> KelvinLinkedMap<K, V>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+{
+    /// Returns the number of entries of the map
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns a reference to the value corresponding to the key
+    pub fn get<'a>(
+        &'a self,
+        k: &K,
+    ) -> Result<Option<impl Deref<Target = V> + 'a>, CanonError> {
+        Ok(Branch::walk(&self.entries, BoundedWalker::key(k))?
+            .map(|branch| branch.map_leaf(linked_value)))
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key
+    pub fn get_mut<'a>(
+        &'a mut self,
+        k: &K,
+    ) -> Result<Option<impl DerefMut<Target = V> + 'a>, CanonError> {
+        Ok(BranchMut::walk(&mut self.entries, BoundedWalker::key(k))?
+            .map(|branch| branch.map_leaf_mut(linked_value_mut)))
+    }
+
+    /// Insertion sequence of the key, if present
+    pub fn sequence(&self, k: &K) -> Result<Option<u64>, CanonError> {
+        Ok(self.entries.get(k)?.map(|entry| entry.0))
+    }
+
+    /// Include a key -> value mapping to the map.
+    ///
+    /// A new key is appended to the insertion order, while an existing one
+    /// keeps its position and has its previous value returned.
+    pub fn insert(&mut self, k: K, v: V) -> Result<Option<V>, CanonError> {
+        if let Some(mut entry) = self.entries.get_mut(&k)? {
+            return Ok(Some(core::mem::replace(&mut entry.1, v)));
+        }
+
+        let seq = self.next;
+        self.next += 1;
+
+        self.order.insert(seq, k.clone())?;
+        self.entries.insert(k, (seq, v))?;
+
+        Ok(None)
+    }
+
+    /// Remove a key -> value mapping from the map
+    pub fn remove(&mut self, k: &K) -> Result<Option<V>, CanonError> {
+        match self.entries.remove(k)? {
+            Some((seq, v)) => {
+                self.order.remove(&seq)?;
+                Ok(Some(v))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Remove the earliest inserted entry still in the map
+    pub fn pop_front(&mut self) -> Result<Option<(K, V)>, CanonError> {
+        let k = match self.order.min_key()? {
+            Some(seq) => self.order.remove(&seq)?,
+            None => return Ok(None),
+        };
+
+        match k {
+            Some(k) => {
+                let v = self.entries.remove(&k)?.map(|(_, v)| v);
+                Ok(v.map(|v| (k, v)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Iterate the entries in key order
+    pub fn iter<'a>(
+        &'a self,
+    ) -> Result<
+        impl Iterator<Item = Result<(&'a K, &'a V), CanonError>> + 'a,
+        CanonError,
+    > {
+        Ok(self
+            .entries
+            .iter()?
+            .map(|entry| entry.map(|(k, (_, v))| (k, v))))
+    }
+
+    /// Iterate the entries in insertion order
+    ///
+    /// Every value is looked up by its key, and returned as a copy.
+    pub fn iter_by_insertion<'a>(
+        &'a self,
+    ) -> Result<
+        impl Iterator<Item = Result<(&'a K, V), CanonError>> + 'a,
+        CanonError,
+    > {
+        Ok(self.order.values()?.map(move |k| {
+            let k = k?;
+            let v = self.get(k)?.ok_or(CanonError::NotFound)?;
+
+            Ok((k, v.clone()))
+        }))
+    }
+}
@@ -0,0 +1,140 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::Map;
+
+use core::ops::Deref;
+
+use canonical::{Canon, CanonError};
+use canonical_derive::Canon;
+
+#[derive(Debug, Clone, PartialEq, Eq, Canon)]
+/// Value of a [`LwwMap`] tagged with the stamp and the writer of its write
+pub struct Stamped<V> {
+    stamp: u64,
+    writer: u64,
+    value: V,
+}
+
+impl<V> Stamped<V> {
+    /// Tag `value` with the stamp and writer of its write
+    pub fn new(value: V, stamp: u64, writer: u64) -> Self {
+        Self {
+            stamp,
+            writer,
+            value,
+        }
+    }
+
+    /// Stamp of the write
+    pub fn stamp(&self) -> u64 {
+        self.stamp
+    }
+
+    /// Identifier of the node that performed the write
+    pub fn writer(&self) -> u64 {
+        self.writer
+    }
+
+    /// Written value
+    pub fn value(&self) -> &V {
+        &self.value
+    }
+
+    /// Check if this write wins over `other` by the last-writer-wins rule.
+    ///
+    /// The greater stamp wins, and concurrent writes with the same stamp are
+    /// ordered by their writer, so every replica resolves them alike.
+    pub fn wins_over(&self, other: &Self) -> bool {
+        (self.stamp, self.writer) > (other.stamp, other.writer)
+    }
+}
+
+#[derive(Debug, Clone, Default, Canon)]
+/// Map replicated between nodes with last-writer-wins conflict resolution.
+///
+/// Every write is tagged with a stamp and the identifier of its writer, and
+/// is only applied if it wins over the write already in the map. Merging
+/// replicas in any order converges to the same contents.
+pub struct LwwMap<K, V>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+{
+    entries: Map<K, Stamped<V>>,
+}
+
+impl<K, V> LwwMap<K, V>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+{
+    /// Returns the number of entries of the map
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns a reference to the stamped value corresponding to the key
+    pub fn get<'a>(
+        &'a self,
+        k: &K,
+    ) -> Result<Option<impl Deref<Target = Stamped<V>> + 'a>, CanonError> {
+        self.entries.get(k)
+    }
+
+    /// Write `value` to `k` if it wins over the current write of the key.
+    ///
+    /// Returns `true` if the write was applied.
+    pub fn insert(
+        &mut self,
+        k: K,
+        value: Stamped<V>,
+    ) -> Result<bool, CanonError> {
+        if let Some(mut current) = self.entries.get_mut(&k)? {
+            if !value.wins_over(&current) {
+                return Ok(false);
+            }
+
+            *current = value;
+            return Ok(true);
+        }
+
+        self.entries.insert(k, value)?;
+        Ok(true)
+    }
+
+    /// Merge the writes of another replica into this one.
+    ///
+    /// Returns the number of keys whose value was taken from `other`.
+    pub fn merge(&mut self, other: &Self) -> Result<u64, CanonError> {
+        let mut applied = 0;
+
+        for entry in other.entries.iter()? {
+            let (k, value) = entry?;
+
+            if self.insert(k.clone(), value.clone())? {
+                applied += 1;
+            }
+        }
+
+        Ok(applied)
+    }
+
+    /// Iterate the entries in key order
+    pub fn iter<'a>(
+        &'a self,
+    ) -> Result<
+        impl Iterator<Item = Result<(&'a K, &'a Stamped<V>), CanonError>> + 'a,
+        CanonError,
+    > {
+        self.entries.iter()
+    }
+}
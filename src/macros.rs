@@ -0,0 +1,26 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+/// Build a [`Map`](crate::Map) from a list of `key => value` literals.
+///
+/// Every mapping is inserted in order, so the naive balancing of `insert` is
+/// applied and the resulting map is balanced. Panics if the backend fails to
+/// insert any of the mappings; this macro is meant for test fixtures only.
+#[cfg(feature = "testing")]
+#[macro_export]
+macro_rules! map {
+    () => {
+        $crate::Map::default()
+    };
+
+    ($($k:expr => $v:expr),+ $(,)?) => {{
+        let mut map = $crate::Map::default();
+        $(
+            map.insert($k, $v).expect("Failed to insert a literal KV");
+        )+
+        map
+    }};
+}
@@ -4,20 +4,22 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use crate::{Leaf, MapAnnotation};
+use crate::bounded::{BoundedWalker, RightmostWalker};
+use crate::{Leaf, MapAnnotation, MapConfig};
 
+use alloc::vec;
+use alloc::vec::Vec;
 use core::ops::{Deref, DerefMut};
 use core::{cmp, mem};
 
-use canonical::{Canon, CanonError};
-use canonical_derive::Canon;
+use canonical::{Canon, CanonError, Sink, Source};
 
 use microkelvin::{
     Annotated, Branch, BranchMut, Cardinality, Child, ChildMut, Compound,
-    MaxKey, Step, Walk, Walker,
+    First, Keyed, MaxKey, MutableLeaves, Nth, Step, Walk, Walker,
 };
 
-#[derive(Debug, Clone, Canon)]
+#[derive(Debug, Clone)]
 /// Binary tree map-like implementation with Microkelvin set as backend
 ///
 /// The borrowed [`Max`] from the annotation will be used to traverse the tree and is expected to
@@ -34,10 +36,139 @@ where
     Leaf(Leaf<K, V>),
     /// Annotated node that will contain, at least, the maximum key value that exists within this
     /// sub-tree
-    Node(
-        Annotated<KelvinMap<K, V, A>, A>,
-        Annotated<KelvinMap<K, V, A>, A>,
-    ),
+    Node(MapNode<K, V, A>),
+}
+
+/// Annotated child of a node
+type Subtree<K, V, A> = Annotated<KelvinMap<K, V, A>, A>;
+
+#[derive(Debug, Clone)]
+/// Inner node of a [`KelvinMap`], holding its two annotated children and the
+/// cached number of leaves of its sub-tree.
+///
+/// Nodes are only built by the map, on structural edits or when decoded,
+/// so the cached cardinality always matches the annotations of the children.
+pub struct MapNode<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    left: Subtree<K, V, A>,
+    right: Subtree<K, V, A>,
+    cardinality: u64,
+}
+
+impl<K, V, A> MapNode<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    pub(crate) fn new(left: Subtree<K, V, A>, right: Subtree<K, V, A>) -> Self {
+        let cardinality = cardinality(&left) + cardinality(&right);
+
+        Self {
+            left,
+            right,
+            cardinality,
+        }
+    }
+
+    /// Left child of the node
+    pub fn left(&self) -> &Subtree<K, V, A> {
+        &self.left
+    }
+
+    /// Right child of the node
+    pub fn right(&self) -> &Subtree<K, V, A> {
+        &self.right
+    }
+
+    /// Both children of the node
+    pub fn children(&self) -> (&Subtree<K, V, A>, &Subtree<K, V, A>) {
+        (&self.left, &self.right)
+    }
+
+    /// Number of leaves of the sub-tree
+    pub fn cardinality(&self) -> u64 {
+        self.cardinality
+    }
+
+    /// Mutable children of the node. The cached cardinality must be
+    /// refreshed with [`MapNode::refresh_cardinality`] after their leaves
+    /// are edited.
+    pub(crate) fn children_mut(
+        &mut self,
+    ) -> (&mut Subtree<K, V, A>, &mut Subtree<K, V, A>) {
+        (&mut self.left, &mut self.right)
+    }
+
+    /// Recompute the cached cardinality out of the annotations of the
+    /// children
+    pub(crate) fn refresh_cardinality(&mut self) {
+        self.cardinality = cardinality(&self.left) + cardinality(&self.right);
+    }
+}
+
+/// Number of leaves of an annotated sub-tree
+pub(crate) fn cardinality<K, V, A>(
+    ann: &Annotated<KelvinMap<K, V, A>, A>,
+) -> u64
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    let c: &Cardinality = ann.annotation().borrow();
+    c.into()
+}
+
+/// The cached length of the nodes is not encoded, but recomputed out of the
+/// annotations of their children, so the encoding is the one of a plain
+/// binary tree.
+impl<K, V, A> Canon for KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn encode(&self, sink: &mut Sink) {
+        match self {
+            KelvinMap::Empty => 0u8.encode(sink),
+            KelvinMap::Leaf(l) => {
+                1u8.encode(sink);
+                l.encode(sink);
+            }
+            KelvinMap::Node(n) => {
+                2u8.encode(sink);
+                n.left.encode(sink);
+                n.right.encode(sink);
+            }
+        }
+    }
+
+    fn decode(source: &mut Source) -> Result<Self, CanonError> {
+        match u8::decode(source)? {
+            0 => Ok(KelvinMap::Empty),
+            1 => Ok(KelvinMap::Leaf(Leaf::decode(source)?)),
+            2 => {
+                let l = Annotated::decode(source)?;
+                let r = Annotated::decode(source)?;
+
+                Ok(KelvinMap::Node(MapNode::new(l, r)))
+            }
+            _ => Err(CanonError::InvalidEncoding),
+        }
+    }
+
+    fn encoded_len(&self) -> usize {
+        1 + match self {
+            KelvinMap::Empty => 0,
+            KelvinMap::Leaf(l) => l.encoded_len(),
+            KelvinMap::Node(n) => n.left.encoded_len() + n.right.encoded_len(),
+        }
+    }
 }
 
 impl<K, V, A> Default for KelvinMap<K, V, A>
@@ -47,10 +178,20 @@ where
     A: MapAnnotation<K, V>,
 {
     fn default() -> Self {
-        KelvinMap::Empty
+        Self::new()
     }
 }
 
+// The keys of the leaves are not exposed mutably, so only the values can be
+// edited through a `BranchMut`, leaving the order of the map intact
+impl<K, V, A> MutableLeaves for KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+}
+
 impl<K, V, A> Compound<A> for KelvinMap<K, V, A>
 where
     V: Canon,
@@ -61,8 +202,8 @@ where
 
     fn child(&self, ofs: usize) -> Child<Self, A> {
         match (ofs, self) {
-            (0, KelvinMap::Node(l, _)) => Child::Node(l),
-            (1, KelvinMap::Node(_, r)) => Child::Node(r),
+            (0, KelvinMap::Node(n)) => Child::Node(n.left()),
+            (1, KelvinMap::Node(n)) => Child::Node(n.right()),
             (0, KelvinMap::Leaf(l)) => Child::Leaf(l),
             _ => Child::EndOfNode,
         }
@@ -70,16 +211,15 @@ where
 
     fn child_mut(&mut self, ofs: usize) -> ChildMut<Self, A> {
         match (ofs, self) {
-            (0, KelvinMap::Node(l, _)) => ChildMut::Node(l),
-            (1, KelvinMap::Node(_, r)) => ChildMut::Node(r),
+            (0, KelvinMap::Node(n)) => ChildMut::Node(n.children_mut().0),
+            (1, KelvinMap::Node(n)) => ChildMut::Node(n.children_mut().1),
             (0, KelvinMap::Leaf(l)) => ChildMut::Leaf(l),
             _ => ChildMut::EndOfNode,
         }
     }
 }
 
-// MaxKey doesn't implement PartialCmp<K>
-fn cmp_max_key<K, V, A>(
+pub(crate) fn cmp_max_key<K, V, A>(
     ann: &Annotated<KelvinMap<K, V, A>, A>,
     key: &K,
 ) -> cmp::Ordering
@@ -88,70 +228,69 @@ where
     V: Canon,
     A: MapAnnotation<K, V>,
 {
-    match ann.annotation().borrow() {
-        MaxKey::Maximum(ann) => MaxKey::Maximum(ann).cmp(&MaxKey::Maximum(key)),
-        MaxKey::NegativeInfinity => cmp::Ordering::Less,
+    ann.annotation().cmp_max_key(key)
+}
+
+/// Typed accessors over the children of a walked node, so walkers don't need
+/// to match the [`Child`] enum of microkelvin directly
+pub(crate) trait WalkExt<'a, K, V, A> {
+    /// The child at `ofs`, if it is a leaf
+    fn as_leaf(&self, ofs: usize) -> Option<&'a Leaf<K, V>>;
+
+    /// The annotation of the child at `ofs`, if it is a node
+    fn as_node_annotation(&self, ofs: usize) -> Option<&'a A>;
+}
+
+impl<'a, K, V, A> WalkExt<'a, K, V, A> for Walk<'a, KelvinMap<K, V, A>, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn as_leaf(&self, ofs: usize) -> Option<&'a Leaf<K, V>> {
+        match self.child(ofs) {
+            Child::Leaf(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    fn as_node_annotation(&self, ofs: usize) -> Option<&'a A> {
+        match self.child(ofs) {
+            Child::Node(n) => Some(n.annotation()),
+            _ => None,
+        }
     }
 }
 
-struct BinaryWalker<'a, K>(&'a K)
+/// Walker that will only yield the matching leaf if its value satisfies the
+/// provided predicate
+struct PredicateWalker<'a, K, P>(BoundedWalker<'a, K>, P)
 where
     K: Canon + Ord;
 
-impl<'a, K, V, A> Walker<KelvinMap<K, V, A>, A> for BinaryWalker<'a, K>
+impl<'a, K, V, A, P> Walker<KelvinMap<K, V, A>, A> for PredicateWalker<'a, K, P>
 where
     K: Canon + Ord,
     V: Canon,
     A: MapAnnotation<K, V>,
+    P: FnMut(&V) -> bool,
 {
     fn walk(&mut self, walk: Walk<KelvinMap<K, V, A>, A>) -> Step {
-        match (walk.child(0), walk.child(1)) {
-            // (0, 0) Empty tree
-            (
-                Child::EndOfNode | Child::Empty,
-                Child::EndOfNode | Child::Empty,
-            ) => Step::Abort,
-
-            // (0, r) Invalid tree
-            (
-                Child::EndOfNode | Child::Empty,
-                Child::Leaf(_) | Child::Node(_),
-            ) => unreachable!(),
-
-            // (_, r), r < k Key out of range
-            (_, Child::Node(r)) if cmp_max_key(r, &self.0).is_lt() => {
-                Step::Abort
-            }
-
-            // Key match
-            (Child::Leaf(l), _) if l._key() == self.0 => Step::Found(0),
-            (_, Child::Leaf(r)) if r._key() == self.0 => Step::Found(1),
-
-            // End of path without match
-            (
-                Child::Leaf(_),
-                Child::Leaf(_) | Child::EndOfNode | Child::Empty,
-            ) => Step::Abort,
-
-            // (l, _) l >= k Traverse left
-            (Child::Node(l), _) if cmp_max_key(l, &self.0).is_ge() => {
-                Step::Into(0)
-            }
-
-            // (_, r) Traverse right, k <= r is already tested
-            (_, Child::Node(_)) => Step::Into(1),
-
-            (
-                Child::Node(_),
-                Child::Empty | Child::EndOfNode | Child::Leaf(_),
-            ) => Step::Abort,
+        match self.0.step(&walk) {
+            Step::Found(i) => match walk.as_leaf(i) {
+                Some(l) if (self.1)(l.value()) => Step::Found(i),
+                _ => Step::Abort,
+            },
+            step => step,
         }
     }
 }
 
 /// Private struct used to hide the complex branch signature behind an
 /// `impl Deref<Target = V>` for returning references to values in the map
-struct ValRef<'a, K, V, A>(Branch<'a, KelvinMap<K, V, A>, A>)
+pub(crate) struct ValRef<'a, K, V, A>(
+    pub(crate) Branch<'a, KelvinMap<K, V, A>, A>,
+)
 where
     K: Canon + Ord,
     V: Canon,
@@ -172,7 +311,9 @@ where
 
 /// Private struct used to hide the complex branch signature behind an
 /// `impl DerefMut<Target = V>` for returning mutable references to values in the map
-struct ValRefMut<'a, K, V, A>(BranchMut<'a, KelvinMap<K, V, A>, A>)
+pub(crate) struct ValRefMut<'a, K, V, A>(
+    pub(crate) BranchMut<'a, KelvinMap<K, V, A>, A>,
+)
 where
     K: Canon + Ord,
     V: Canon,
@@ -208,22 +349,117 @@ where
     V: Canon,
     A: MapAnnotation<K, V>,
 {
+    /// Create a new empty map
+    ///
+    /// Usable in const context, so empty maps can be part of a `const`
+    /// initial state.
+    pub const fn new() -> Self {
+        KelvinMap::Empty
+    }
+
+    /// Create a node out of two sub-trees.
+    ///
+    /// If any of the sub-trees is empty, the other one is returned instead so
+    /// walkers never observe a node with an empty child. Every structural edit
+    /// must build nodes via this constructor.
+    pub(crate) fn node(left: Self, right: Self) -> Self {
+        match (left, right) {
+            (KelvinMap::Empty, tree) | (tree, KelvinMap::Empty) => tree,
+            (left, right) => KelvinMap::Node(MapNode::new(
+                Annotated::new(left),
+                Annotated::new(right),
+            )),
+        }
+    }
+
+    /// Build a balanced tree out of the next `len` leaves of an iterator
+    /// sorted by key.
+    ///
+    /// The left sub-trees take the extra leaf of odd splits, so the result
+    /// is already balanced for the naive balancing of `insert` and `remove`.
+    pub(crate) fn from_sorted_leaves<I>(leaves: &mut I, len: usize) -> Self
+    where
+        I: Iterator<Item = Leaf<K, V>>,
+    {
+        match len {
+            0 => KelvinMap::Empty,
+            1 => leaves.next().map(KelvinMap::Leaf).unwrap_or_default(),
+            _ => {
+                let left = Self::from_sorted_leaves(leaves, len - len / 2);
+                let right = Self::from_sorted_leaves(leaves, len / 2);
+
+                Self::node(left, right)
+            }
+        }
+    }
+
+    /// Replace a node that has an empty child with its other child
+    pub(crate) fn collapse(&mut self) -> Result<(), CanonError> {
+        let (l, r) = match self {
+            KelvinMap::Node(n) => n.children(),
+            _ => return Ok(()),
+        };
+
+        if l.val()?.is_empty() {
+            let new = r.val()?.clone();
+            *self = new;
+        } else if r.val()?.is_empty() {
+            let new = l.val()?.clone();
+            *self = new;
+        }
+
+        Ok(())
+    }
+
     /// Returns the number of elements in the map.
+    ///
+    /// The length is cached by every node, so no annotation is read.
     pub fn len(&self) -> usize {
         match self {
             KelvinMap::Empty => 0,
             KelvinMap::Leaf(_) => 1,
-            KelvinMap::Node(l, r) => {
-                let c_l: &Cardinality = l.annotation().borrow();
-                let c_l: u64 = c_l.into();
-                let c_l = c_l as usize;
+            KelvinMap::Node(n) => n.cardinality() as usize,
+        }
+    }
 
-                let c_r: &Cardinality = r.annotation().borrow();
-                let c_r: u64 = c_r.into();
-                let c_r = c_r as usize;
+    /// Recompute the cached length of a node out of the annotations of its
+    /// children, after they were edited
+    pub(crate) fn refresh_len(&mut self) {
+        if let KelvinMap::Node(n) = self {
+            n.refresh_cardinality();
+        }
+    }
 
-                c_l + c_r
+    /// Bounds on the number of elements of the union of `self` and `other`,
+    /// in the form `(lower, upper)`, in the fashion of
+    /// [`Iterator::size_hint`].
+    ///
+    /// Only the cardinalities and the key bounds of the maps are used, so no
+    /// merge is performed. If the key ranges of the maps don't overlap, the
+    /// bounds are exact.
+    pub fn estimate_union_len(
+        &self,
+        other: &Self,
+    ) -> Result<(usize, usize), CanonError> {
+        let (len, other_len) = (self.len(), other.len());
+        let upper = len.saturating_add(other_len);
+
+        let disjoint = match (
+            self.min_key()?,
+            self.max_key(),
+            other.min_key()?,
+            other.max_key(),
+        ) {
+            (Some(min), Some(max), Some(other_min), Some(other_max)) => {
+                max < other_min || other_max < min
             }
+            _ => true,
+        };
+
+        if disjoint {
+            Ok((upper, upper))
+        } else {
+            Ok((cmp::max(len, other_len), upper))
         }
     }
 
@@ -235,6 +471,19 @@ where
         }
     }
 
+    /// Number of entries of the left and right sub-trees of the root, or
+    /// `None` if the map has less than two entries
+    ///
+    /// The sizes are read from the cached annotations, so no node is fetched.
+    pub fn split_sizes(&self) -> Option<(u64, u64)> {
+        match self {
+            KelvinMap::Node(n) => {
+                Some((cardinality(n.left()), cardinality(n.right())))
+            }
+            _ => None,
+        }
+    }
+
     /// Returns a reference to the value corresponding to the key
     ///
     /// Will return `Ok(None)` if no correspondent key was found.
@@ -242,10 +491,112 @@ where
         &'a self,
         k: &K,
     ) -> Result<Option<impl Deref<Target = V> + 'a>, CanonError> {
-        Branch::walk(self, BinaryWalker(k))
+        Branch::walk(self, BoundedWalker::key(k))
             .map(|result| result.map(|branch| ValRef(branch)))
     }
 
+    /// Returns references to the stored key and the value corresponding to
+    /// the key
+    ///
+    /// Will return `Ok(None)` if no correspondent key was found.
+    pub fn get_key_value(&self, k: &K) -> Result<Option<(&K, &V)>, CanonError> {
+        let found = Branch::walk(self, BoundedWalker::key(k))?;
+
+        Ok(found
+            .into_iter()
+            .flatten()
+            .next()
+            .transpose()?
+            .map(|l| (l.key(), l.value())))
+    }
+
+    /// Check if the map contains a mapping for the key
+    pub fn contains_key(&self, k: &K) -> Result<bool, CanonError> {
+        Branch::walk(self, BoundedWalker::key(k)).map(|result| result.is_some())
+    }
+
+    /// Check if the map contains a mapping for the key, using only the
+    /// maximum keys cached in the annotations of the nodes.
+    ///
+    /// The search stops at the first sub-tree whose maximum key is `k`, so
+    /// the matching leaf is usually not even fetched. Values are never read,
+    /// compared or cloned, which makes it suited to hot membership checks
+    /// such as nullifier lookups.
+    pub fn contains_key_fast(&self, k: &K) -> Result<bool, CanonError> {
+        match self {
+            KelvinMap::Empty => Ok(false),
+            KelvinMap::Leaf(l) => Ok(l._key() == k),
+            KelvinMap::Node(n) => {
+                for child in &[n.left(), n.right()] {
+                    match cmp_max_key(child, k) {
+                        cmp::Ordering::Less => (),
+                        cmp::Ordering::Equal => return Ok(true),
+                        cmp::Ordering::Greater => {
+                            return child.val()?.contains_key_fast(k)
+                        }
+                    }
+                }
+
+                Ok(false)
+            }
+        }
+    }
+
+    /// Iterate the entries of the map in key order
+    ///
+    /// The tree is walked lazily along a single branch, so no entry is copied
+    /// and the nodes not yet in memory are fetched as the iteration goes.
+    pub fn iter<'a>(
+        &'a self,
+    ) -> Result<
+        impl Iterator<Item = Result<(&'a K, &'a V), CanonError>> + 'a,
+        CanonError,
+    > {
+        Ok(First::first(self)?
+            .into_iter()
+            .flatten()
+            .map(|leaf| leaf.map(|l| (l.key(), l.value()))))
+    }
+
+    /// Iterate the values of the map in key order
+    ///
+    /// See [`KelvinMap::iter`].
+    pub fn values<'a>(
+        &'a self,
+    ) -> Result<impl Iterator<Item = Result<&'a V, CanonError>> + 'a, CanonError>
+    {
+        Ok(self.iter()?.map(|entry| entry.map(|(_, v)| v)))
+    }
+
+    /// Iterate the entries of the map in key order, with mutable references
+    /// to the values
+    ///
+    /// The annotations of the nodes are recomputed as the iteration leaves
+    /// them, so the whole map is updated in a single walk.
+    pub fn iter_mut<'a>(
+        &'a mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<(&'a K, &'a mut V), CanonError>> + 'a,
+        CanonError,
+    > {
+        Ok(First::first_mut(self)?
+            .into_iter()
+            .flatten()
+            .map(|leaf| leaf.map(Leaf::parts_mut)))
+    }
+
+    /// Iterate the values of the map in key order, as mutable references
+    ///
+    /// See [`KelvinMap::iter_mut`].
+    pub fn values_mut<'a>(
+        &'a mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<&'a mut V, CanonError>> + 'a,
+        CanonError,
+    > {
+        Ok(self.iter_mut()?.map(|entry| entry.map(|(_, v)| v)))
+    }
+
     /// Returns a mutable reference to the value corresponding to the key
     ///
     /// Will return `Ok(None)` if no correspondent key was found.
@@ -253,32 +604,185 @@ where
         &'a mut self,
         k: &K,
     ) -> Result<Option<impl DerefMut<Target = V> + 'a>, CanonError> {
-        BranchMut::walk(self, BinaryWalker(k))
+        BranchMut::walk(self, BoundedWalker::key(k))
             .map(|result| result.map(|branch| ValRefMut(branch)))
     }
 
+    /// Returns a reference to the value corresponding to the key, if the value
+    /// satisfies the provided predicate
+    ///
+    /// The predicate is evaluated inside the walk, against the stored leaf, so
+    /// values that fail the check are never handed to the caller.
+    ///
+    /// Will return `Ok(None)` if no correspondent key was found, or if the
+    /// predicate doesn't hold for its value.
+    pub fn get_if<'a, P>(
+        &'a self,
+        k: &K,
+        pred: P,
+    ) -> Result<Option<impl Deref<Target = V> + 'a>, CanonError>
+    where
+        P: FnMut(&V) -> bool,
+    {
+        Branch::walk(self, PredicateWalker(BoundedWalker::key(k), pred))
+            .map(|result| result.map(|branch| ValRef(branch)))
+    }
+
+    /// Returns the largest key of the map.
+    ///
+    /// It is read from the annotations of the root, so no traversal is
+    /// performed.
+    pub fn max_key(&self) -> Option<K> {
+        match self {
+            KelvinMap::Empty => None,
+            KelvinMap::Leaf(l) => Some(l._key().clone()),
+            KelvinMap::Node(n) => {
+                let max_l: &MaxKey<K> = n.left().annotation().borrow();
+                let max_r: &MaxKey<K> = n.right().annotation().borrow();
+
+                match cmp::max(max_l, max_r) {
+                    MaxKey::Maximum(max) => Some(max.clone()),
+                    MaxKey::NegativeInfinity => None,
+                }
+            }
+        }
+    }
+
+    /// Returns references to the entry with the smallest key, if any
+    ///
+    /// Only the leftmost path of the tree is descended.
+    pub fn first_key_value(&self) -> Result<Option<(&K, &V)>, CanonError> {
+        self.iter()?.next().transpose()
+    }
+
+    /// Returns references to the entry with the greatest key, if any
+    ///
+    /// Only the rightmost path of the tree is descended.
+    pub fn last_key_value(&self) -> Result<Option<(&K, &V)>, CanonError> {
+        let last = Branch::walk(self, RightmostWalker)?;
+
+        Ok(last
+            .into_iter()
+            .flatten()
+            .next()
+            .transpose()?
+            .map(|l| (l.key(), l.value())))
+    }
+
+    /// Returns references to the `n`-th smallest entry, if any
+    ///
+    /// The entry is reached in a single walk, skipping the sub-trees by their
+    /// cached cardinality.
+    pub fn nth(&self, n: usize) -> Result<Option<(&K, &V)>, CanonError> {
+        let nth = Nth::nth(self, n as u64)?;
+
+        Ok(nth
+            .into_iter()
+            .flatten()
+            .next()
+            .transpose()?
+            .map(|l| (l.key(), l.value())))
+    }
+
+    /// Returns the number of keys of the map lower than `k`
+    ///
+    /// Only the path to `k` is traversed, summing the cached cardinality of
+    /// the sub-trees to its left. If `k` is mapped, its entry is returned by
+    /// [`KelvinMap::nth`] with the rank as index.
+    pub fn rank(&self, k: &K) -> Result<usize, CanonError> {
+        Ok(self.count_prefix(&|key: &K| key < k)? as usize)
+    }
+
+    /// Returns the smallest key of the map.
+    ///
+    /// The leftmost path of the tree is traversed, with O(log n) complexity.
+    pub fn min_key(&self) -> Result<Option<K>, CanonError> {
+        match self {
+            KelvinMap::Empty => Ok(None),
+            KelvinMap::Leaf(l) => Ok(Some(l._key().clone())),
+            KelvinMap::Node(n) => n.left().val()?.min_key(),
+        }
+    }
+
+    /// Traverse the tree in key order, calling `f` for every leaf
+    pub(crate) fn for_each_leaf<F>(&self, f: &mut F) -> Result<(), CanonError>
+    where
+        F: FnMut(&Leaf<K, V>),
+    {
+        match self {
+            KelvinMap::Empty => Ok(()),
+            KelvinMap::Leaf(l) => {
+                f(l);
+                Ok(())
+            }
+            KelvinMap::Node(n) => {
+                n.left().val()?.for_each_leaf(f)?;
+                n.right().val()?.for_each_leaf(f)
+            }
+        }
+    }
+
+    /// Number of keys satisfying `pred`, for a predicate that holds for a
+    /// prefix of the keys in order.
+    ///
+    /// Only the path to the boundary is traversed.
+    pub(crate) fn count_prefix<P>(&self, pred: &P) -> Result<u64, CanonError>
+    where
+        P: Fn(&K) -> bool,
+    {
+        match self {
+            KelvinMap::Empty => Ok(0),
+            KelvinMap::Leaf(l) => Ok(pred(l._key()) as u64),
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let max_l: &MaxKey<K> = l.annotation().borrow();
+
+                match max_l {
+                    MaxKey::Maximum(max) if pred(max) => {
+                        let c_l: &Cardinality = l.annotation().borrow();
+                        let c_l: u64 = c_l.into();
+
+                        Ok(c_l + r.val()?.count_prefix(pred)?)
+                    }
+                    _ => l.val()?.count_prefix(pred),
+                }
+            }
+        }
+    }
+
     /// Traverse the tree to find the minimum leaf-key
     fn min_key_leaf(&self) -> Result<Option<Leaf<K, V>>, CanonError> {
         match self {
             KelvinMap::Empty => Ok(None),
             KelvinMap::Leaf(l) => Ok(Some(l.clone())),
-            KelvinMap::Node(l, _) => l.val()?.min_key_leaf(),
+            KelvinMap::Node(n) => n.left().val()?.min_key_leaf(),
         }
     }
 
     /// Traverse the tree to find the maximum leaf-key
-    fn max_key_leaf(&self) -> Result<Option<Leaf<K, V>>, CanonError> {
+    pub(crate) fn max_key_leaf(
+        &self,
+    ) -> Result<Option<Leaf<K, V>>, CanonError> {
         match self {
             KelvinMap::Empty => Ok(None),
             KelvinMap::Leaf(l) => Ok(Some(l.clone())),
-            KelvinMap::Node(_, r) => r.val()?.max_key_leaf(),
+            KelvinMap::Node(n) => n.right().val()?.max_key_leaf(),
         }
     }
 
     /// Balance the map
-    fn balance(&mut self) -> Result<(), CanonError> {
+    pub(crate) fn balance(&mut self) -> Result<(), CanonError> {
+        self.balance_with(MapConfig::DEFAULT_BALANCE_TOLERANCE)
+    }
+
+    /// Balance the map, tolerating a cardinality difference of `tolerance`
+    /// between the left and right children
+    pub(crate) fn balance_with(
+        &mut self,
+        tolerance: u64,
+    ) -> Result<(), CanonError> {
         let (l, r) = match self {
-            KelvinMap::Node(l, r) => (l, r),
+            KelvinMap::Node(n) => n.children_mut(),
             _ => return Ok(()),
         };
 
@@ -289,15 +793,15 @@ where
         let c_r: u64 = c_r.into();
 
         // TODO - Improve the performance with a tree rotation
-        let left_leaf = l.val_mut()?.max_key_leaf()?;
+        let left_leaf = l.val()?.max_key_leaf()?;
         let right_leaf = r.val()?.min_key_leaf()?;
         match (left_leaf, right_leaf) {
-            (_, Some(leaf)) if c_r > c_l.saturating_add(1) => {
+            (_, Some(leaf)) if c_r > c_l.saturating_add(tolerance) => {
                 r.val_mut()?._remove(leaf._key())?;
                 l.val_mut()?._insert(leaf)?;
             }
 
-            (Some(leaf), _) if c_l > c_r.saturating_add(1) => {
+            (Some(leaf), _) if c_l > c_r.saturating_add(tolerance) => {
                 l.val_mut()?._remove(leaf._key())?;
                 r.val_mut()?._insert(leaf)?;
             }
@@ -323,7 +827,229 @@ where
         self._remove(k)
     }
 
-    fn _remove(&mut self, k: &K) -> Result<Option<V>, CanonError> {
+    /// Remove all the mappings of the map, returning the detached tree
+    ///
+    /// No node is visited. The detached tree can be dropped, or used to tell
+    /// the backing store which nodes are no longer referenced by the map.
+    pub fn clear(&mut self) -> Self {
+        mem::take(self)
+    }
+
+    /// Move all the entries of `other` into the map, leaving `other` empty.
+    ///
+    /// If all the keys of one map are lower than the keys of the other, and
+    /// their lengths are within the balance tolerance, both trees are grafted
+    /// as children of a new root without visiting their leaves. Otherwise,
+    /// the leaves of both maps are merged in key order, taking the values of
+    /// `other` for the keys present in both, and the map is rebuilt balanced
+    /// out of them.
+    pub fn append(&mut self, other: &mut Self) -> Result<(), CanonError> {
+        let other = other.clear();
+
+        let (min, max) = match (self.min_key()?, self.max_key()) {
+            (Some(min), Some(max)) => (min, max),
+            _ => {
+                *self = other;
+                return Ok(());
+            }
+        };
+        let (other_min, other_max) = match (other.min_key()?, other.max_key()) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Ok(()),
+        };
+
+        let (low, high) = if max < other_min {
+            (mem::take(self), other)
+        } else if other_max < min {
+            (other, mem::take(self))
+        } else {
+            let leaves = other
+                .into_iter()
+                .map(|entry| entry.map(|(k, v)| Leaf::new(k, v)))
+                .collect::<Result<_, _>>()?;
+
+            return self.merge_sorted_leaves(leaves, |_, _, leaf| leaf);
+        };
+
+        let len = low.len() + high.len();
+        let tolerance = MapConfig::DEFAULT_BALANCE_TOLERANCE as usize;
+
+        if low.len().abs_diff(high.len()) <= tolerance {
+            *self = KelvinMap::node(low, high);
+            return Ok(());
+        }
+
+        let mut leaves = Vec::with_capacity(len);
+        low.for_each_leaf(&mut |leaf| leaves.push(leaf.clone()))?;
+        high.for_each_leaf(&mut |leaf| leaves.push(leaf.clone()))?;
+
+        *self = Self::from_sorted_leaves(&mut leaves.into_iter(), len);
+
+        Ok(())
+    }
+
+    /// Rebuild the map balanced out of its leaves merged with `leaves`, which
+    /// must be sorted by strictly ascending keys.
+    ///
+    /// For every key present in both, `f` is called with the position of the
+    /// key in `leaves`, the leaf of the map and the leaf of `leaves`, and
+    /// returns the leaf to keep.
+    pub(crate) fn merge_sorted_leaves<F>(
+        &mut self,
+        leaves: Vec<Leaf<K, V>>,
+        mut f: F,
+    ) -> Result<(), CanonError>
+    where
+        F: FnMut(usize, Leaf<K, V>, Leaf<K, V>) -> Leaf<K, V>,
+    {
+        let mut own = Vec::with_capacity(self.len());
+        self.for_each_leaf(&mut |leaf| own.push(leaf.clone()))?;
+
+        let mut merged = Vec::with_capacity(own.len() + leaves.len());
+        let mut own = own.into_iter().peekable();
+
+        for (i, leaf) in leaves.into_iter().enumerate() {
+            while let Some(l) = own.next_if(|l| l._key() < leaf._key()) {
+                merged.push(l);
+            }
+
+            match own.next_if(|l| l._key() == leaf._key()) {
+                Some(l) => merged.push(f(i, l, leaf)),
+                None => merged.push(leaf),
+            }
+        }
+        merged.extend(own);
+
+        let len = merged.len();
+        *self = Self::from_sorted_leaves(&mut merged.into_iter(), len);
+
+        Ok(())
+    }
+
+    /// Merge the entries of `other` into the map, calling `f` with the key,
+    /// the value in the map and the value in `other` for every key present
+    /// in both, and storing its result.
+    ///
+    /// The leaves of both maps are merged in key order and the map is rebuilt
+    /// balanced out of them, so the cost is linear in the size of both maps.
+    pub fn merge<F>(&mut self, other: &Self, mut f: F) -> Result<(), CanonError>
+    where
+        F: FnMut(&K, &V, &V) -> V,
+    {
+        let mut leaves = Vec::with_capacity(other.len());
+        other.for_each_leaf(&mut |leaf| leaves.push(leaf.clone()))?;
+
+        self.merge_sorted_leaves(leaves, |_, own, leaf| {
+            let v = f(own._key(), own.value(), leaf.value());
+            let (k, _) = own.into_parts();
+
+            Leaf::new(k, v)
+        })
+    }
+
+    /// Remove the mappings of all the provided keys, returning the number of
+    /// removed mappings.
+    ///
+    /// The keys are sorted and removed in a single ordered pass over the tree,
+    /// followed by a single naive balancing of the root, instead of balancing
+    /// once per removed key.
+    pub fn remove_many(&mut self, keys: &[K]) -> Result<u64, CanonError> {
+        let mut keys: Vec<&K> = keys.iter().collect();
+        keys.sort();
+        keys.dedup();
+
+        let removed = self.remove_sorted(&keys)?;
+        self.balance()?;
+
+        Ok(removed)
+    }
+
+    /// Retain only the entries for which `f` returns `true`, returning the
+    /// number of removed entries.
+    ///
+    /// The entries are visited in key order in a single pass over the tree,
+    /// followed by a single naive balancing of the root.
+    pub fn retain<F>(&mut self, mut f: F) -> Result<u64, CanonError>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let removed = self.retain_leaves(&mut f)?;
+        self.balance()?;
+
+        Ok(removed)
+    }
+
+    fn retain_leaves<F>(&mut self, f: &mut F) -> Result<u64, CanonError>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        match self {
+            KelvinMap::Empty => Ok(0),
+
+            KelvinMap::Leaf(leaf) => {
+                let (k, v) = leaf.parts_mut();
+
+                if f(k, v) {
+                    Ok(0)
+                } else {
+                    *self = KelvinMap::Empty;
+                    Ok(1)
+                }
+            }
+
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children_mut();
+                let removed = l.val_mut()?.retain_leaves(f)?
+                    + r.val_mut()?.retain_leaves(f)?;
+
+                self.collapse()?;
+                self.refresh_len();
+
+                Ok(removed)
+            }
+        }
+    }
+
+    /// Remove the mappings of a sorted list of keys from the sub-tree
+    fn remove_sorted(&mut self, keys: &[&K]) -> Result<u64, CanonError> {
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        match self {
+            KelvinMap::Empty => Ok(0),
+
+            KelvinMap::Leaf(leaf) => {
+                if keys.binary_search(&leaf._key()).is_ok() {
+                    *self = KelvinMap::Empty;
+                    Ok(1)
+                } else {
+                    Ok(0)
+                }
+            }
+
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children_mut();
+                let split = keys.partition_point(|k| cmp_max_key(l, k).is_ge());
+                let (keys_l, keys_r) = keys.split_at(split);
+
+                let mut removed = 0;
+                if !keys_l.is_empty() {
+                    removed += l.val_mut()?.remove_sorted(keys_l)?;
+                }
+                if !keys_r.is_empty() {
+                    removed += r.val_mut()?.remove_sorted(keys_r)?;
+                }
+
+                self.collapse()?;
+                self.refresh_len();
+
+                Ok(removed)
+            }
+        }
+    }
+
+    pub(crate) fn _remove(&mut self, k: &K) -> Result<Option<V>, CanonError> {
         match self {
             KelvinMap::Empty => Ok(None),
 
@@ -336,44 +1062,50 @@ where
             }
             KelvinMap::Leaf(_) => Ok(None),
 
-            KelvinMap::Node(l, r) => {
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children_mut();
                 let mut old = None;
 
                 // If the key is the left child, take its value and move the right child to current
                 // node
-                if let KelvinMap::Leaf(leaf) = &mut *l.val_mut()? {
+                if let KelvinMap::Leaf(leaf) = &*l.val()? {
                     if leaf._key() == k {
                         old.replace(leaf.value().clone());
                     }
                 }
 
                 if old.is_some() {
-                    let new = mem::take(&mut *r.val_mut()?);
+                    let new = r.val()?.clone();
                     *self = new;
                     return Ok(old);
                 }
 
                 // If the key is the right child, take its value and move the left child to current
                 // node
-                if let KelvinMap::Leaf(leaf) = &mut *r.val_mut()? {
+                if let KelvinMap::Leaf(leaf) = &*r.val()? {
                     if leaf._key() == k {
                         old.replace(leaf.value().clone());
                     }
                 }
 
                 if old.is_some() {
-                    let new = mem::take(&mut *l.val_mut()?);
+                    let new = l.val()?.clone();
                     *self = new;
                     return Ok(old);
                 }
 
-                if cmp_max_key(l, k).is_ge() {
-                    l.val_mut()?.remove(k)
+                let old = if cmp_max_key(l, k).is_ge() {
+                    l.val_mut()?.remove(k)?
                 } else if cmp_max_key(r, k).is_ge() {
-                    r.val_mut()?.remove(k)
+                    r.val_mut()?.remove(k)?
                 } else {
-                    Ok(None)
-                }
+                    None
+                };
+
+                self.collapse()?;
+                self.refresh_len();
+
+                Ok(old)
             }
         }
     }
@@ -394,7 +1126,60 @@ where
         self._insert(leaf)
     }
 
-    fn _insert(&mut self, leaf: Leaf<K, V>) -> Result<Option<V>, CanonError> {
+    /// Include all the key -> value mappings of `iter` to the map, returning
+    /// the values they displaced, in the order of `iter`.
+    ///
+    /// The batch is sorted, and merged in key order with the leaves of the
+    /// map, which is then rebuilt balanced bottom-up in a single pass instead
+    /// of being balanced on every insertion.
+    pub fn insert_batch<I>(
+        &mut self,
+        iter: I,
+    ) -> Result<Vec<Option<V>>, CanonError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut batch: Vec<(usize, Leaf<K, V>)> = iter
+            .into_iter()
+            .map(|(k, v)| Leaf::new(k, v))
+            .enumerate()
+            .collect();
+        let mut displaced = vec![None; batch.len()];
+
+        // Stable, so the repeated keys keep their order in the batch
+        batch.sort_by(|(_, a), (_, b)| a._key().cmp(b._key()));
+
+        // Position in the batch of the first insertion of every key
+        let mut firsts = Vec::with_capacity(batch.len());
+        let mut leaves: Vec<Leaf<K, V>> = Vec::with_capacity(batch.len());
+
+        for (i, leaf) in batch {
+            match leaves.last_mut() {
+                Some(last) if last._key() == leaf._key() => {
+                    let (_, v) = mem::replace(last, leaf).into_parts();
+                    displaced[i] = Some(v);
+                }
+                _ => {
+                    firsts.push(i);
+                    leaves.push(leaf);
+                }
+            }
+        }
+
+        if !leaves.is_empty() {
+            self.merge_sorted_leaves(leaves, |pos, own, leaf| {
+                displaced[firsts[pos]] = Some(own.into_parts().1);
+                leaf
+            })?;
+        }
+
+        Ok(displaced)
+    }
+
+    pub(crate) fn _insert(
+        &mut self,
+        leaf: Leaf<K, V>,
+    ) -> Result<Option<V>, CanonError> {
         let mut old = None;
 
         match self {
@@ -406,30 +1191,34 @@ where
             }
 
             KelvinMap::Leaf(l) if l._key() < leaf._key() => {
-                let left = Annotated::new(mem::take(self));
-                let right = Annotated::new(KelvinMap::Leaf(leaf));
+                let left = mem::take(self);
+                let right = KelvinMap::Leaf(leaf);
 
-                *self = KelvinMap::Node(left, right);
+                *self = KelvinMap::node(left, right);
             }
 
             KelvinMap::Leaf(l) if leaf._key() < l._key() => {
-                let left = Annotated::new(KelvinMap::Leaf(leaf));
-                let right = Annotated::new(mem::take(self));
+                let left = KelvinMap::Leaf(leaf);
+                let right = mem::take(self);
 
-                *self = KelvinMap::Node(left, right);
+                *self = KelvinMap::node(left, right);
             }
 
-            KelvinMap::Node(l, _) if cmp_max_key(l, leaf._key()).is_ge() => {
-                old = l.val_mut()?._insert(leaf)?;
-            }
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children_mut();
 
-            KelvinMap::Node(l, r) if cmp_max_key(l, leaf._key()).is_lt() => {
-                old = r.val_mut()?._insert(leaf)?;
+                if cmp_max_key(l, leaf._key()).is_ge() {
+                    old = l.val_mut()?._insert(leaf)?;
+                } else {
+                    old = r.val_mut()?._insert(leaf)?;
+                }
             }
 
             _ => return Err(CanonError::InvalidEncoding),
         }
 
+        self.refresh_len();
+
         Ok(old)
     }
 }
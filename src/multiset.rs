@@ -0,0 +1,197 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation};
+
+use core::borrow::Borrow;
+
+use canonical::{Canon, CanonError};
+use canonical_derive::Canon;
+use microkelvin::{Annotation, Cardinality, Combine, Compound, MaxKey};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Canon)]
+/// Annotation with the sum of the occurrence counts stored in the leaves of a
+/// sub-tree
+pub struct DupCount(u64);
+
+impl From<DupCount> for u64 {
+    fn from(c: DupCount) -> Self {
+        c.0
+    }
+}
+
+impl<K> Annotation<Leaf<K, u64>> for DupCount
+where
+    K: Ord,
+{
+    fn from_leaf(leaf: &Leaf<K, u64>) -> Self {
+        DupCount(*leaf.value())
+    }
+}
+
+impl<K, A> Combine<KelvinMap<K, u64, A>, A> for DupCount
+where
+    K: Canon + Ord,
+    A: MapAnnotation<K, u64> + Borrow<DupCount>,
+{
+    fn combine(node: &KelvinMap<K, u64, A>) -> Self {
+        DupCount(
+            node.children()
+                .map(|child| {
+                    let annotation = child.annotation();
+                    let count: &DupCount = (*annotation).borrow();
+                    count.0
+                })
+                .fold(0u64, |sum, count| sum.saturating_add(count)),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Canon)]
+/// Annotation of a [`KelvinMultiSet`]
+pub struct MultiSetAnnotation<K>
+where
+    K: Canon + Ord + Default,
+{
+    cardinality: Cardinality,
+    max: MaxKey<K>,
+    count: DupCount,
+}
+
+impl<K> Borrow<MaxKey<K>> for MultiSetAnnotation<K>
+where
+    K: Canon + Ord + Default,
+{
+    fn borrow(&self) -> &MaxKey<K> {
+        &self.max
+    }
+}
+
+impl<K> Borrow<Cardinality> for MultiSetAnnotation<K>
+where
+    K: Canon + Ord + Default,
+{
+    fn borrow(&self) -> &Cardinality {
+        &self.cardinality
+    }
+}
+
+impl<K> Borrow<DupCount> for MultiSetAnnotation<K>
+where
+    K: Canon + Ord + Default,
+{
+    fn borrow(&self) -> &DupCount {
+        &self.count
+    }
+}
+
+impl<K> Annotation<Leaf<K, u64>> for MultiSetAnnotation<K>
+where
+    K: Canon + Ord + Default,
+{
+    fn from_leaf(leaf: &Leaf<K, u64>) -> Self {
+        let cardinality = Cardinality::from_leaf(leaf);
+        let max = MaxKey::from_leaf(leaf);
+        let count = DupCount::from_leaf(leaf);
+
+        Self {
+            cardinality,
+            max,
+            count,
+        }
+    }
+}
+
+impl<K> Combine<KelvinMap<K, u64, MultiSetAnnotation<K>>, MultiSetAnnotation<K>>
+    for MultiSetAnnotation<K>
+where
+    K: Canon + Ord + Default,
+{
+    fn combine(node: &KelvinMap<K, u64, MultiSetAnnotation<K>>) -> Self {
+        let cardinality = Cardinality::combine(node);
+        let max = MaxKey::combine(node);
+        let count = DupCount::combine(node);
+
+        Self {
+            cardinality,
+            max,
+            count,
+        }
+    }
+}
+
+impl<K> MapAnnotation<K, u64> for MultiSetAnnotation<K> where
+    K: Canon + Ord + Default
+{
+}
+
+#[derive(Debug, Clone, Default, Canon)]
+/// Ordered multiset backed by a [`KelvinMap`] from the elements to their
+/// number of occurrences.
+///
+/// The total number of occurrences is tracked by the [`DupCount`] annotation.
+pub struct KelvinMultiSet<K>
+where
+    K: Canon + Ord + Default,
+{
+    map: KelvinMap<K, u64, MultiSetAnnotation<K>>,
+}
+
+impl<K> KelvinMultiSet<K>
+where
+    K: Canon + Ord + Default,
+{
+    /// Total number of occurrences of all the elements of the multiset
+    pub fn len(&self) -> u64 {
+        DupCount::combine(&self.map).into()
+    }
+
+    /// Check if the multiset is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Number of distinct elements of the multiset
+    pub fn distinct(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Number of occurrences of `k`
+    pub fn count(&self, k: &K) -> Result<u64, CanonError> {
+        Ok(self.map.get(k)?.map(|count| *count).unwrap_or_default())
+    }
+
+    /// Include an occurrence of `k`, returning the new number of occurrences
+    pub fn insert(&mut self, k: K) -> Result<u64, CanonError> {
+        if let Some(mut count) = self.map.get_mut(&k)? {
+            *count = count.saturating_add(1);
+            return Ok(*count);
+        }
+
+        self.map.insert(k, 1)?;
+
+        Ok(1)
+    }
+
+    /// Remove an occurrence of `k`.
+    ///
+    /// Returns `Ok(true)` if `k` was present in the multiset.
+    pub fn remove_one(&mut self, k: &K) -> Result<bool, CanonError> {
+        if let Some(mut count) = self.map.get_mut(k)? {
+            if *count > 1 {
+                *count -= 1;
+                return Ok(true);
+            }
+        }
+
+        self.map.remove(k).map(|old| old.is_some())
+    }
+
+    /// Underlying map from the elements to their number of occurrences
+    pub fn as_map(&self) -> &KelvinMap<K, u64, MultiSetAnnotation<K>> {
+        &self.map
+    }
+}
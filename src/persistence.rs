@@ -0,0 +1,41 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use alloc::vec::Vec;
+
+use canonical::{Canon, CanonError, Id};
+use microkelvin::{Compound, IterChild};
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Id of the root node, as persisted in the store.
+    ///
+    /// Will return `None` for an empty map.
+    pub fn root_id(&self) -> Option<Id> {
+        match self {
+            KelvinMap::Empty => None,
+            _ => Some(Id::new(self)),
+        }
+    }
+
+    /// Ids of the child nodes of the root, in key order.
+    ///
+    /// The children are fetched from the store if they were not loaded yet.
+    pub fn child_ids(&self) -> Result<Vec<Id>, CanonError> {
+        self.children()
+            .filter_map(|child| match child {
+                IterChild::Node(n) => Some(n.val().map(|n| Id::new(&*n))),
+                _ => None,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,59 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{Digest, MapHasher};
+
+use alloc::vec::Vec;
+
+use canonical_derive::Canon;
+use dusk_bls12_381::BlsScalar;
+use dusk_bytes::Serializable;
+use dusk_poseidon::sponge;
+
+/// Number of bytes packed into a scalar, so every chunk is lower than the
+/// modulus of the BLS12-381 scalar field
+pub const SCALAR_CHUNK: usize = 31;
+
+/// Scalar of a chunk of at most [`SCALAR_CHUNK`] bytes, read in little endian
+fn chunk_scalar(chunk: &[u8]) -> BlsScalar {
+    let mut bytes = [0u8; 32];
+    bytes[..chunk.len()].copy_from_slice(chunk);
+
+    let mut limbs = [0u64; 4];
+    for (limb, word) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+        let mut le = [0u8; 8];
+        le.copy_from_slice(word);
+        *limb = u64::from_le_bytes(le);
+    }
+
+    BlsScalar::from_raw(limbs)
+}
+
+/// BLS12-381 scalars encoding `bytes`: their length, followed by their
+/// little endian chunks of [`SCALAR_CHUNK`] bytes
+pub fn to_scalars(bytes: &[u8]) -> Vec<BlsScalar> {
+    let mut scalars = Vec::with_capacity(1 + bytes.len() / SCALAR_CHUNK + 1);
+
+    scalars.push(BlsScalar::from(bytes.len() as u64));
+    scalars.extend(bytes.chunks(SCALAR_CHUNK).map(chunk_scalar));
+
+    scalars
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Canon)]
+/// [`MapHasher`] hashing with the Poseidon sponge over BLS12-381 scalars, so
+/// the commitments of a map can be recomputed inside PLONK circuits.
+///
+/// The hashed bytes, such as the domain-separated encoding of a leaf, are
+/// packed into scalars by [`to_scalars`], and the digest is the canonical
+/// encoding of the resulting scalar.
+pub struct PoseidonHasher;
+
+impl MapHasher for PoseidonHasher {
+    fn hash(bytes: &[u8]) -> Digest {
+        sponge::hash(&to_scalars(bytes)).to_bytes()
+    }
+}
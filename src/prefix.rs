@@ -0,0 +1,502 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use alloc::vec::Vec;
+
+use canonical::{Canon, CanonError, Sink, Source};
+
+/// Length of the keys of a [`Bytes32Map`]
+const KEY_LEN: usize = 32;
+
+/// Maximum number of children of a branch, one per value of a byte
+const MAX_CHILDREN: usize = 256;
+
+/// Length of the prefix shared by `a` and `b`
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+fn encode_bytes(bytes: &[u8], sink: &mut Sink) {
+    (bytes.len() as u8).encode(sink);
+    bytes.iter().for_each(|b| b.encode(sink));
+}
+
+/// Decode a byte string of exactly `len` bytes
+fn decode_bytes(
+    source: &mut Source,
+    len: usize,
+) -> Result<Vec<u8>, CanonError> {
+    if u8::decode(source)? as usize != len {
+        return Err(CanonError::InvalidEncoding);
+    }
+
+    (0..len).map(|_| u8::decode(source)).collect()
+}
+
+#[derive(Debug, Clone)]
+/// Node of a [`Bytes32Map`], storing only the bytes of its keys that follow
+/// the ones stored by its ancestors
+enum PrefixNode<V> {
+    /// Entry, with the bytes of its key not stored by the ancestors
+    Leaf { suffix: Vec<u8>, value: V },
+    /// Sub-tree with the bytes shared by all its keys, and at least two
+    /// children sorted by the byte following them
+    Branch {
+        prefix: Vec<u8>,
+        children: Vec<(u8, PrefixNode<V>)>,
+    },
+}
+
+impl<V> PrefixNode<V> {
+    /// Branch with `prefix` over two children selected by distinct bytes
+    fn fork(prefix: &[u8], a: (u8, Self), b: (u8, Self)) -> Self {
+        let children = if a.0 < b.0 {
+            [a, b].into()
+        } else {
+            [b, a].into()
+        };
+
+        PrefixNode::Branch {
+            prefix: prefix.to_vec(),
+            children,
+        }
+    }
+
+    /// Branch with `prefix` over `children`, merged into its only child if
+    /// there is one left
+    fn branch(mut prefix: Vec<u8>, mut children: Vec<(u8, Self)>) -> Self {
+        if children.len() == 1 {
+            if let Some((byte, child)) = children.pop() {
+                prefix.push(byte);
+
+                return match child {
+                    PrefixNode::Leaf { suffix, value } => {
+                        prefix.extend(suffix);
+                        PrefixNode::Leaf {
+                            suffix: prefix,
+                            value,
+                        }
+                    }
+                    PrefixNode::Branch {
+                        prefix: tail,
+                        children,
+                    } => {
+                        prefix.extend(tail);
+                        PrefixNode::Branch { prefix, children }
+                    }
+                };
+            }
+        }
+
+        PrefixNode::Branch { prefix, children }
+    }
+
+    /// Position of the child following `prefix` in `rest`, along with the
+    /// bytes of the key left to it
+    fn child<'k>(
+        prefix: &[u8],
+        children: &[(u8, Self)],
+        rest: &'k [u8],
+    ) -> Option<(usize, &'k [u8])> {
+        let (byte, tail) = rest.strip_prefix(prefix)?.split_first()?;
+
+        children
+            .binary_search_by_key(byte, |(b, _)| *b)
+            .ok()
+            .map(|i| (i, tail))
+    }
+
+    fn get(&self, rest: &[u8]) -> Option<&V> {
+        match self {
+            PrefixNode::Leaf { suffix, value } if suffix[..] == *rest => {
+                Some(value)
+            }
+            PrefixNode::Leaf { .. } => None,
+            PrefixNode::Branch { prefix, children } => {
+                let (i, tail) = Self::child(prefix, children, rest)?;
+                children[i].1.get(tail)
+            }
+        }
+    }
+
+    fn get_mut(&mut self, rest: &[u8]) -> Option<&mut V> {
+        match self {
+            PrefixNode::Leaf { suffix, value } if suffix[..] == *rest => {
+                Some(value)
+            }
+            PrefixNode::Leaf { .. } => None,
+            PrefixNode::Branch { prefix, children } => {
+                let (i, tail) = Self::child(prefix, children, rest)?;
+                children[i].1.get_mut(tail)
+            }
+        }
+    }
+
+    /// Insert `value` under the key ending with `rest`, returning the new
+    /// node and the replaced value
+    fn inserted(self, rest: &[u8], value: V) -> (Self, Option<V>) {
+        match self {
+            PrefixNode::Leaf { suffix, value: old } if suffix[..] == *rest => {
+                (PrefixNode::Leaf { suffix, value }, Some(old))
+            }
+            PrefixNode::Leaf { suffix, value: old } => {
+                let p = common_prefix(&suffix, rest);
+                let old = PrefixNode::Leaf {
+                    suffix: suffix[p + 1..].to_vec(),
+                    value: old,
+                };
+                let new = PrefixNode::Leaf {
+                    suffix: rest[p + 1..].to_vec(),
+                    value,
+                };
+
+                (
+                    Self::fork(&rest[..p], (suffix[p], old), (rest[p], new)),
+                    None,
+                )
+            }
+            PrefixNode::Branch {
+                prefix,
+                mut children,
+            } => {
+                let p = common_prefix(&prefix, rest);
+                let new = |value| PrefixNode::Leaf {
+                    suffix: rest[p + 1..].to_vec(),
+                    value,
+                };
+
+                // The key leaves the shared prefix, so the branch is split
+                if p < prefix.len() {
+                    let byte = prefix[p];
+                    let old = PrefixNode::Branch {
+                        prefix: prefix[p + 1..].to_vec(),
+                        children,
+                    };
+
+                    let node = Self::fork(
+                        &rest[..p],
+                        (byte, old),
+                        (rest[p], new(value)),
+                    );
+
+                    return (node, None);
+                }
+
+                match children.binary_search_by_key(&rest[p], |(b, _)| *b) {
+                    Ok(i) => {
+                        let (byte, child) = children.remove(i);
+                        let (child, old) =
+                            child.inserted(&rest[p + 1..], value);
+
+                        children.insert(i, (byte, child));
+                        (PrefixNode::Branch { prefix, children }, old)
+                    }
+                    Err(i) => {
+                        children.insert(i, (rest[p], new(value)));
+                        (PrefixNode::Branch { prefix, children }, None)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Remove the key ending with `rest`, returning the node left, if any,
+    /// and the removed value
+    fn removed(self, rest: &[u8]) -> (Option<Self>, Option<V>) {
+        match self {
+            PrefixNode::Leaf { suffix, value } if suffix[..] == *rest => {
+                (None, Some(value))
+            }
+            leaf @ PrefixNode::Leaf { .. } => (Some(leaf), None),
+            PrefixNode::Branch {
+                prefix,
+                mut children,
+            } => {
+                let (i, tail) = match Self::child(&prefix, &children, rest) {
+                    Some(found) => found,
+                    None => {
+                        return (
+                            Some(PrefixNode::Branch { prefix, children }),
+                            None,
+                        )
+                    }
+                };
+
+                let (byte, child) = children.remove(i);
+                let (child, removed) = child.removed(tail);
+
+                if let Some(child) = child {
+                    children.insert(i, (byte, child));
+                }
+
+                (Some(Self::branch(prefix, children)), removed)
+            }
+        }
+    }
+
+    fn leaves(&self) -> usize {
+        match self {
+            PrefixNode::Leaf { .. } => 1,
+            PrefixNode::Branch { children, .. } => {
+                children.iter().map(|(_, child)| child.leaves()).sum()
+            }
+        }
+    }
+}
+
+impl<V> PrefixNode<V>
+where
+    V: Canon,
+{
+    fn encode(&self, sink: &mut Sink) {
+        match self {
+            PrefixNode::Leaf { suffix, value } => {
+                0u8.encode(sink);
+                encode_bytes(suffix, sink);
+                value.encode(sink);
+            }
+            PrefixNode::Branch { prefix, children } => {
+                1u8.encode(sink);
+                encode_bytes(prefix, sink);
+                (children.len() as u16).encode(sink);
+
+                for (byte, child) in children {
+                    byte.encode(sink);
+                    child.encode(sink);
+                }
+            }
+        }
+    }
+
+    /// Decode a node holding the last `rest` bytes of its keys, rejecting
+    /// the encodings that don't sum up to whole, distinct and sorted keys
+    fn decode(source: &mut Source, rest: usize) -> Result<Self, CanonError> {
+        match u8::decode(source)? {
+            0 => {
+                let suffix = decode_bytes(source, rest)?;
+                let value = V::decode(source)?;
+
+                Ok(PrefixNode::Leaf { suffix, value })
+            }
+            1 => {
+                let len = u8::decode(source)? as usize;
+                if len >= rest {
+                    return Err(CanonError::InvalidEncoding);
+                }
+
+                let prefix = (0..len)
+                    .map(|_| u8::decode(source))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let count = u16::decode(source)? as usize;
+                if !(2..=MAX_CHILDREN).contains(&count) {
+                    return Err(CanonError::InvalidEncoding);
+                }
+
+                let mut children: Vec<(u8, Self)> = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let byte = u8::decode(source)?;
+
+                    if matches!(children.last(), Some((last, _)) if *last >= byte)
+                    {
+                        return Err(CanonError::InvalidEncoding);
+                    }
+
+                    let child = Self::decode(source, rest - len - 1)?;
+                    children.push((byte, child));
+                }
+
+                Ok(PrefixNode::Branch { prefix, children })
+            }
+            _ => Err(CanonError::InvalidEncoding),
+        }
+    }
+
+    fn encoded_len(&self) -> usize {
+        match self {
+            PrefixNode::Leaf { suffix, value } => {
+                2 + suffix.len() + value.encoded_len()
+            }
+            PrefixNode::Branch { prefix, children } => {
+                4 + prefix.len()
+                    + children
+                        .iter()
+                        .map(|(_, child)| 1 + child.encoded_len())
+                        .sum::<usize>()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Map keyed by 32-byte arrays, such as hashes, with prefix compression.
+///
+/// Every branch of the tree stores the bytes shared by all the keys of its
+/// sub-tree once, and every leaf only the suffix of its key left by its
+/// ancestors. Hash-keyed maps sharing long prefixes are persisted smaller,
+/// and a lookup compares every byte of the key at most once.
+pub struct Bytes32Map<V> {
+    root: Option<PrefixNode<V>>,
+    len: usize,
+}
+
+impl<V> Default for Bytes32Map<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Bytes32Map<V> {
+    /// Create a new empty map
+    pub const fn new() -> Self {
+        Self { root: None, len: 0 }
+    }
+
+    /// Number of entries of the map
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a reference to the value corresponding to the key
+    pub fn get(&self, k: &[u8; KEY_LEN]) -> Option<&V> {
+        self.root.as_ref()?.get(k)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key
+    pub fn get_mut(&mut self, k: &[u8; KEY_LEN]) -> Option<&mut V> {
+        self.root.as_mut()?.get_mut(k)
+    }
+
+    /// Check if the map contains a mapping for the key
+    pub fn contains_key(&self, k: &[u8; KEY_LEN]) -> bool {
+        self.get(k).is_some()
+    }
+
+    /// Include a key -> value mapping to the map, returning the previous
+    /// value of the key, if any
+    pub fn insert(&mut self, k: [u8; KEY_LEN], v: V) -> Option<V> {
+        let (root, old) = match self.root.take() {
+            Some(root) => root.inserted(&k, v),
+            None => {
+                let leaf = PrefixNode::Leaf {
+                    suffix: k.to_vec(),
+                    value: v,
+                };
+
+                (leaf, None)
+            }
+        };
+
+        self.root = Some(root);
+        if old.is_none() {
+            self.len += 1;
+        }
+
+        old
+    }
+
+    /// Remove a key -> value mapping from the map, returning the removed
+    /// value, if any
+    pub fn remove(&mut self, k: &[u8; KEY_LEN]) -> Option<V> {
+        let (root, removed) = match self.root.take() {
+            Some(root) => root.removed(k),
+            None => (None, None),
+        };
+
+        self.root = root;
+        if removed.is_some() {
+            self.len -= 1;
+        }
+
+        removed
+    }
+
+    /// Iterate over the entries of the map in key order
+    pub fn iter(&self) -> Bytes32Iter<V> {
+        let stack = self.root.iter().map(|root| (0, None, root)).collect();
+
+        Bytes32Iter {
+            stack,
+            key: [0u8; KEY_LEN],
+        }
+    }
+}
+
+impl<V> Canon for Bytes32Map<V>
+where
+    V: Canon,
+{
+    fn encode(&self, sink: &mut Sink) {
+        match &self.root {
+            None => 0u8.encode(sink),
+            Some(root) => {
+                1u8.encode(sink);
+                root.encode(sink);
+            }
+        }
+    }
+
+    fn decode(source: &mut Source) -> Result<Self, CanonError> {
+        let root = match u8::decode(source)? {
+            0 => None,
+            1 => Some(PrefixNode::decode(source, KEY_LEN)?),
+            _ => return Err(CanonError::InvalidEncoding),
+        };
+        let len = root.as_ref().map(PrefixNode::leaves).unwrap_or_default();
+
+        Ok(Self { root, len })
+    }
+
+    fn encoded_len(&self) -> usize {
+        1 + self.root.as_ref().map(PrefixNode::encoded_len).unwrap_or(0)
+    }
+}
+
+/// Iterator over the entries of a [`Bytes32Map`] in key order, rebuilding
+/// every key out of the bytes stored along its path
+pub struct Bytes32Iter<'a, V> {
+    // Nodes left to visit, with the offset of their first byte within the
+    // key, and the byte selecting them from their parent
+    stack: Vec<(usize, Option<u8>, &'a PrefixNode<V>)>,
+    key: [u8; KEY_LEN],
+}
+
+impl<'a, V> Iterator for Bytes32Iter<'a, V> {
+    type Item = ([u8; KEY_LEN], &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((offset, byte, node)) = self.stack.pop() {
+            if let Some(byte) = byte {
+                self.key[offset - 1] = byte;
+            }
+
+            match node {
+                PrefixNode::Leaf { suffix, value } => {
+                    self.key[offset..].copy_from_slice(suffix);
+                    return Some((self.key, value));
+                }
+                PrefixNode::Branch { prefix, children } => {
+                    let end = offset + prefix.len();
+                    self.key[offset..end].copy_from_slice(prefix);
+
+                    self.stack.extend(
+                        children
+                            .iter()
+                            .rev()
+                            .map(|(byte, child)| (end + 1, Some(*byte), child)),
+                    );
+                }
+            }
+        }
+
+        None
+    }
+}
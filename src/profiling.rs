@@ -0,0 +1,126 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::Reverse;
+use core::ops::Deref;
+
+use canonical::{Canon, CanonError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Key range of a map with the number of reads of its keys, as reported by
+/// [`ProfiledMap::hottest_ranges`]
+pub struct HotRange<K> {
+    /// Smallest key of the range
+    pub first: K,
+    /// Largest key of the range
+    pub last: K,
+    /// Number of reads of the keys within the range
+    pub reads: u64,
+}
+
+/// [`KelvinMap`] counting the reads of each of its keys, to find skewed
+/// access patterns.
+///
+/// The counters are kept in memory only, and never persisted along the map.
+///
+/// Read access to the underlying map is provided via `Deref`, bypassing the
+/// counters.
+pub struct ProfiledMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    map: KelvinMap<K, V, A>,
+    reads: RefCell<BTreeMap<K, u64>>,
+}
+
+impl<K, V, A> ProfiledMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Start counting the reads of `map`
+    pub fn new(map: KelvinMap<K, V, A>) -> Self {
+        Self {
+            map,
+            reads: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Stop counting and return the underlying map
+    pub fn into_inner(self) -> KelvinMap<K, V, A> {
+        self.map
+    }
+
+    /// Returns a reference to the value corresponding to the key, counting
+    /// the read even if the key is not in the map
+    pub fn get<'a>(
+        &'a self,
+        k: &K,
+    ) -> Result<Option<impl Deref<Target = V> + 'a>, CanonError> {
+        *self.reads.borrow_mut().entry(k.clone()).or_insert(0) += 1;
+
+        self.map.get(k)
+    }
+
+    /// Number of reads of `k`
+    pub fn reads(&self, k: &K) -> u64 {
+        self.reads.borrow().get(k).copied().unwrap_or(0)
+    }
+
+    /// Reset all the counters
+    pub fn reset(&self) {
+        self.reads.borrow_mut().clear();
+    }
+
+    /// Split the map into `n` key ranges of equal length, as
+    /// [`KelvinMap::shard`], and return them sorted by number of reads in
+    /// descending order.
+    ///
+    /// Reads of keys that are not in the map, or between two ranges, are not
+    /// accounted.
+    pub fn hottest_ranges(
+        &self,
+        n: usize,
+    ) -> Result<Vec<HotRange<K>>, CanonError> {
+        let reads = self.reads.borrow();
+        let mut ranges = Vec::with_capacity(n);
+
+        for shard in self.map.shard(n)? {
+            if let (Some(first), Some(last)) =
+                (shard.min_key()?, shard.max_key())
+            {
+                let reads = reads.range(&first..=&last).map(|(_, r)| r).sum();
+
+                ranges.push(HotRange { first, last, reads });
+            }
+        }
+
+        ranges.sort_by_key(|r| Reverse(r.reads));
+
+        Ok(ranges)
+    }
+}
+
+impl<K, V, A> Deref for ProfiledMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    type Target = KelvinMap<K, V, A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
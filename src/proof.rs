@@ -0,0 +1,400 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::bounded::BoundedWalker;
+use crate::{
+    node_hash, Digest, KelvinMap, Leaf, MapAnnotation, MapHash, MapHasher,
+};
+
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp;
+use core::ops::RangeBounds;
+
+use canonical::{Canon, CanonError};
+use canonical_derive::Canon;
+use microkelvin::{Annotated, Cardinality, Combine};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Canon)]
+/// Sibling of a node in the path from a leaf to the root
+pub struct ProofStep {
+    sibling: Digest,
+    sibling_cardinality: u64,
+    sibling_is_left: bool,
+}
+
+impl ProofStep {
+    fn new<K, V, A, H>(
+        sibling: &Annotated<KelvinMap<K, V, A>, A>,
+        sibling_is_left: bool,
+    ) -> Self
+    where
+        K: Canon + Ord,
+        V: Canon,
+        A: MapAnnotation<K, V> + Borrow<MapHash<H>>,
+        H: MapHasher,
+    {
+        let hash: &MapHash<H> = sibling.annotation().borrow();
+
+        Self {
+            sibling: *hash.digest(),
+            sibling_cardinality: hash.cardinality(),
+            sibling_is_left,
+        }
+    }
+
+    /// Commitment to the sibling sub-tree
+    pub fn sibling(&self) -> &Digest {
+        &self.sibling
+    }
+
+    /// Number of leaves of the sibling sub-tree
+    pub fn sibling_cardinality(&self) -> u64 {
+        self.sibling_cardinality
+    }
+
+    /// Check if the sibling sub-tree contains the smaller keys
+    pub fn sibling_is_left(&self) -> bool {
+        self.sibling_is_left
+    }
+
+    /// Fold the step into the hash and the cardinality of the sub-tree
+    /// containing the proven leaf
+    fn fold<H>(&self, digest: &Digest, cardinality: u64) -> (Digest, u64)
+    where
+        H: MapHasher,
+    {
+        let cardinality = cardinality.saturating_add(self.sibling_cardinality);
+
+        let digest = if self.sibling_is_left {
+            node_hash::<H, _>(cardinality, &[self.sibling, *digest])
+        } else {
+            node_hash::<H, _>(cardinality, &[*digest, self.sibling])
+        };
+
+        (digest, cardinality)
+    }
+}
+
+#[derive(Debug, Clone, Canon)]
+/// Proof that a key -> value mapping is the `i`-th smallest entry of a map
+/// committed with [`MapHash`]
+pub struct NthProof<K, V> {
+    leaf: Leaf<K, V>,
+    path: Vec<ProofStep>,
+}
+
+impl<K, V> NthProof<K, V>
+where
+    K: Canon + Ord,
+    V: Canon,
+{
+    /// Proven key -> value mapping
+    pub fn leaf(&self) -> &Leaf<K, V> {
+        &self.leaf
+    }
+
+    /// Siblings of the path from the leaf to the root, bottom-up
+    pub fn path(&self) -> &[ProofStep] {
+        &self.path
+    }
+
+    /// Check the proof against the root hash of a map, as returned by
+    /// [`KelvinMap::root_hash`]. The position of the leaf is derived from the
+    /// committed cardinalities of the left siblings.
+    pub fn verify<H>(&self, root: &Digest, i: u64) -> bool
+    where
+        H: MapHasher,
+    {
+        let mut digest = node_hash::<H, _>(1, &[self.leaf.hash::<H>()]);
+        let mut cardinality = 1;
+        let mut position = 0u64;
+
+        for step in self.path.iter() {
+            if step.sibling_is_left {
+                position = position.saturating_add(step.sibling_cardinality);
+            }
+
+            let (d, c) = step.fold::<H>(&digest, cardinality);
+            digest = d;
+            cardinality = c;
+        }
+
+        &digest == root && position == i
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Generate a proof that the `i`-th smallest key of the map (starting at
+    /// zero) is mapped to its value.
+    ///
+    /// Will return `Ok(None)` if `i` is out of bounds.
+    pub fn prove_nth<H>(
+        &self,
+        i: u64,
+    ) -> Result<Option<NthProof<K, V>>, CanonError>
+    where
+        A: Borrow<MapHash<H>>,
+        H: MapHasher,
+    {
+        let mut path = Vec::new();
+
+        Ok(self
+            .nth_path::<H>(i, &mut path)?
+            .map(|leaf| NthProof { leaf, path }))
+    }
+
+    fn nth_path<H>(
+        &self,
+        i: u64,
+        path: &mut Vec<ProofStep>,
+    ) -> Result<Option<Leaf<K, V>>, CanonError>
+    where
+        A: Borrow<MapHash<H>>,
+        H: MapHasher,
+    {
+        match self {
+            KelvinMap::Empty => Ok(None),
+            KelvinMap::Leaf(l) if i == 0 => Ok(Some(l.clone())),
+            KelvinMap::Leaf(_) => Ok(None),
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let c_l: &Cardinality = l.annotation().borrow();
+                let c_l: u64 = c_l.into();
+
+                let (leaf, step) = if i < c_l {
+                    (l.val()?.nth_path::<H>(i, path)?, ProofStep::new(r, false))
+                } else {
+                    let leaf = r.val()?.nth_path::<H>(i - c_l, path)?;
+                    (leaf, ProofStep::new(l, true))
+                };
+
+                path.push(step);
+
+                Ok(leaf)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Canon)]
+/// Node of a pre-order encoded partial tree
+pub enum RangeProofNode<K, V> {
+    /// Sub-tree that was not revealed, with its committed digest and number
+    /// of leaves
+    Pruned(Digest, u64),
+    /// Revealed leaf
+    Leaf(Leaf<K, V>),
+    /// Node with both children following in pre-order
+    Node,
+}
+
+#[derive(Debug, Clone, Canon)]
+/// Proof that a set of entries is exactly the contents of a map within a key
+/// range.
+///
+/// The proof is a partial tree revealing the entries within the range
+/// together with the closest entries out of it. Since the positions of the
+/// revealed leaves are committed by [`MapHash`], a verifier can check that no
+/// entry is omitted between the boundaries.
+pub struct RangeProof<K, V> {
+    nodes: Vec<RangeProofNode<K, V>>,
+}
+
+impl<K, V> RangeProof<K, V>
+where
+    K: Canon + Ord,
+    V: Canon,
+{
+    /// Nodes of the partial tree, in pre-order
+    pub fn nodes(&self) -> &[RangeProofNode<K, V>] {
+        &self.nodes
+    }
+
+    /// Rebuild the commitment of the sub-tree starting at `idx`, pushing the
+    /// revealed leaves with their positions
+    fn rebuild<'a, H>(
+        &'a self,
+        idx: &mut usize,
+        position: u64,
+        leaves: &mut Vec<(u64, &'a Leaf<K, V>)>,
+    ) -> Option<(Digest, u64)>
+    where
+        H: MapHasher,
+    {
+        let node = self.nodes.get(*idx)?;
+        *idx += 1;
+
+        match node {
+            RangeProofNode::Pruned(digest, cardinality) => {
+                Some((*digest, *cardinality))
+            }
+            RangeProofNode::Leaf(l) => {
+                leaves.push((position, l));
+                Some((node_hash::<H, _>(1, &[l.hash::<H>()]), 1))
+            }
+            RangeProofNode::Node => {
+                let (l, c_l) = self.rebuild::<H>(idx, position, leaves)?;
+                let (r, c_r) =
+                    self.rebuild::<H>(idx, position.checked_add(c_l)?, leaves)?;
+                let cardinality = c_l.checked_add(c_r)?;
+
+                Some((node_hash::<H, _>(cardinality, &[l, r]), cardinality))
+            }
+        }
+    }
+
+    /// Check the proof against the root hash of a map, as returned by
+    /// [`KelvinMap::root_hash`].
+    ///
+    /// Returns the entries of the map within `range`, or `None` if the proof
+    /// is invalid.
+    pub fn verify<H, R>(
+        &self,
+        root: &Digest,
+        range: &R,
+    ) -> Option<Vec<&Leaf<K, V>>>
+    where
+        H: MapHasher,
+        R: RangeBounds<K>,
+    {
+        let mut idx = 0;
+        let mut leaves = Vec::new();
+
+        let (digest, cardinality) =
+            self.rebuild::<H>(&mut idx, 0, &mut leaves)?;
+
+        if &digest != root || idx != self.nodes.len() {
+            return None;
+        }
+
+        let contiguous = leaves.windows(2).all(|w| w[0].0 + 1 == w[1].0);
+
+        let bounded = match (leaves.first(), leaves.last()) {
+            (Some((first, l)), Some((last, r))) => {
+                let bounds = BoundedWalker::range(range);
+
+                (*first == 0 || bounds.is_below(l._key()))
+                    && (*last + 1 == cardinality || bounds.is_above(r._key()))
+            }
+            _ => cardinality == 0,
+        };
+
+        if !contiguous || !bounded {
+            return None;
+        }
+
+        Some(
+            leaves
+                .into_iter()
+                .map(|(_, l)| l)
+                .filter(|l| range.contains(l._key()))
+                .collect(),
+        )
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Generate a proof of the entries of the map within `range`, including
+    /// the closest entries out of it to prove that no entry is omitted.
+    pub fn prove_range<H, R>(
+        &self,
+        range: R,
+    ) -> Result<RangeProof<K, V>, CanonError>
+    where
+        A: Borrow<MapHash<H>>,
+        H: MapHasher,
+        R: RangeBounds<K>,
+    {
+        let bounds = BoundedWalker::range(&range);
+
+        let below = self.count_prefix(&|k: &K| bounds.is_below(k))?;
+        let within = self.count_prefix(&|k: &K| !bounds.is_above(k))?;
+
+        // Reveal the positions `[from, to)`
+        let from = below.saturating_sub(1);
+        let to = cmp::min(within + 1, self.len() as u64);
+
+        let mut nodes = Vec::new();
+        self.partial_tree::<H>(0, from, to, &mut nodes)?;
+
+        Ok(RangeProof { nodes })
+    }
+
+    fn partial_tree<H>(
+        &self,
+        position: u64,
+        from: u64,
+        to: u64,
+        nodes: &mut Vec<RangeProofNode<K, V>>,
+    ) -> Result<(), CanonError>
+    where
+        A: Borrow<MapHash<H>>,
+        H: MapHasher,
+    {
+        match self {
+            KelvinMap::Leaf(l) if position >= from && position < to => {
+                nodes.push(RangeProofNode::Leaf(l.clone()));
+            }
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let hash_l: &MapHash<H> = l.annotation().borrow();
+                let hash_r: &MapHash<H> = r.annotation().borrow();
+
+                let mid = position + hash_l.cardinality();
+                let end = mid + hash_r.cardinality();
+
+                if position < to && end > from {
+                    nodes.push(RangeProofNode::Node);
+
+                    if mid > from {
+                        l.val()?
+                            .partial_tree::<H>(position, from, to, nodes)?;
+                    } else {
+                        nodes.push(RangeProofNode::Pruned(
+                            *hash_l.digest(),
+                            hash_l.cardinality(),
+                        ));
+                    }
+
+                    if mid < to {
+                        r.val()?.partial_tree::<H>(mid, from, to, nodes)?;
+                    } else {
+                        nodes.push(RangeProofNode::Pruned(
+                            *hash_r.digest(),
+                            hash_r.cardinality(),
+                        ));
+                    }
+                } else {
+                    let hash = MapHash::<H>::combine(self);
+                    nodes.push(RangeProofNode::Pruned(
+                        *hash.digest(),
+                        hash.cardinality(),
+                    ));
+                }
+            }
+            _ => {
+                let hash = MapHash::<H>::combine(self);
+                nodes.push(RangeProofNode::Pruned(
+                    *hash.digest(),
+                    hash.cardinality(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::MapError;
+
+use core::ops::{Bound, RangeBounds};
+
+use canonical::{Canon, CanonError, Sink, Source};
+use canonical_derive::Canon;
+
+#[derive(Debug, Clone, PartialEq, Eq, Canon)]
+/// Canonical counterpart of [`Bound`]
+enum KeyBound<K> {
+    Included(K),
+    Excluded(K),
+    Unbounded,
+}
+
+impl<K> KeyBound<K> {
+    fn as_bound(&self) -> Bound<&K> {
+        match self {
+            KeyBound::Included(k) => Bound::Included(k),
+            KeyBound::Excluded(k) => Bound::Excluded(k),
+            KeyBound::Unbounded => Bound::Unbounded,
+        }
+    }
+}
+
+impl<K> From<Bound<K>> for KeyBound<K> {
+    fn from(bound: Bound<K>) -> Self {
+        match bound {
+            Bound::Included(k) => KeyBound::Included(k),
+            Bound::Excluded(k) => KeyBound::Excluded(k),
+            Bound::Unbounded => KeyBound::Unbounded,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Range of keys whose bounds are known not to be inverted
+///
+/// The range is validated when created and when decoded, so it can be
+/// received as an argument and passed on to any range-based method of the
+/// map, which accept any [`RangeBounds`], without further checks.
+pub struct KeyRange<K> {
+    start: KeyBound<K>,
+    end: KeyBound<K>,
+}
+
+impl<K> KeyRange<K>
+where
+    K: Ord,
+{
+    /// Create a range from its bounds.
+    ///
+    /// Will return [`MapError::InvalidRange`] if the start is after the end,
+    /// or if both bounds exclude the same key.
+    pub fn new(start: Bound<K>, end: Bound<K>) -> Result<Self, MapError> {
+        let range = Self {
+            start: start.into(),
+            end: end.into(),
+        };
+
+        range.validate()?;
+        Ok(range)
+    }
+
+    /// Create a range from the bounds of `range`
+    pub fn from_bounds<R>(range: &R) -> Result<Self, MapError>
+    where
+        K: Clone,
+        R: RangeBounds<K>,
+    {
+        Self::new(range.start_bound().cloned(), range.end_bound().cloned())
+    }
+
+    /// Range of all the keys
+    pub fn full() -> Self {
+        Self {
+            start: KeyBound::Unbounded,
+            end: KeyBound::Unbounded,
+        }
+    }
+
+    /// Check if no key is within the range
+    pub fn is_empty(&self) -> bool {
+        match (&self.start, &self.end) {
+            (KeyBound::Included(s), KeyBound::Included(e)) => s > e,
+            (KeyBound::Included(s), KeyBound::Excluded(e))
+            | (KeyBound::Excluded(s), KeyBound::Included(e))
+            | (KeyBound::Excluded(s), KeyBound::Excluded(e)) => s >= e,
+            _ => false,
+        }
+    }
+
+    fn validate(&self) -> Result<(), MapError> {
+        let valid = match (&self.start, &self.end) {
+            (KeyBound::Excluded(s), KeyBound::Excluded(e)) => s < e,
+            (KeyBound::Included(s), KeyBound::Included(e))
+            | (KeyBound::Included(s), KeyBound::Excluded(e))
+            | (KeyBound::Excluded(s), KeyBound::Included(e)) => s <= e,
+            _ => true,
+        };
+
+        valid.then_some(()).ok_or(MapError::InvalidRange)
+    }
+}
+
+impl<K> RangeBounds<K> for KeyRange<K> {
+    fn start_bound(&self) -> Bound<&K> {
+        self.start.as_bound()
+    }
+
+    fn end_bound(&self) -> Bound<&K> {
+        self.end.as_bound()
+    }
+}
+
+impl<K> Canon for KeyRange<K>
+where
+    K: Canon + Ord,
+{
+    fn encode(&self, sink: &mut Sink) {
+        self.start.encode(sink);
+        self.end.encode(sink);
+    }
+
+    fn decode(source: &mut Source) -> Result<Self, CanonError> {
+        let range = Self {
+            start: KeyBound::decode(source)?,
+            end: KeyBound::decode(source)?,
+        };
+        range.validate().map_err(|_| CanonError::InvalidEncoding)?;
+
+        Ok(range)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.start.encoded_len() + self.end.encoded_len()
+    }
+}
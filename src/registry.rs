@@ -0,0 +1,94 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{Map, MapError};
+
+use canonical::{Canon, CanonError, Id};
+use canonical_derive::Canon;
+
+/// Update of the root of a contract in a [`MapRegistry`]
+pub type RootUpdate<C> = (C, Option<Id>, Option<Id>);
+
+#[derive(Debug, Clone, Default, Canon)]
+/// Registry of the current root of the map of every contract, for the host
+/// managing them
+///
+/// Roots are only ever replaced by compare-and-swap, so a writer working on
+/// a stale root is rejected instead of silently discarding a concurrent
+/// update. The registry is itself canonical, and can be persisted along with
+/// the maps it tracks.
+pub struct MapRegistry<C>
+where
+    C: Canon + Ord + Default,
+{
+    roots: Map<C, Id>,
+}
+
+impl<C> MapRegistry<C>
+where
+    C: Canon + Ord + Default,
+{
+    /// Current root of the map of `contract`, if registered
+    pub fn root(&self, contract: &C) -> Result<Option<Id>, CanonError> {
+        Ok(self.roots.get(contract)?.map(|root| *root))
+    }
+
+    /// Number of registered contracts
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Check if no contract is registered
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Replace the root of `contract` with `new`, provided the current one is
+    /// still `expected`
+    ///
+    /// `None` stands for an unregistered contract on both sides, so a
+    /// contract is registered with an `expected` of `None`, and removed with
+    /// a `new` of `None`.
+    ///
+    /// Will return [`MapError::StaleRoot`], leaving the registry untouched,
+    /// if the current root is not `expected`.
+    pub fn compare_and_swap(
+        &mut self,
+        contract: C,
+        expected: Option<Id>,
+        new: Option<Id>,
+    ) -> Result<(), MapError> {
+        self.commit(core::iter::once((contract, expected, new)))
+    }
+
+    /// Apply a batch of [`compare_and_swap`](Self::compare_and_swap) updates
+    /// as a whole
+    ///
+    /// Either every update is applied, or, if any of them is stale or the
+    /// store fails, none is. The updates are applied in order, so later ones
+    /// observe the roots set by the earlier ones.
+    pub fn commit<I>(&mut self, updates: I) -> Result<(), MapError>
+    where
+        I: IntoIterator<Item = RootUpdate<C>>,
+    {
+        let mut roots = self.roots.clone();
+
+        for (contract, expected, new) in updates {
+            let current = roots.get(&contract)?.map(|root| *root);
+            if current != expected {
+                return Err(MapError::StaleRoot);
+            }
+
+            match new {
+                Some(root) => roots.insert(contract, root)?,
+                None => roots.remove(&contract)?,
+            };
+        }
+
+        self.roots = roots;
+        Ok(())
+    }
+}
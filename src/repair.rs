@@ -0,0 +1,104 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::verify::annotation_matches;
+use crate::{KelvinMap, MapAnnotation, MapError};
+
+use canonical::Canon;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Outcome of [`KelvinMap::repair`]
+pub struct RepairReport {
+    /// Number of verified nodes
+    pub checked: usize,
+    /// Number of recomputed annotations
+    pub annotations: usize,
+}
+
+impl RepairReport {
+    /// Check if anything was repaired
+    pub fn is_clean(&self) -> bool {
+        self.annotations == 0
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Verify the whole tree as [`KelvinMap::validate`], recomputing in place
+    /// the annotations that don't match the children of their nodes.
+    ///
+    /// Only the nodes on the paths to the repaired ones are rewritten, so the
+    /// consistent sub-trees stay shared. Keys out of order and empty children
+    /// can't be repaired, and are reported as errors, leaving the map
+    /// untouched.
+    pub fn repair(&mut self) -> Result<RepairReport, MapError> {
+        let mut report = RepairReport::default();
+
+        if let Some(repaired) = self.repaired(&mut report, None)? {
+            *self = repaired;
+        }
+
+        Ok(report)
+    }
+
+    /// Verify a sub-tree whose keys are greater than `lower`, returning its
+    /// replacement if any of its nodes was repaired
+    fn repaired(
+        &self,
+        report: &mut RepairReport,
+        lower: Option<&K>,
+    ) -> Result<Option<Self>, MapError> {
+        let above = |key: &K| lower.map(|lower| key > lower).unwrap_or(true);
+
+        match self {
+            KelvinMap::Empty => Ok(None),
+            KelvinMap::Leaf(l) if above(l._key()) => Ok(None),
+            KelvinMap::Leaf(_) => Err(MapError::InvalidOrder),
+
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                report.checked += 1;
+
+                let left = l.val()?;
+                let right = r.val()?;
+
+                if left.is_empty() || right.is_empty() {
+                    return Err(MapError::EmptyChild);
+                }
+
+                let new_left = left.repaired(report, lower)?;
+                let split = match &new_left {
+                    Some(new_left) => new_left.max_key(),
+                    None => left.max_key(),
+                };
+                let new_right = right.repaired(report, split.as_ref())?;
+
+                let stale_l =
+                    new_left.is_none() && !annotation_matches(l, &left);
+                let stale_r =
+                    new_right.is_none() && !annotation_matches(r, &right);
+                if new_left.is_none()
+                    && new_right.is_none()
+                    && !stale_l
+                    && !stale_r
+                {
+                    return Ok(None);
+                }
+
+                report.annotations += stale_l as usize + stale_r as usize;
+
+                let new_left = new_left.unwrap_or_else(|| left.clone());
+                let new_right = new_right.unwrap_or_else(|| right.clone());
+
+                Ok(Some(KelvinMap::node(new_left, new_right)))
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use canonical::{Canon, CanonError};
+
+impl<K, A> KelvinMap<K, (), A>
+where
+    K: Canon + Ord,
+    A: MapAnnotation<K, ()>,
+{
+    /// Include a key in the set.
+    ///
+    /// The unit value has an empty canonical encoding, so the leaves of the
+    /// set store the key only.
+    ///
+    /// Returns `Ok(true)` if the key was not previously present.
+    pub fn insert_key(&mut self, k: K) -> Result<bool, CanonError> {
+        self.insert(k, ()).map(|old| old.is_none())
+    }
+
+    /// Remove a key from the set.
+    ///
+    /// Returns `Ok(true)` if the key was previously present.
+    pub fn remove_key(&mut self, k: &K) -> Result<bool, CanonError> {
+        self.remove(k).map(|old| old.is_some())
+    }
+}
@@ -0,0 +1,113 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::map::cardinality;
+use crate::{KelvinMap, MapAnnotation, MapError};
+
+use alloc::vec::Vec;
+use core::cmp;
+
+use canonical::{Canon, CanonError};
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Split the map into `n` shards of consecutive key ranges, with lengths
+    /// differing by at most one.
+    ///
+    /// The sub-trees entirely within a shard are shared with `self` rather
+    /// than copied, so only the nodes on the boundaries between shards are
+    /// rewritten.
+    pub fn shard(&self, n: usize) -> Result<Vec<Self>, CanonError> {
+        let len = self.len() as u64;
+        let n64 = n as u64;
+
+        (0..n64)
+            .map(|i| self.alias_positions(i * len / n64, (i + 1) * len / n64))
+            .collect()
+    }
+
+    /// Join shards of consecutive key ranges, as created by
+    /// [`KelvinMap::shard`], into a single map.
+    ///
+    /// Will return [`MapError::InvalidOrder`] if the key ranges of the
+    /// shards overlap, or are not in ascending order.
+    pub fn unshard<I>(shards: I) -> Result<Self, MapError>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        let shards: Vec<Self> =
+            shards.into_iter().filter(|s| !s.is_empty()).collect();
+
+        let mut prev_max = None;
+        for shard in shards.iter() {
+            if let (Some(prev_max), Some(min)) = (prev_max, shard.min_key()?) {
+                if min <= prev_max {
+                    return Err(MapError::InvalidOrder);
+                }
+            }
+
+            prev_max = shard.max_key();
+        }
+
+        Ok(Self::join_shards(shards))
+    }
+
+    /// Join ordered shards under a balanced tree of nodes
+    fn join_shards(mut shards: Vec<Self>) -> Self {
+        match shards.len() {
+            0 => KelvinMap::Empty,
+            1 => shards.pop().unwrap_or_default(),
+            len => {
+                let right = shards.split_off(len - len / 2);
+
+                Self::node(Self::join_shards(shards), Self::join_shards(right))
+            }
+        }
+    }
+
+    /// Alias the leaves at the positions `start..end` in key order
+    fn alias_positions(
+        &self,
+        start: u64,
+        end: u64,
+    ) -> Result<Self, CanonError> {
+        if start >= end {
+            return Ok(KelvinMap::Empty);
+        }
+
+        match self {
+            KelvinMap::Empty => Ok(KelvinMap::Empty),
+            KelvinMap::Leaf(_) if start == 0 => Ok(self.clone()),
+            KelvinMap::Leaf(_) => Ok(KelvinMap::Empty),
+            KelvinMap::Node(n) if start == 0 && end >= n.cardinality() => {
+                Ok(self.clone())
+            }
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let len_l = cardinality(l);
+
+                let left = if start < len_l {
+                    l.val()?.alias_positions(start, cmp::min(end, len_l))?
+                } else {
+                    KelvinMap::Empty
+                };
+
+                let right = if end > len_l {
+                    let start = start.saturating_sub(len_l);
+                    r.val()?.alias_positions(start, end - len_l)?
+                } else {
+                    KelvinMap::Empty
+                };
+
+                Ok(Self::node(left, right))
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use alloc::vec::Vec;
+use core::cmp;
+
+use canonical::{Canon, CanonError};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Outcome of [`KelvinMap::shrink`]
+pub struct ShrinkReport {
+    /// Depth of the tree before the compaction
+    pub depth_before: usize,
+    /// Depth of the tree after the compaction
+    pub depth_after: usize,
+    /// Number of nodes rewritten by the compaction
+    pub rewritten: usize,
+}
+
+/// Depth of the balanced tree built from `len` sorted leaves, `log2(len)`
+/// rounded up
+fn balanced_depth(len: usize) -> usize {
+    match len {
+        0 | 1 => 0,
+        _ => (usize::BITS - (len - 1).leading_zeros()) as usize,
+    }
+}
+
+/// Maximum tolerated depth of a tree with `len` leaves, `2 * log2(len)`
+/// rounded up, and at most `limit` unless the leaves can't fit in it
+fn max_depth(len: usize, limit: usize) -> usize {
+    let tolerated = 2 * cmp::max(balanced_depth(len), 1);
+
+    cmp::max(cmp::min(tolerated, limit), balanced_depth(len))
+}
+
+/// Compaction of a sub-tree
+struct Shrunk<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Depth of the sub-tree before the compaction
+    before: usize,
+    /// Depth of the sub-tree after the compaction
+    after: usize,
+    /// Number of rewritten nodes
+    rewritten: usize,
+    /// Replacement of the sub-tree, if any of its nodes was rewritten
+    node: Option<KelvinMap<K, V, A>>,
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Number of levels of nodes above the deepest leaf
+    pub(crate) fn depth(&self) -> Result<usize, CanonError> {
+        match self {
+            KelvinMap::Empty | KelvinMap::Leaf(_) => Ok(0),
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let depth_l = l.val()?.depth()?;
+                let depth_r = r.val()?.depth()?;
+
+                Ok(1 + cmp::max(depth_l, depth_r))
+            }
+        }
+    }
+
+    /// Compact the tree if its depth exceeds `2 * log2(len)`, which can
+    /// happen after heavy deletion.
+    ///
+    /// The children of the sub-trees that exceed the bound are compacted
+    /// first, and a sub-tree is only rebuilt from its sorted leaves if it
+    /// still exceeds the bound afterwards. The depths are computed bottom-up
+    /// in a single traversal, and the result is deterministic for a given
+    /// tree.
+    pub fn shrink(&mut self) -> Result<ShrinkReport, CanonError> {
+        self.shrink_within(usize::MAX)
+    }
+
+    /// Compact the tree as [`KelvinMap::shrink`], with the depth of every
+    /// sub-tree also bounded by `limit`
+    pub(crate) fn shrink_within(
+        &mut self,
+        limit: usize,
+    ) -> Result<ShrinkReport, CanonError> {
+        let shrunk = self.shrunk(limit)?;
+
+        if let Some(node) = shrunk.node {
+            *self = node;
+        }
+
+        Ok(ShrinkReport {
+            depth_before: shrunk.before,
+            depth_after: shrunk.after,
+            rewritten: shrunk.rewritten,
+        })
+    }
+
+    /// Compact a sub-tree, as if it exceeded the bound of its parent.
+    ///
+    /// The compaction of the children is computed along with their depths,
+    /// and only kept if the sub-tree itself exceeds its bound.
+    fn shrunk(&self, limit: usize) -> Result<Shrunk<K, V, A>, CanonError> {
+        let (l, r) = match self {
+            KelvinMap::Node(n) => n.children(),
+            _ => {
+                return Ok(Shrunk {
+                    before: 0,
+                    after: 0,
+                    rewritten: 0,
+                    node: None,
+                })
+            }
+        };
+
+        let (left, right) = (l.val()?, r.val()?);
+        let (shrunk_l, shrunk_r) = (left.shrunk(limit)?, right.shrunk(limit)?);
+
+        let before = 1 + cmp::max(shrunk_l.before, shrunk_r.before);
+        let len = self.len();
+
+        if before <= max_depth(len, limit) {
+            return Ok(Shrunk {
+                before,
+                after: before,
+                rewritten: 0,
+                node: None,
+            });
+        }
+
+        let after = 1 + cmp::max(shrunk_l.after, shrunk_r.after);
+        let mut rewritten = shrunk_l.rewritten + shrunk_r.rewritten;
+
+        // The node itself is rewritten with its updated children
+        rewritten += 1;
+        let node = match (shrunk_l.node, shrunk_r.node) {
+            (None, None) => self.clone(),
+            (new_l, new_r) => KelvinMap::node(
+                new_l.unwrap_or_else(|| left.clone()),
+                new_r.unwrap_or_else(|| right.clone()),
+            ),
+        };
+
+        if after <= max_depth(len, limit) {
+            return Ok(Shrunk {
+                before,
+                after,
+                rewritten,
+                node: Some(node),
+            });
+        }
+
+        let mut leaves = Vec::with_capacity(len);
+        node.for_each_leaf(&mut |l| leaves.push(l.clone()))?;
+        rewritten += len.saturating_sub(1);
+
+        Ok(Shrunk {
+            before,
+            after: balanced_depth(len),
+            rewritten,
+            node: Some(Self::from_sorted_leaves(&mut leaves.into_iter(), len)),
+        })
+    }
+}
@@ -0,0 +1,114 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::map::cmp_max_key;
+use crate::{KelvinMap, MapAnnotation};
+
+use canonical::{Canon, CanonError};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Predicted effects of a write, computed by [`KelvinMap::simulate_insert`]
+/// and [`KelvinMap::simulate_remove`] without mutating the map
+pub struct SimulationReport {
+    /// The write replaces or removes an existing mapping
+    pub would_overwrite: bool,
+    /// Number of nodes above the written position after the write, which is
+    /// a lower bound of the depth of the tree
+    pub new_depth_estimate: usize,
+    /// Number of nodes and leaves rewritten along the path of the write
+    pub nodes_touched: usize,
+}
+
+/// Path of a write to a key
+struct WritePath {
+    /// Number of nodes above the reached leaf
+    nodes: usize,
+    /// The reached leaf holds the key
+    found: bool,
+    /// A leaf was reached, so the map isn't empty
+    leaf: bool,
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Descend towards `k` as a write would, down to a leaf
+    fn write_path(&self, k: &K) -> Result<WritePath, CanonError> {
+        match self {
+            KelvinMap::Empty => Ok(WritePath {
+                nodes: 0,
+                found: false,
+                leaf: false,
+            }),
+            KelvinMap::Leaf(l) => Ok(WritePath {
+                nodes: 0,
+                found: l._key() == k,
+                leaf: true,
+            }),
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let mut path = if cmp_max_key(l, k).is_ge() {
+                    l.val()?.write_path(k)?
+                } else {
+                    r.val()?.write_path(k)?
+                };
+
+                path.nodes += 1;
+                Ok(path)
+            }
+        }
+    }
+
+    /// Predict the effects of inserting `k` into the map.
+    ///
+    /// Only the path of the write is traversed. The naive balancing step
+    /// preceding the write is not accounted for.
+    pub fn simulate_insert(
+        &self,
+        k: &K,
+        _v: &V,
+    ) -> Result<SimulationReport, CanonError> {
+        let path = self.write_path(k)?;
+
+        // A new key splits the reached leaf into a node holding both
+        let split = path.leaf && !path.found;
+
+        Ok(SimulationReport {
+            would_overwrite: path.found,
+            new_depth_estimate: path.nodes + split as usize,
+            nodes_touched: path.nodes + 1,
+        })
+    }
+
+    /// Predict the effects of removing `k` from the map.
+    ///
+    /// Only the path of the write is traversed. The naive balancing step
+    /// preceding the write is not accounted for.
+    pub fn simulate_remove(
+        &self,
+        k: &K,
+    ) -> Result<SimulationReport, CanonError> {
+        let path = self.write_path(k)?;
+
+        if !path.found {
+            return Ok(SimulationReport {
+                would_overwrite: false,
+                new_depth_estimate: path.nodes,
+                nodes_touched: 0,
+            });
+        }
+
+        // The parent of the removed leaf is replaced by its other child
+        Ok(SimulationReport {
+            would_overwrite: true,
+            new_depth_estimate: path.nodes.saturating_sub(1),
+            nodes_touched: path.nodes,
+        })
+    }
+}
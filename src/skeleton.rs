@@ -0,0 +1,127 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation};
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp;
+
+use canonical::{Canon, CanonError};
+use canonical_derive::Canon;
+
+#[derive(Debug, Clone, PartialEq, Canon)]
+/// Node of a pre-order encoded [`Skeleton`]
+pub enum SkeletonNode<A> {
+    /// Leaf, with its annotation
+    Leaf(A),
+    /// Node with its annotation, with both children following in pre-order
+    Node(A),
+    /// Empty child of a node, only found in corrupted trees
+    Empty,
+}
+
+#[derive(Debug, Clone, PartialEq, Canon)]
+/// Shape and annotations of a map, without any of its keys and values.
+///
+/// The nodes are listed in pre-order, each with the annotation cached for
+/// it in the tree, so cardinalities and commitments can be inspected without
+/// transferring the contents of the map.
+pub struct Skeleton<A> {
+    nodes: Vec<SkeletonNode<A>>,
+}
+
+impl<A> Skeleton<A> {
+    /// Nodes of the tree, in pre-order
+    pub fn nodes(&self) -> &[SkeletonNode<A>] {
+        &self.nodes
+    }
+
+    /// Annotation of the root, or `None` for an empty map
+    pub fn root(&self) -> Option<&A> {
+        match self.nodes.first() {
+            Some(SkeletonNode::Leaf(a)) | Some(SkeletonNode::Node(a)) => {
+                Some(a)
+            }
+            _ => None,
+        }
+    }
+
+    /// Number of leaves of the tree
+    pub fn leaves(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|n| matches!(n, SkeletonNode::Leaf(_)))
+            .count()
+    }
+
+    /// Number of levels of the tree, counting the leaves
+    pub fn depth(&self) -> usize {
+        let mut pending = vec![1];
+        let mut depth = 0;
+
+        for node in self.nodes.iter() {
+            let level = match pending.pop() {
+                Some(level) => level,
+                None => break,
+            };
+
+            match node {
+                SkeletonNode::Node(_) => {
+                    pending.push(level + 1);
+                    pending.push(level + 1);
+                }
+                SkeletonNode::Leaf(_) => depth = cmp::max(depth, level),
+                SkeletonNode::Empty => (),
+            }
+        }
+
+        depth
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Export the shape of the tree and its annotations, leaving out the
+    /// keys and values.
+    ///
+    /// The annotations cached in the tree are exported as they are, so only
+    /// the one of the root is computed.
+    pub fn export_skeleton(&self) -> Result<Skeleton<A>, CanonError> {
+        let mut nodes = Vec::with_capacity(self.len() * 2);
+
+        if !matches!(self, KelvinMap::Empty) {
+            self.skeleton_into(A::combine(self), &mut nodes)?;
+        }
+
+        Ok(Skeleton { nodes })
+    }
+
+    /// Push the pre-order skeleton of a sub-tree annotated with `annotation`
+    fn skeleton_into(
+        &self,
+        annotation: A,
+        nodes: &mut Vec<SkeletonNode<A>>,
+    ) -> Result<(), CanonError> {
+        match self {
+            KelvinMap::Empty => nodes.push(SkeletonNode::Empty),
+            KelvinMap::Leaf(_) => nodes.push(SkeletonNode::Leaf(annotation)),
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                nodes.push(SkeletonNode::Node(annotation));
+
+                l.val()?.skeleton_into(l.annotation().clone(), nodes)?;
+                r.val()?.skeleton_into(r.annotation().clone(), nodes)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::zip::Cursor;
+use crate::{KelvinMap, MapAnnotation};
+
+use canonical::{Canon, CanonError, Id};
+
+/// Iterator over the entries of a frozen state of a map, in key order,
+/// created by [`KelvinMap::snapshot_iter`], [`KelvinMap::snapshot_iter_at`],
+/// [`KelvinMap::drain`] or by consuming the map.
+///
+/// The iterator owns the root of the state it was created from, rather than
+/// borrowing the map, so the live map can keep accepting writes meanwhile.
+/// Writes copy the nodes they edit instead of mutating the shared ones, so
+/// they are never observed by the iterator: it yields exactly the entries of
+/// the map at the time of its creation. The nodes not yet in memory are
+/// fetched from the store as the iteration goes.
+pub struct SnapshotIter<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    cursor: Cursor<K, V, A>,
+}
+
+impl<K, V, A> Iterator for SnapshotIter<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    type Item = Result<(K, V), CanonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor
+            .next_leaf()
+            .map(|leaf| leaf.map(|l| l.into_parts()))
+            .transpose()
+    }
+}
+
+/// Consume the map, yielding its entries in key order.
+impl<K, V, A> IntoIterator for KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    type Item = Result<(K, V), CanonError>;
+    type IntoIter = SnapshotIter<K, V, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.snapshot_iter()
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Iterate the current state of the map, independently of the writes
+    /// performed on `self` afterwards.
+    ///
+    /// See [`SnapshotIter`] for the consistency model.
+    pub fn snapshot_iter(&self) -> SnapshotIter<K, V, A> {
+        SnapshotIter {
+            cursor: Cursor::new(self),
+        }
+    }
+
+    /// Iterate the state of the map persisted under `root`.
+    ///
+    /// See [`SnapshotIter`] for the consistency model.
+    pub fn snapshot_iter_at(
+        root: &Id,
+    ) -> Result<SnapshotIter<K, V, A>, CanonError> {
+        let map: Self = root.reify()?;
+
+        Ok(map.snapshot_iter())
+    }
+
+    /// Empty the map, yielding its former entries in key order
+    ///
+    /// Every entry is copied once out of the detached tree and then handed
+    /// over, rather than cloned from the map and again on removal. The map is
+    /// empty as soon as this returns, even if the iterator is not consumed.
+    pub fn drain(&mut self) -> SnapshotIter<K, V, A> {
+        self.clear().into_iter()
+    }
+}
@@ -0,0 +1,506 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{Digest, Leaf, MapError, MapHasher, NODE_DOMAIN};
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use canonical::{Canon, CanonError, Sink, Source};
+use canonical_derive::Canon;
+use microkelvin::{Annotated, Annotation, Child, ChildMut, Combine, Compound};
+
+/// Maximum depth of a [`SparseMap`], covering the whole `u64` key space
+pub const MAX_SPARSE_DEPTH: u8 = 64;
+
+/// Commitment to an empty slot at the bottom of a [`SparseMap`]
+pub const EMPTY_LEAF: Digest = [0u8; 32];
+
+/// Commitment to a node of a sparse tree:
+///
+/// `H(NODE_DOMAIN || left || right)`
+fn sparse_node_hash<H>(left: &Digest, right: &Digest) -> Digest
+where
+    H: MapHasher,
+{
+    let mut bytes = Vec::with_capacity(1 + 2 * 32);
+
+    bytes.push(NODE_DOMAIN);
+    bytes.extend_from_slice(left);
+    bytes.extend_from_slice(right);
+
+    H::hash(&bytes)
+}
+
+/// Commitment to an empty sub-tree of the given height
+pub fn empty_hash<H>(height: u8) -> Digest
+where
+    H: MapHasher,
+{
+    (0..height).fold(EMPTY_LEAF, |e, _| sparse_node_hash::<H>(&e, &e))
+}
+
+/// Commitments to the empty sub-trees of every height up to `height`,
+/// indexed by height
+fn empty_hashes<H>(height: u8) -> [Digest; MAX_SPARSE_DEPTH as usize + 1]
+where
+    H: MapHasher,
+{
+    let mut empties = [EMPTY_LEAF; MAX_SPARSE_DEPTH as usize + 1];
+
+    for h in 1..=height as usize {
+        empties[h] = sparse_node_hash::<H>(&empties[h - 1], &empties[h - 1]);
+    }
+
+    empties
+}
+
+/// Check if `depth` is a valid depth for a [`SparseMap`]
+fn valid_depth(depth: u8) -> bool {
+    depth != 0 && depth <= MAX_SPARSE_DEPTH
+}
+
+/// Check if `key` addresses a slot of a [`SparseMap`] of `depth`
+fn within_depth(key: u64, depth: u8) -> bool {
+    depth >= MAX_SPARSE_DEPTH || key >> depth == 0
+}
+
+/// Hash annotation of a sub-tree of a [`SparseMap`].
+///
+/// Along with the digest of the sub-tree, the digest of an empty sub-tree of
+/// the same height is cached, so the missing siblings never need to be
+/// stored.
+pub struct SparseHash<H> {
+    digest: Digest,
+    empty: Digest,
+    _marker: PhantomData<H>,
+}
+
+impl<H> SparseHash<H> {
+    fn new(digest: Digest, empty: Digest) -> Self {
+        Self {
+            digest,
+            empty,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The committed digest
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+}
+
+impl<H> fmt::Debug for SparseHash<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SparseHash").field(&self.digest).finish()
+    }
+}
+
+impl<H> Clone for SparseHash<H> {
+    fn clone(&self) -> Self {
+        Self::new(self.digest, self.empty)
+    }
+}
+
+impl<H> Default for SparseHash<H> {
+    fn default() -> Self {
+        Self::new(EMPTY_LEAF, EMPTY_LEAF)
+    }
+}
+
+impl<H> Canon for SparseHash<H> {
+    fn encode(&self, sink: &mut Sink) {
+        self.digest.encode(sink);
+        self.empty.encode(sink);
+    }
+
+    fn decode(source: &mut Source) -> Result<Self, CanonError> {
+        let digest = Digest::decode(source)?;
+        let empty = Digest::decode(source)?;
+
+        Ok(Self::new(digest, empty))
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.digest.encoded_len() + self.empty.encoded_len()
+    }
+}
+
+impl<V, H> Annotation<Leaf<u64, V>> for SparseHash<H>
+where
+    V: Canon,
+    H: MapHasher,
+{
+    fn from_leaf(leaf: &Leaf<u64, V>) -> Self {
+        Self::new(leaf.hash::<H>(), EMPTY_LEAF)
+    }
+}
+
+impl<V, H> Combine<SparseTree<V, H>, SparseHash<H>> for SparseHash<H>
+where
+    V: Canon,
+    H: MapHasher,
+{
+    fn combine(node: &SparseTree<V, H>) -> Self {
+        match node {
+            SparseTree::Leaf(l) => Self::from_leaf(l),
+            SparseTree::Node(l, r) => {
+                // Both children have the same height, and at least one of
+                // them is present
+                let empty = l
+                    .as_ref()
+                    .or(r.as_ref())
+                    .map(|c| c.annotation().empty)
+                    .unwrap_or(EMPTY_LEAF);
+
+                let digest = |c: &Option<SparseChild<V, H>>| {
+                    c.as_ref().map(|c| c.annotation().digest).unwrap_or(empty)
+                };
+
+                Self::new(
+                    sparse_node_hash::<H>(&digest(l), &digest(r)),
+                    sparse_node_hash::<H>(&empty, &empty),
+                )
+            }
+        }
+    }
+}
+
+type SparseChild<V, H> = Annotated<SparseTree<V, H>, SparseHash<H>>;
+
+/// Updated sub-tree along with the previous value of the slot
+type SparseUpdate<V, H> = (Option<SparseChild<V, H>>, Option<V>);
+
+type SparseChildren<V, H> =
+    (Option<SparseChild<V, H>>, Option<SparseChild<V, H>>);
+
+#[derive(Debug, Clone, Canon)]
+/// Sub-tree of a [`SparseMap`]
+pub enum SparseTree<V, H> {
+    /// Slot at the bottom of the tree
+    Leaf(Leaf<u64, V>),
+    /// Node with its children, `None` standing for an empty sub-tree
+    Node(Option<SparseChild<V, H>>, Option<SparseChild<V, H>>),
+}
+
+impl<V, H> Compound<SparseHash<H>> for SparseTree<V, H>
+where
+    V: Canon,
+    H: MapHasher,
+{
+    type Leaf = Leaf<u64, V>;
+
+    fn child(&self, ofs: usize) -> Child<Self, SparseHash<H>> {
+        match (ofs, self) {
+            (0, SparseTree::Leaf(l)) => Child::Leaf(l),
+            (0, SparseTree::Node(Some(l), _)) => Child::Node(l),
+            (1, SparseTree::Node(_, Some(r))) => Child::Node(r),
+            (0, SparseTree::Node(None, _)) => Child::Empty,
+            (1, SparseTree::Node(_, None)) => Child::Empty,
+            _ => Child::EndOfNode,
+        }
+    }
+
+    fn child_mut(&mut self, ofs: usize) -> ChildMut<Self, SparseHash<H>> {
+        match (ofs, self) {
+            (0, SparseTree::Leaf(l)) => ChildMut::Leaf(l),
+            (0, SparseTree::Node(Some(l), _)) => ChildMut::Node(l),
+            (1, SparseTree::Node(_, Some(r))) => ChildMut::Node(r),
+            (0, SparseTree::Node(None, _)) => ChildMut::Empty,
+            (1, SparseTree::Node(_, None)) => ChildMut::Empty,
+            _ => ChildMut::EndOfNode,
+        }
+    }
+}
+
+/// Check if the path to `key` descends right at a node of `height`
+fn is_right(key: u64, height: u8) -> bool {
+    (key >> (height - 1)) & 1 == 1
+}
+
+/// Children of a node, or a pair of empty children if there is none
+fn children<V, H>(
+    node: Option<&SparseChild<V, H>>,
+) -> Result<SparseChildren<V, H>, CanonError>
+where
+    V: Canon,
+    H: MapHasher,
+{
+    match node {
+        None => Ok((None, None)),
+        Some(n) => match &*n.val()? {
+            SparseTree::Node(l, r) => Ok((l.clone(), r.clone())),
+            SparseTree::Leaf(_) => Err(CanonError::InvalidEncoding),
+        },
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Sparse tree over a fixed key space of `2^depth` slots.
+///
+/// Every key has a fixed path given by its bits, and the missing sub-trees
+/// are represented by the precomputed commitment to an empty sub-tree of
+/// their height, like in a sparse Merkle tree. Operations cost `O(depth)`
+/// regardless of the number of entries, and the proofs are also valid for
+/// missing keys.
+pub struct SparseMap<V, H> {
+    depth: u8,
+    root: Option<SparseChild<V, H>>,
+}
+
+impl<V, H> SparseMap<V, H>
+where
+    V: Canon,
+    H: MapHasher,
+{
+    /// Create an empty map with `2^depth` slots
+    ///
+    /// Will return [`MapError::InvalidConfig`] if `depth` is zero or greater
+    /// than [`MAX_SPARSE_DEPTH`].
+    pub fn new(depth: u8) -> Result<Self, MapError> {
+        if !valid_depth(depth) {
+            return Err(MapError::InvalidConfig);
+        }
+
+        Ok(Self { depth, root: None })
+    }
+
+    /// Number of bits of the keys
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Check if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    fn check_key(&self, key: u64) -> Result<(), MapError> {
+        if !within_depth(key, self.depth) {
+            return Err(MapError::OutOfRange);
+        }
+
+        Ok(())
+    }
+
+    /// Commitment to the whole contents of the map
+    pub fn root_hash(&self) -> Digest {
+        self.root
+            .as_ref()
+            .map(|r| r.annotation().digest)
+            .unwrap_or_else(|| empty_hash::<H>(self.depth))
+    }
+
+    /// Returns a clone of the value stored in the slot `key`
+    pub fn get(&self, key: u64) -> Result<Option<V>, MapError> {
+        self.check_key(key)?;
+
+        let mut node = self.root.clone();
+        for height in (1..=self.depth).rev() {
+            let (l, r) = children(node.as_ref())?;
+            node = if is_right(key, height) { r } else { l };
+        }
+
+        match node {
+            None => Ok(None),
+            Some(n) => match &*n.val()? {
+                SparseTree::Leaf(l) => Ok(Some(l.value().clone())),
+                SparseTree::Node(..) => Err(CanonError::InvalidEncoding.into()),
+            },
+        }
+    }
+
+    /// Store `value` in the slot `key`, returning the previous value
+    pub fn insert(
+        &mut self,
+        key: u64,
+        value: V,
+    ) -> Result<Option<V>, MapError> {
+        self.check_key(key)?;
+
+        let leaf = Leaf::new(key, value);
+        let (root, old) =
+            Self::update(self.root.as_ref(), self.depth, key, Some(leaf))?;
+        self.root = root;
+
+        Ok(old)
+    }
+
+    /// Clear the slot `key`, returning the previous value
+    pub fn remove(&mut self, key: u64) -> Result<Option<V>, MapError> {
+        self.check_key(key)?;
+
+        let (root, old) =
+            Self::update(self.root.as_ref(), self.depth, key, None)?;
+        self.root = root;
+
+        Ok(old)
+    }
+
+    /// Replace the slot `key` below `node` with `leaf`, dropping the
+    /// sub-trees that become empty
+    fn update(
+        node: Option<&SparseChild<V, H>>,
+        height: u8,
+        key: u64,
+        leaf: Option<Leaf<u64, V>>,
+    ) -> Result<SparseUpdate<V, H>, CanonError> {
+        if height == 0 {
+            let old = match node {
+                Some(n) => match &*n.val()? {
+                    SparseTree::Leaf(l) => Some(l.value().clone()),
+                    SparseTree::Node(..) => {
+                        return Err(CanonError::InvalidEncoding)
+                    }
+                },
+                None => None,
+            };
+
+            let new = leaf.map(|l| Annotated::new(SparseTree::Leaf(l)));
+            return Ok((new, old));
+        }
+
+        let (mut l, mut r) = children(node)?;
+        let old = if is_right(key, height) {
+            let (new, old) = Self::update(r.as_ref(), height - 1, key, leaf)?;
+            r = new;
+            old
+        } else {
+            let (new, old) = Self::update(l.as_ref(), height - 1, key, leaf)?;
+            l = new;
+            old
+        };
+
+        let new = match (l, r) {
+            (None, None) => None,
+            (l, r) => Some(Annotated::new(SparseTree::Node(l, r))),
+        };
+
+        Ok((new, old))
+    }
+
+    /// Generate a proof of the contents of the slot `key`, valid both for
+    /// present and missing keys
+    pub fn prove(&self, key: u64) -> Result<SparseProof<V>, MapError> {
+        self.check_key(key)?;
+
+        let empties = empty_hashes::<H>(self.depth);
+        let mut siblings = Vec::with_capacity(self.depth as usize);
+        let mut node = self.root.clone();
+
+        for height in (1..=self.depth).rev() {
+            let (l, r) = children(node.as_ref())?;
+            let (next, sibling) = if is_right(key, height) {
+                (r, l)
+            } else {
+                (l, r)
+            };
+
+            siblings.push(
+                sibling
+                    .map(|s| s.annotation().digest)
+                    .unwrap_or(empties[height as usize - 1]),
+            );
+            node = next;
+        }
+
+        let value = match node {
+            None => None,
+            Some(n) => match &*n.val()? {
+                SparseTree::Leaf(l) => Some(l.value().clone()),
+                SparseTree::Node(..) => {
+                    return Err(CanonError::InvalidEncoding.into())
+                }
+            },
+        };
+
+        siblings.reverse();
+
+        Ok(SparseProof { value, siblings })
+    }
+}
+
+impl<V, H> Canon for SparseMap<V, H>
+where
+    V: Canon,
+    H: MapHasher,
+{
+    fn encode(&self, sink: &mut Sink) {
+        self.depth.encode(sink);
+        self.root.encode(sink);
+    }
+
+    fn decode(source: &mut Source) -> Result<Self, CanonError> {
+        let depth = u8::decode(source)?;
+        if !valid_depth(depth) {
+            return Err(CanonError::InvalidEncoding);
+        }
+
+        let root = Option::decode(source)?;
+
+        Ok(Self { depth, root })
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.depth.encoded_len() + self.root.encoded_len()
+    }
+}
+
+#[derive(Debug, Clone, Canon)]
+/// Proof of the contents of a slot of a [`SparseMap`]
+pub struct SparseProof<V> {
+    value: Option<V>,
+    siblings: Vec<Digest>,
+}
+
+impl<V> SparseProof<V>
+where
+    V: Canon,
+{
+    /// Proven value of the slot, `None` if the slot is empty
+    pub fn value(&self) -> Option<&V> {
+        self.value.as_ref()
+    }
+
+    /// Digests of the siblings of the path from the slot to the root,
+    /// bottom-up
+    pub fn siblings(&self) -> &[Digest] {
+        &self.siblings
+    }
+
+    /// Check the proof for the slot `key` against the root hash of a map of
+    /// `depth`, as returned by [`SparseMap::root_hash`]
+    ///
+    /// Proofs with a number of siblings other than `depth` are rejected.
+    pub fn verify<H>(&self, root: &Digest, key: u64, depth: u8) -> bool
+    where
+        H: MapHasher,
+    {
+        if !valid_depth(depth)
+            || self.siblings.len() != depth as usize
+            || !within_depth(key, depth)
+        {
+            return false;
+        }
+
+        let mut digest = match &self.value {
+            Some(v) => Leaf::new(key, v.clone()).hash::<H>(),
+            None => EMPTY_LEAF,
+        };
+
+        for (i, sibling) in self.siblings.iter().enumerate() {
+            digest = if is_right(key, i as u8 + 1) {
+                sparse_node_hash::<H>(sibling, &digest)
+            } else {
+                sparse_node_hash::<H>(&digest, sibling)
+            };
+        }
+
+        &digest == root
+    }
+}
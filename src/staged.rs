@@ -0,0 +1,147 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, MapAnnotation, MapError};
+
+use core::ops::{Deref, DerefMut};
+
+use canonical::Canon;
+
+/// Check run on a staged map when it is prepared
+pub type StagedCheck<K, V, A> = fn(&KelvinMap<K, V, A>) -> Result<(), MapError>;
+
+/// Participant of a two-phase commit over several maps, driven by
+/// [`commit_all`]
+pub trait TwoPhase {
+    /// Check the staged changes can be committed
+    fn prepare(&mut self) -> Result<(), MapError>;
+
+    /// Publish the staged changes, once every participant is prepared
+    fn commit(&mut self);
+
+    /// Discard the staged changes
+    fn abort(&mut self);
+}
+
+/// Changes to a map staged on a copy of its root, created by
+/// [`KelvinMap::stage`].
+///
+/// The writes are performed on the staged copy, accessible via `Deref`, and
+/// the map is left untouched until they are committed. Committing swaps the
+/// staged root into the map, so it can't fail. Dropping the guard without
+/// committing discards the changes.
+pub struct StagedMap<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    map: &'a mut KelvinMap<K, V, A>,
+    staged: KelvinMap<K, V, A>,
+    check: Option<StagedCheck<K, V, A>>,
+}
+
+impl<'a, K, V, A> StagedMap<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Run `check` on the staged map when it is prepared, voting against the
+    /// commit if it fails
+    pub fn with_check(mut self, check: StagedCheck<K, V, A>) -> Self {
+        self.check = Some(check);
+        self
+    }
+
+    /// Prepare and commit the staged changes of this map alone
+    pub fn commit(mut self) -> Result<(), MapError> {
+        commit_all(&mut [&mut self])
+    }
+}
+
+impl<'a, K, V, A> TwoPhase for StagedMap<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn prepare(&mut self) -> Result<(), MapError> {
+        match self.check {
+            Some(check) => check(&self.staged),
+            None => Ok(()),
+        }
+    }
+
+    fn commit(&mut self) {
+        *self.map = self.staged.clone();
+    }
+
+    fn abort(&mut self) {
+        self.staged = self.map.clone();
+    }
+}
+
+impl<'a, K, V, A> Deref for StagedMap<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    type Target = KelvinMap<K, V, A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.staged
+    }
+}
+
+impl<'a, K, V, A> DerefMut for StagedMap<'a, K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.staged
+    }
+}
+
+/// Commit the staged changes of several maps together.
+///
+/// Every participant is prepared first. If any of them fails, all of them
+/// are aborted and the error is returned, otherwise all of them are
+/// committed.
+pub fn commit_all(
+    participants: &mut [&mut dyn TwoPhase],
+) -> Result<(), MapError> {
+    let prepared = participants.iter_mut().try_for_each(|p| p.prepare());
+
+    match prepared {
+        Ok(()) => participants.iter_mut().for_each(|p| p.commit()),
+        Err(_) => participants.iter_mut().for_each(|p| p.abort()),
+    }
+
+    prepared
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Stage changes to the map, to be committed together with the changes
+    /// to other maps with [`commit_all`]
+    pub fn stage(&mut self) -> StagedMap<'_, K, V, A> {
+        let staged = self.clone();
+
+        StagedMap {
+            map: self,
+            staged,
+            check: None,
+        }
+    }
+}
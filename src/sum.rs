@@ -0,0 +1,298 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation, MapError};
+
+use core::borrow::Borrow;
+use core::fmt;
+use core::marker::PhantomData;
+
+use canonical::{Canon, CanonError, Sink, Source};
+use canonical_derive::Canon;
+use microkelvin::{Annotation, Cardinality, Combine, Compound, MaxKey};
+
+/// Values that contribute a weight to a [`Sum`] annotation
+pub trait Weighted {
+    /// Weight of the value
+    fn weight(&self) -> u64;
+}
+
+macro_rules! impl_weighted {
+    ($($t:ty),*) => {
+        $(
+            impl Weighted for $t {
+                fn weight(&self) -> u64 {
+                    *self as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_weighted!(u8, u16, u32, u64);
+
+/// Overflow behavior of a [`Sum`] annotation.
+///
+/// The policy is a marker type parameter of the annotation, so it is required
+/// to be a canonical, defaultable type.
+pub trait OverflowPolicy: Canon + Default + fmt::Debug {
+    /// Add two partial sums, returning `None` if the result overflows
+    fn add(a: u64, b: u64) -> Option<u64>;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Canon)]
+/// Clamp the sum at `u64::MAX`
+pub struct Saturating;
+
+impl OverflowPolicy for Saturating {
+    fn add(a: u64, b: u64) -> Option<u64> {
+        Some(a.saturating_add(b))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Canon)]
+/// Flag the sum as overflown, so reading it results in
+/// [`MapError::Overflow`]
+pub struct Checked;
+
+impl OverflowPolicy for Checked {
+    fn add(a: u64, b: u64) -> Option<u64> {
+        a.checked_add(b)
+    }
+}
+
+/// Annotation with the sum of the [`Weighted`] values of a sub-tree.
+///
+/// The sum never wraps: depending on the [`OverflowPolicy`] it is either
+/// clamped, or the overflow is propagated up to the root.
+pub struct Sum<O> {
+    sum: Option<u64>,
+    _marker: PhantomData<O>,
+}
+
+impl<O> Sum<O> {
+    fn new(sum: Option<u64>) -> Self {
+        Self {
+            sum,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The sum of the weights, or [`MapError::Overflow`] if it overflowed
+    pub fn value(&self) -> Result<u64, MapError> {
+        self.sum.ok_or(MapError::Overflow)
+    }
+}
+
+impl<O> fmt::Debug for Sum<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Sum").field(&self.sum).finish()
+    }
+}
+
+impl<O> Clone for Sum<O> {
+    fn clone(&self) -> Self {
+        Self::new(self.sum)
+    }
+}
+
+impl<O> Default for Sum<O> {
+    fn default() -> Self {
+        Self::new(Some(0))
+    }
+}
+
+impl<O> PartialEq for Sum<O> {
+    fn eq(&self, other: &Self) -> bool {
+        self.sum == other.sum
+    }
+}
+
+impl<O> Eq for Sum<O> {}
+
+impl<O> Canon for Sum<O> {
+    fn encode(&self, sink: &mut Sink) {
+        self.sum.encode(sink)
+    }
+
+    fn decode(source: &mut Source) -> Result<Self, CanonError> {
+        Option::decode(source).map(Self::new)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.sum.encoded_len()
+    }
+}
+
+impl<K, V, O> Annotation<Leaf<K, V>> for Sum<O>
+where
+    K: Ord,
+    V: Weighted,
+{
+    fn from_leaf(leaf: &Leaf<K, V>) -> Self {
+        Self::new(Some(leaf.value().weight()))
+    }
+}
+
+impl<K, V, A, O> Combine<KelvinMap<K, V, A>, A> for Sum<O>
+where
+    K: Canon + Ord,
+    V: Canon + Weighted,
+    A: MapAnnotation<K, V> + Borrow<Sum<O>>,
+    O: OverflowPolicy,
+{
+    fn combine(node: &KelvinMap<K, V, A>) -> Self {
+        let sum = node.children().try_fold(0u64, |sum, child| {
+            let annotation = child.annotation();
+            let child: &Sum<O> = (*annotation).borrow();
+
+            child.sum.and_then(|s| O::add(sum, s))
+        });
+
+        Self::new(sum)
+    }
+}
+
+#[derive(Debug, Clone, Default, Canon)]
+/// Annotation tracking the sum of the values of a map along with the
+/// requirements of [`MapAnnotation`]
+pub struct MapAnnotationSum<K, O>
+where
+    K: Canon + Ord + Default,
+{
+    cardinality: Cardinality,
+    max: MaxKey<K>,
+    sum: Sum<O>,
+}
+
+impl<K, O> Borrow<MaxKey<K>> for MapAnnotationSum<K, O>
+where
+    K: Canon + Ord + Default,
+{
+    fn borrow(&self) -> &MaxKey<K> {
+        &self.max
+    }
+}
+
+impl<K, O> Borrow<Cardinality> for MapAnnotationSum<K, O>
+where
+    K: Canon + Ord + Default,
+{
+    fn borrow(&self) -> &Cardinality {
+        &self.cardinality
+    }
+}
+
+impl<K, O> Borrow<Sum<O>> for MapAnnotationSum<K, O>
+where
+    K: Canon + Ord + Default,
+{
+    fn borrow(&self) -> &Sum<O> {
+        &self.sum
+    }
+}
+
+impl<K, V, O> Annotation<Leaf<K, V>> for MapAnnotationSum<K, O>
+where
+    K: Canon + Ord + Default,
+    V: Canon + Weighted,
+    O: OverflowPolicy,
+{
+    fn from_leaf(leaf: &Leaf<K, V>) -> Self {
+        let cardinality = Cardinality::from_leaf(leaf);
+        let max = MaxKey::from_leaf(leaf);
+        let sum = Sum::from_leaf(leaf);
+
+        Self {
+            cardinality,
+            max,
+            sum,
+        }
+    }
+}
+
+impl<K, V, O>
+    Combine<KelvinMap<K, V, MapAnnotationSum<K, O>>, MapAnnotationSum<K, O>>
+    for MapAnnotationSum<K, O>
+where
+    K: Canon + Ord + Default,
+    V: Canon + Weighted,
+    O: OverflowPolicy,
+{
+    fn combine(node: &KelvinMap<K, V, MapAnnotationSum<K, O>>) -> Self {
+        let cardinality = Cardinality::combine(node);
+        let max = MaxKey::combine(node);
+        let sum = Sum::combine(node);
+
+        Self {
+            cardinality,
+            max,
+            sum,
+        }
+    }
+}
+
+impl<K, V, O> MapAnnotation<K, V> for MapAnnotationSum<K, O>
+where
+    K: Canon + Ord + Default,
+    V: Canon + Weighted,
+    O: OverflowPolicy,
+{
+    fn check(&self) -> Result<(), MapError> {
+        self.sum.value().map(|_| ())
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon + Weighted,
+    A: MapAnnotation<K, V>,
+{
+    /// Sum of the weights of all the values of the map, computed from the
+    /// cached annotations of the root.
+    ///
+    /// Will return [`MapError::Overflow`] if the sum overflowed with the
+    /// [`Checked`] policy.
+    pub fn sum<O>(&self) -> Result<u64, MapError>
+    where
+        A: Borrow<Sum<O>>,
+        O: OverflowPolicy,
+    {
+        Sum::<O>::combine(self).value()
+    }
+
+    /// Returns the smallest key at which the prefix sum of the weights of the
+    /// values reaches `target`, or `None` if the total sum is below it.
+    ///
+    /// The tree is descended once, guided by the sums of the left children.
+    /// With the [`Saturating`] policy, saturated sub-trees are treated as if
+    /// their sum was `u64::MAX`.
+    pub fn threshold_key<O>(&self, target: u64) -> Result<Option<K>, MapError>
+    where
+        A: Borrow<Sum<O>>,
+        O: OverflowPolicy,
+    {
+        match self {
+            KelvinMap::Empty => Ok(None),
+            KelvinMap::Leaf(l) if l.value().weight() >= target => {
+                Ok(Some(l._key().clone()))
+            }
+            KelvinMap::Leaf(_) => Ok(None),
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let sum_l: &Sum<O> = l.annotation().borrow();
+                let sum_l = sum_l.value()?;
+
+                if sum_l >= target {
+                    l.val()?.threshold_key::<O>(target)
+                } else {
+                    r.val()?.threshold_key::<O>(target - sum_l)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,270 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation};
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use canonical::{Canon, CanonError, EncodeToVec, IdHash, Sink, Source, Store};
+use canonical_derive::Canon;
+
+/// Maximum number of bits used to select the bucket of a key.
+pub const MAX_BUCKET_BITS: u8 = 16;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Canon)]
+/// Summary of the entries of a [`KeyDigest`] bucket
+pub struct BucketDigest {
+    count: u64,
+    xor: IdHash,
+}
+
+impl BucketDigest {
+    /// Number of entries that fall in this bucket
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// XOR of the hashes of every `(K, V)` entry that fall in this bucket
+    pub fn xor(&self) -> &IdHash {
+        &self.xor
+    }
+
+    fn push(&mut self, entry: &IdHash) {
+        self.count += 1;
+        self.xor
+            .iter_mut()
+            .zip(entry.iter())
+            .for_each(|(x, e)| *x ^= e);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Compact summary of the contents of a map.
+///
+/// The keys are distributed in `2^bucket_bits` buckets according to the hash
+/// of their canonical encoding. Two peers holding a digest of the same
+/// `bucket_bits` can compare them bucket-wise to find out which parts of the
+/// key space differ, before exchanging the actual entries.
+///
+/// A digest received from a peer is checked when decoded, so its
+/// `bucket_bits` never exceeds [`MAX_BUCKET_BITS`].
+pub struct KeyDigest {
+    bucket_bits: u8,
+    buckets: Vec<BucketDigest>,
+}
+
+impl KeyDigest {
+    /// Number of bits of the key hash used to select a bucket
+    pub fn bucket_bits(&self) -> u8 {
+        self.bucket_bits
+    }
+
+    /// Summaries of every bucket, indexed by bucket
+    pub fn buckets(&self) -> &[BucketDigest] {
+        &self.buckets
+    }
+
+    /// Bucket index of a given key for this digest
+    pub fn bucket_of<K>(&self, key: &K) -> usize
+    where
+        K: Canon,
+    {
+        bucket_of(key_prefix(key), self.bucket_bits)
+    }
+}
+
+impl Canon for KeyDigest {
+    fn encode(&self, sink: &mut Sink) {
+        self.bucket_bits.encode(sink);
+        self.buckets.encode(sink);
+    }
+
+    fn decode(source: &mut Source) -> Result<Self, CanonError> {
+        let bucket_bits = u8::decode(source)?;
+        if bucket_bits > MAX_BUCKET_BITS {
+            return Err(CanonError::InvalidEncoding);
+        }
+
+        let buckets: Vec<BucketDigest> = Vec::decode(source)?;
+        if buckets.len() != 1 << bucket_bits {
+            return Err(CanonError::InvalidEncoding);
+        }
+
+        Ok(Self {
+            bucket_bits,
+            buckets,
+        })
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.bucket_bits.encoded_len() + self.buckets.encoded_len()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Request for all the entries of a bucket of a [`KeyDigest`].
+///
+/// A request received from a peer is checked when decoded, so its
+/// `bucket_bits` never exceeds [`MAX_BUCKET_BITS`] and its bucket is one of
+/// the `2^bucket_bits` buckets.
+pub struct RangeRequest {
+    bucket_bits: u8,
+    bucket: u32,
+}
+
+impl RangeRequest {
+    /// Number of bits of the key hash used to select a bucket
+    pub fn bucket_bits(&self) -> u8 {
+        self.bucket_bits
+    }
+
+    /// Index of the requested bucket
+    pub fn bucket(&self) -> usize {
+        self.bucket as usize
+    }
+}
+
+impl Canon for RangeRequest {
+    fn encode(&self, sink: &mut Sink) {
+        self.bucket_bits.encode(sink);
+        self.bucket.encode(sink);
+    }
+
+    fn decode(source: &mut Source) -> Result<Self, CanonError> {
+        let bucket_bits = u8::decode(source)?;
+        let bucket = u32::decode(source)?;
+
+        if bucket_bits > MAX_BUCKET_BITS || bucket >> bucket_bits != 0 {
+            return Err(CanonError::InvalidEncoding);
+        }
+
+        Ok(Self {
+            bucket_bits,
+            bucket,
+        })
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.bucket_bits.encoded_len() + self.bucket.encoded_len()
+    }
+}
+
+/// First 32 bits of the hash of the canonical encoding of a key
+fn key_prefix<K>(key: &K) -> u32
+where
+    K: Canon,
+{
+    let hash = Store::hash(&key.encode_to_vec());
+    u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+}
+
+/// Bucket of a key hash prefix, for `bucket_bits` up to [`MAX_BUCKET_BITS`]
+fn bucket_of(prefix: u32, bucket_bits: u8) -> usize {
+    match bucket_bits {
+        0 => 0,
+        bits => (prefix >> (32 - bits as u32)) as usize,
+    }
+}
+
+fn entry_hash<K, V>(leaf: &Leaf<K, V>) -> IdHash
+where
+    K: Canon,
+    V: Canon,
+{
+    Store::hash(&leaf.encode_to_vec())
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Summarize the entries of the map in `2^bucket_bits` buckets.
+    ///
+    /// `bucket_bits` is capped at [`MAX_BUCKET_BITS`].
+    pub fn key_digest(&self, bucket_bits: u8) -> Result<KeyDigest, CanonError> {
+        let bucket_bits = bucket_bits.min(MAX_BUCKET_BITS);
+        let mut buckets = vec![BucketDigest::default(); 1 << bucket_bits];
+
+        self.for_each_leaf(&mut |leaf| {
+            buckets[bucket_of(key_prefix(leaf._key()), bucket_bits)]
+                .push(&entry_hash(leaf));
+        })?;
+
+        Ok(KeyDigest {
+            bucket_bits,
+            buckets,
+        })
+    }
+
+    /// Compare the map against the digest of a remote peer, returning the
+    /// requests for the buckets that differ.
+    ///
+    /// The remote peer is expected to answer with [`KelvinMap::serve_ranges`],
+    /// so the transferred data is proportional to the difference between the
+    /// maps and not to their size.
+    pub fn reconcile(
+        &self,
+        remote: &KeyDigest,
+    ) -> Result<Vec<RangeRequest>, CanonError> {
+        let local = self.key_digest(remote.bucket_bits)?;
+
+        if local.buckets.len() != remote.buckets.len() {
+            return Err(CanonError::InvalidEncoding);
+        }
+
+        Ok(local
+            .buckets
+            .iter()
+            .zip(remote.buckets.iter())
+            .enumerate()
+            .filter(|(_, (l, r))| l != r)
+            .map(|(bucket, _)| RangeRequest {
+                bucket_bits: local.bucket_bits,
+                bucket: bucket as u32,
+            })
+            .collect())
+    }
+
+    /// Return all the entries that belong to any of the requested buckets, in
+    /// key order.
+    ///
+    /// The key of every entry is hashed once, and its bucket looked up in the
+    /// set of requested buckets.
+    pub fn serve_ranges(
+        &self,
+        requests: &[RangeRequest],
+    ) -> Result<Vec<(K, V)>, CanonError> {
+        let mut requested: BTreeMap<u8, BTreeSet<usize>> = BTreeMap::new();
+        for r in requests {
+            requested
+                .entry(r.bucket_bits)
+                .or_default()
+                .insert(r.bucket());
+        }
+
+        let mut entries = vec![];
+        if requested.is_empty() {
+            return Ok(entries);
+        }
+
+        self.for_each_leaf(&mut |leaf| {
+            let prefix = key_prefix(leaf._key());
+            let wanted = requested.iter().any(|(bits, buckets)| {
+                buckets.contains(&bucket_of(prefix, *bits))
+            });
+
+            if wanted {
+                entries.push((leaf._key().clone(), leaf.value().clone()));
+            }
+        })?;
+
+        Ok(entries)
+    }
+}
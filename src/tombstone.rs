@@ -0,0 +1,149 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::Map;
+
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+use canonical::{Canon, CanonError};
+use canonical_derive::Canon;
+
+#[derive(Debug, Clone, PartialEq, Eq, Canon)]
+/// State of a key of a [`TombstoneMap`]
+pub enum Slot<V> {
+    /// The key is mapped to a value
+    Live(V),
+    /// The key was removed at the provided stamp
+    Tombstone(u64),
+}
+
+impl<V> Slot<V> {
+    /// Value of a live key
+    pub fn live(&self) -> Option<&V> {
+        match self {
+            Slot::Live(v) => Some(v),
+            Slot::Tombstone(_) => None,
+        }
+    }
+
+    /// Deletion stamp of a removed key
+    pub fn tombstone(&self) -> Option<u64> {
+        match self {
+            Slot::Live(_) => None,
+            Slot::Tombstone(stamp) => Some(*stamp),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Canon)]
+/// Map keeping a stamped tombstone in place of every removed key, so the
+/// deletions can be replicated to peers that are lagging behind.
+///
+/// The tombstones are part of the contents of the map, and its commitments,
+/// until they are purged with [`TombstoneMap::purge_tombstones_before`].
+pub struct TombstoneMap<K, V>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+{
+    slots: Map<K, Slot<V>>,
+}
+
+impl<K, V> TombstoneMap<K, V>
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+{
+    /// Underlying map of the slots, including the tombstones
+    pub fn slots(&self) -> &Map<K, Slot<V>> {
+        &self.slots
+    }
+
+    /// Returns a reference to the slot of the key, live or removed
+    pub fn slot<'a>(
+        &'a self,
+        k: &K,
+    ) -> Result<Option<impl Deref<Target = Slot<V>> + 'a>, CanonError> {
+        self.slots.get(k)
+    }
+
+    /// Returns a copy of the value of the key, if live
+    pub fn get(&self, k: &K) -> Result<Option<V>, CanonError> {
+        Ok(self.slots.get(k)?.and_then(|slot| slot.live().cloned()))
+    }
+
+    /// Check if the key is live
+    pub fn contains_key(&self, k: &K) -> Result<bool, CanonError> {
+        Ok(self.get(k)?.is_some())
+    }
+
+    /// Include a key -> value mapping to the map, replacing its tombstone,
+    /// if any.
+    ///
+    /// Returns the previous value of a live key.
+    pub fn insert(&mut self, k: K, v: V) -> Result<Option<V>, CanonError> {
+        let previous = self.slots.insert(k, Slot::Live(v))?;
+
+        Ok(previous.and_then(|slot| match slot {
+            Slot::Live(v) => Some(v),
+            Slot::Tombstone(_) => None,
+        }))
+    }
+
+    /// Replace the value of a live key with a tombstone stamped with `stamp`.
+    ///
+    /// Returns the removed value. The stamp of an existing tombstone is kept.
+    pub fn remove(
+        &mut self,
+        k: &K,
+        stamp: u64,
+    ) -> Result<Option<V>, CanonError> {
+        let mut slot = match self.slots.get_mut(k)? {
+            Some(slot) => slot,
+            None => return Ok(None),
+        };
+
+        match core::mem::replace(&mut *slot, Slot::Tombstone(stamp)) {
+            Slot::Live(v) => Ok(Some(v)),
+            Slot::Tombstone(previous) => {
+                *slot = Slot::Tombstone(previous);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Delete the tombstones stamped before `stamp`, returning how many were
+    /// deleted
+    pub fn purge_tombstones_before(
+        &mut self,
+        stamp: u64,
+    ) -> Result<u64, CanonError> {
+        let mut expired = Vec::new();
+        for entry in self.slots.iter()? {
+            let (k, slot) = entry?;
+
+            if slot.tombstone().map(|s| s < stamp).unwrap_or(false) {
+                expired.push(k.clone());
+            }
+        }
+
+        self.slots.remove_many(&expired)
+    }
+
+    /// Iterate the live entries in key order
+    pub fn iter<'a>(
+        &'a self,
+    ) -> Result<
+        impl Iterator<Item = Result<(&'a K, &'a V), CanonError>> + 'a,
+        CanonError,
+    > {
+        Ok(self.slots.iter()?.filter_map(|entry| match entry {
+            Ok((k, slot)) => slot.live().map(|v| Ok((k, v))),
+            Err(e) => Some(Err(e)),
+        }))
+    }
+}
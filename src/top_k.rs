@@ -0,0 +1,151 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation};
+
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use canonical::{Canon, CanonError};
+use microkelvin::Annotated;
+
+/// Sub-tree pending in the best-first descent, ranked by the metric of its
+/// annotation
+struct Ranked<K, V, A, T>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    annotation: A,
+    tree: KelvinMap<K, V, A>,
+    select: fn(&A) -> &T,
+}
+
+impl<K, V, A, T> Ranked<K, V, A, T>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    fn metric(&self) -> &T {
+        (self.select)(&self.annotation)
+    }
+}
+
+impl<K, V, A, T> PartialEq for Ranked<K, V, A, T>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    T: Ord,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.metric() == other.metric()
+    }
+}
+
+impl<K, V, A, T> Eq for Ranked<K, V, A, T>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    T: Ord,
+{
+}
+
+impl<K, V, A, T> PartialOrd for Ranked<K, V, A, T>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    T: Ord,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K, V, A, T> Ord for Ranked<K, V, A, T>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    T: Ord,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.metric().cmp(other.metric())
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Returns the `k` leaves with the largest metric, as selected by
+    /// `select` out of their annotations, in descending order of the metric.
+    ///
+    /// The metric of a node must be the maximum of the metrics of its
+    /// children, such as the maximum stake of a sub-tree. The sub-trees are
+    /// then descended best-first, so only the paths to the selected leaves
+    /// and their siblings are fetched.
+    pub fn top_k_by<T>(
+        &self,
+        k: usize,
+        select: fn(&A) -> &T,
+    ) -> Result<Vec<Leaf<K, V>>, CanonError>
+    where
+        T: Ord,
+    {
+        let mut leaves = Vec::with_capacity(k);
+        let mut heap = BinaryHeap::new();
+
+        let push_children = |heap: &mut BinaryHeap<_>, tree: &Self| {
+            if let KelvinMap::Node(n) = tree {
+                for child in [n.left(), n.right()].iter() {
+                    heap.push(Self::ranked(child, select)?);
+                }
+            }
+
+            Ok::<_, CanonError>(())
+        };
+
+        match self {
+            KelvinMap::Empty => (),
+            KelvinMap::Leaf(l) if k > 0 => leaves.push(l.clone()),
+            KelvinMap::Leaf(_) => (),
+            node => push_children(&mut heap, node)?,
+        }
+
+        while leaves.len() < k {
+            match heap.pop() {
+                None => break,
+                Some(Ranked {
+                    tree: KelvinMap::Leaf(l),
+                    ..
+                }) => leaves.push(l),
+                Some(ranked) => push_children(&mut heap, &ranked.tree)?,
+            }
+        }
+
+        Ok(leaves)
+    }
+
+    /// Clone a child out of its node to be ranked
+    fn ranked<T>(
+        child: &Annotated<Self, A>,
+        select: fn(&A) -> &T,
+    ) -> Result<Ranked<K, V, A, T>, CanonError> {
+        Ok(Ranked {
+            annotation: child.annotation().clone(),
+            tree: child.val()?.clone(),
+            select,
+        })
+    }
+}
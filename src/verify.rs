@@ -0,0 +1,305 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::diff::split_key;
+use crate::map::{cardinality, cmp_max_key};
+use crate::{KelvinMap, MapAnnotation, MapError};
+
+use core::ops::Deref;
+
+use canonical::{Canon, EncodeToVec, Source};
+use microkelvin::Annotated;
+use rand_core::RngCore;
+
+/// Check the cached annotation of a sub-tree matches the one computed from its
+/// children.
+///
+/// The annotations are compared by their encoding, so every part of them is
+/// checked, such as the [`MapHash`](crate::MapHash) or the
+/// [`Sum`](crate::Sum) of the sub-tree.
+pub(crate) fn annotation_matches<K, V, A>(
+    annotated: &Annotated<KelvinMap<K, V, A>, A>,
+    tree: &KelvinMap<K, V, A>,
+) -> bool
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    let stored = annotated.annotation();
+    let computed = A::combine(tree);
+
+    stored.encode_to_vec() == computed.encode_to_vec()
+}
+
+#[derive(Debug, Clone)]
+/// Map decoded from untrusted bytes, verified lazily.
+///
+/// The top levels of the tree are verified when decoding, and the deeper
+/// nodes along the path of every lookup are verified before it is answered.
+pub struct VerifiedMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    map: KelvinMap<K, V, A>,
+    depth: usize,
+}
+
+impl<K, V, A> VerifiedMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Number of levels verified when decoding
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns a reference to the value corresponding to the key
+    ///
+    /// The nodes along the path to the key below the verified levels are
+    /// checked as in [`KelvinMap::validate`] first.
+    pub fn get<'a>(
+        &'a self,
+        k: &K,
+    ) -> Result<Option<impl Deref<Target = V> + 'a>, MapError> {
+        self.map.verify_key(k, self.depth, None, None)?;
+
+        self.map.get(k).map_err(MapError::from)
+    }
+
+    /// Verify the remaining levels of the tree, returning the verified map
+    pub fn validate(self) -> Result<KelvinMap<K, V, A>, MapError> {
+        self.map.validate()?;
+
+        Ok(self.map)
+    }
+
+    /// Returns the map, with its deeper levels left unverified
+    pub fn into_inner(self) -> KelvinMap<K, V, A> {
+        self.map
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Decode a map from `bytes`, verifying the top `depth` levels of the tree.
+    ///
+    /// The nodes of the verified levels are fetched eagerly, while the deeper
+    /// ones are verified by the returned [`VerifiedMap`] along the path of
+    /// each lookup.
+    pub fn decode_verified(
+        bytes: &[u8],
+        depth: usize,
+    ) -> Result<VerifiedMap<K, V, A>, MapError> {
+        let map = Self::decode(&mut Source::new(bytes))?;
+
+        map.validate_depth(depth)?;
+
+        Ok(VerifiedMap { map, depth })
+    }
+
+    /// Verify the whole tree.
+    ///
+    /// Every node is fetched, its cached annotation checked against its
+    /// children, and the keys checked to be in strictly ascending order.
+    pub fn validate(&self) -> Result<(), MapError> {
+        self.validate_depth(usize::MAX)
+    }
+
+    /// Verify the top `depth` levels of the tree.
+    ///
+    /// See [`KelvinMap::validate`].
+    pub fn validate_depth(&self, depth: usize) -> Result<(), MapError> {
+        self.verify(depth, None)
+    }
+
+    fn verify(&self, depth: usize, lower: Option<&K>) -> Result<(), MapError> {
+        let above = |key: &K| lower.map(|lower| key > lower).unwrap_or(true);
+
+        match self {
+            KelvinMap::Empty => Ok(()),
+
+            KelvinMap::Leaf(l) if above(l._key()) => Ok(()),
+            KelvinMap::Leaf(_) => Err(MapError::InvalidOrder),
+
+            // The deeper levels are trusted, but the cached maximum can still
+            // be checked against the lower bound
+            KelvinMap::Node(..) if depth == 0 => match self.max_key() {
+                Some(max) if above(&max) => Ok(()),
+                _ => Err(MapError::InvalidOrder),
+            },
+
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let (left, right) = (l.val()?, r.val()?);
+                Self::verify_node(l, &left, r, &right)?;
+
+                left.verify(depth - 1, lower)?;
+                right.verify(depth - 1, left.max_key().as_ref())
+            }
+        }
+    }
+
+    /// Verify the path to `k`, skipping the top `verified` levels, with the
+    /// keys of the sub-tree above `lower` and up to `upper`
+    fn verify_key(
+        &self,
+        k: &K,
+        verified: usize,
+        lower: Option<&K>,
+        upper: Option<&K>,
+    ) -> Result<(), MapError> {
+        let n = match self {
+            KelvinMap::Node(n) => n,
+            _ => return self.verify_path(0, lower, upper),
+        };
+
+        let (l, r) = n.children();
+
+        // The annotations of the verified levels were checked against their
+        // children, so only the path to the key is fetched
+        let max_l = match verified {
+            0 => {
+                let (left, right) = (l.val()?, r.val()?);
+                Self::verify_bounded(l, &left, r, &right, lower, upper)?
+            }
+            _ => split_key(l).cloned(),
+        };
+
+        let verified = verified.saturating_sub(1);
+        if cmp_max_key(l, k).is_ge() {
+            l.val()?.verify_key(k, verified, lower, max_l.as_ref())
+        } else {
+            r.val()?.verify_key(k, verified, max_l.as_ref(), upper)
+        }
+    }
+
+    /// Verify the children of a node
+    fn verify_node(
+        l: &Annotated<Self, A>,
+        left: &Self,
+        r: &Annotated<Self, A>,
+        right: &Self,
+    ) -> Result<(), MapError> {
+        if left.is_empty() || right.is_empty() {
+            return Err(MapError::EmptyChild);
+        }
+
+        if !annotation_matches(l, left) || !annotation_matches(r, right) {
+            return Err(MapError::InvalidAnnotation);
+        }
+
+        Ok(())
+    }
+
+    /// Verify the paths to `samples` leaves picked at random with `rng`.
+    ///
+    /// The leaves are reached by descending the cached cardinalities, and
+    /// every node along their paths is checked as in
+    /// [`KelvinMap::validate`], along with the ordering of the leaf keys
+    /// against the bounds of the path. Only `samples` paths are fetched, so
+    /// the corruption of a fraction of the tree is detected with a
+    /// probability growing with the number of samples.
+    pub fn spot_check<R>(
+        &self,
+        rng: &mut R,
+        samples: usize,
+    ) -> Result<(), MapError>
+    where
+        R: RngCore,
+    {
+        let len = self.len() as u64;
+
+        for _ in 0..samples {
+            if len == 0 {
+                break;
+            }
+
+            self.verify_path(rng.next_u64() % len, None, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Verify the children of a node whose keys must be above `lower` and up
+    /// to `upper`, returning the max key of the left child
+    fn verify_bounded(
+        l: &Annotated<Self, A>,
+        left: &Self,
+        r: &Annotated<Self, A>,
+        right: &Self,
+        lower: Option<&K>,
+        upper: Option<&K>,
+    ) -> Result<Option<K>, MapError> {
+        let within = |key: Option<&K>, lower: Option<&K>| match key {
+            Some(key) => {
+                lower.map(|lower| key > lower).unwrap_or(true)
+                    && upper.map(|upper| key <= upper).unwrap_or(true)
+            }
+            None => false,
+        };
+
+        Self::verify_node(l, left, r, right)?;
+
+        // The max keys of both children are checked against the bounds, so a
+        // misplaced sub-tree is detected by every path crossing its parent
+        let max_l = left.max_key();
+        let max_r = right.max_key();
+        if !within(max_l.as_ref(), lower)
+            || !within(max_r.as_ref(), max_l.as_ref())
+        {
+            return Err(MapError::InvalidOrder);
+        }
+
+        Ok(max_l)
+    }
+
+    /// Verify the path to the `i`-th leaf, whose key must be above `lower`
+    /// and up to `upper`
+    fn verify_path(
+        &self,
+        i: u64,
+        lower: Option<&K>,
+        upper: Option<&K>,
+    ) -> Result<(), MapError> {
+        let within = |key: Option<&K>, lower: Option<&K>| match key {
+            Some(key) => {
+                lower.map(|lower| key > lower).unwrap_or(true)
+                    && upper.map(|upper| key <= upper).unwrap_or(true)
+            }
+            None => false,
+        };
+
+        match self {
+            KelvinMap::Empty => Ok(()),
+
+            KelvinMap::Leaf(l) if within(Some(l._key()), lower) => Ok(()),
+            KelvinMap::Leaf(_) => Err(MapError::InvalidOrder),
+
+            KelvinMap::Node(n) => {
+                let (l, r) = n.children();
+                let (left, right) = (l.val()?, r.val()?);
+                let max_l =
+                    Self::verify_bounded(l, &left, r, &right, lower, upper)?;
+
+                let c_l = cardinality(l);
+                if i < c_l {
+                    left.verify_path(i, lower, max_l.as_ref())
+                } else {
+                    right.verify_path(i - c_l, max_l.as_ref(), upper)
+                }
+            }
+        }
+    }
+}
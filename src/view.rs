@@ -0,0 +1,107 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::diff::{skip_left, skip_right, split_key};
+use crate::{KelvinMap, Leaf, MapAnnotation, MapError};
+
+use alloc::vec::Vec;
+use core::ops::{Deref, RangeBounds};
+
+use canonical::{Canon, CanonError, Id};
+
+#[derive(Debug, Clone)]
+/// Read-only view of a persisted map restricted to a key range.
+///
+/// Only the sub-trees intersecting the range are fetched from the store; the
+/// remaining ones are kept as unfetched ids.
+pub struct RangeView<K, V, A, R>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    map: KelvinMap<K, V, A>,
+    range: R,
+}
+
+impl<K, V, A, R> RangeView<K, V, A, R>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    R: RangeBounds<K>,
+{
+    /// Key range of the view
+    pub fn range(&self) -> &R {
+        &self.range
+    }
+
+    /// Returns a reference to the value corresponding to the key
+    ///
+    /// Will return [`MapError::OutOfRange`] if the key is not within the
+    /// range of the view.
+    pub fn get<'a>(
+        &'a self,
+        k: &K,
+    ) -> Result<Option<impl Deref<Target = V> + 'a>, MapError> {
+        if !self.range.contains(k) {
+            return Err(MapError::OutOfRange);
+        }
+
+        self.map.get(k).map_err(MapError::from)
+    }
+
+    /// Clones of the entries of the view, in key order
+    pub fn entries(&self) -> Result<Vec<Leaf<K, V>>, CanonError> {
+        let mut leaves = Vec::new();
+        self.map.collect_range(&self.range, &mut leaves)?;
+
+        Ok(leaves)
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Open the map persisted under `root`, fetching only the sub-trees that
+    /// intersect `range`.
+    pub fn open_range<R>(
+        root: &Id,
+        range: R,
+    ) -> Result<RangeView<K, V, A, R>, CanonError>
+    where
+        R: RangeBounds<K>,
+    {
+        let map: Self = root.reify()?;
+        map.fetch_range(&range)?;
+
+        Ok(RangeView { map, range })
+    }
+
+    /// Fetch the sub-trees intersecting `range`
+    fn fetch_range<R>(&self, range: &R) -> Result<(), CanonError>
+    where
+        R: RangeBounds<K>,
+    {
+        if let KelvinMap::Node(n) = self {
+            let (l, r) = n.children();
+            let split = split_key(l);
+
+            if !split.map(|s| skip_left(range, s)).unwrap_or(false) {
+                l.val()?.fetch_range(range)?;
+            }
+
+            if !split.map(|s| skip_right(range, s)).unwrap_or(false) {
+                r.val()?.fetch_range(range)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::diff::split_key;
+use crate::{KelvinMap, MapAnnotation};
+
+use alloc::vec::Vec;
+use core::cmp;
+use core::mem;
+use core::ops::Deref;
+use core::ptr;
+
+use canonical::{Canon, CanonError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Node of a map rewritten by a write operation.
+///
+/// The node is identified by its path from the root, and covers the keys in
+/// `(lower, upper]`, derived from the keys that split the tree along the
+/// path. Unbounded sides are represented by `None`.
+pub struct PathSegment<K> {
+    path: Vec<bool>,
+    lower: Option<K>,
+    upper: Option<K>,
+}
+
+impl<K> PathSegment<K> {
+    /// Directions from the root to the node, `true` standing for the right
+    /// child
+    pub fn path(&self) -> &[bool] {
+        &self.path
+    }
+
+    /// Exclusive lower bound of the keys covered by the node
+    pub fn lower(&self) -> Option<&K> {
+        self.lower.as_ref()
+    }
+
+    /// Inclusive upper bound of the keys covered by the node
+    pub fn upper(&self) -> Option<&K> {
+        self.upper.as_ref()
+    }
+}
+
+/// Check if `(a_lower, a_upper]` and `(b_lower, b_upper]` overlap, `None`
+/// standing for an unbounded side
+fn overlaps<K>(a: &PathSegment<K>, b: &PathSegment<K>) -> bool
+where
+    K: Ord,
+{
+    let below = |lower: &Option<K>, upper: &Option<K>| match (lower, upper) {
+        (Some(l), Some(u)) => l < u,
+        _ => true,
+    };
+
+    below(&a.lower, &b.upper) && below(&b.lower, &a.upper)
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Nodes rewritten by a batch of writes, sorted by path
+pub struct WriteSet<K> {
+    segments: Vec<PathSegment<K>>,
+}
+
+impl<K> WriteSet<K>
+where
+    K: Ord,
+{
+    /// Rewritten nodes, sorted by path
+    pub fn segments(&self) -> &[PathSegment<K>] {
+        &self.segments
+    }
+
+    /// Check if no node was rewritten
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Deepest rewritten nodes, that have no rewritten descendant
+    fn frontier(&self) -> impl Iterator<Item = &PathSegment<K>> {
+        // Sorted by path, the descendants of a node follow it
+        self.segments.iter().enumerate().filter_map(move |(i, s)| {
+            match self.segments.get(i + 1) {
+                Some(next) if next.path.starts_with(&s.path) => None,
+                _ => Some(s),
+            }
+        })
+    }
+
+    /// Check if the key ranges of the deepest rewritten nodes of the write
+    /// sets overlap.
+    ///
+    /// Write sets extracted from the same version of a map that don't
+    /// conflict touch disjoint key ranges, so the transactions that produced
+    /// them can be scheduled concurrently.
+    pub fn conflicts_with(&self, other: &Self) -> bool {
+        self.frontier()
+            .any(|a| other.frontier().any(|b| overlaps(a, b)))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`KelvinMap`] recording the nodes rewritten by its write operations.
+///
+/// Read access to the underlying map is provided via `Deref`.
+pub struct TrackedMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    map: KelvinMap<K, V, A>,
+    write_set: Vec<PathSegment<K>>,
+}
+
+impl<K, V, A> TrackedMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Track the writes to `map`
+    pub fn new(map: KelvinMap<K, V, A>) -> Self {
+        Self {
+            map,
+            write_set: Vec::new(),
+        }
+    }
+
+    /// Discard the write set and return the underlying map
+    pub fn into_inner(self) -> KelvinMap<K, V, A> {
+        self.map
+    }
+
+    /// Include a key -> value mapping to the map, recording the rewritten
+    /// nodes.
+    ///
+    /// Returns the previously mapped value, if any.
+    pub fn insert(&mut self, k: K, v: V) -> Result<Option<V>, CanonError> {
+        let old = self.map.clone();
+        let result = self.map.insert(k, v);
+        self.record(&old)?;
+
+        result
+    }
+
+    /// Remove a key -> value mapping from the map, recording the rewritten
+    /// nodes.
+    ///
+    /// Returns the previously mapped value, if any.
+    pub fn remove(&mut self, k: &K) -> Result<Option<V>, CanonError> {
+        let old = self.map.clone();
+        let result = self.map.remove(k);
+        self.record(&old)?;
+
+        result
+    }
+
+    /// Return the nodes rewritten since the last call, sorted by path,
+    /// and start a new write set
+    pub fn take_write_set(&mut self) -> WriteSet<K> {
+        let mut segments = mem::take(&mut self.write_set);
+
+        segments.sort_by(|a, b| a.path.cmp(&b.path));
+        segments.dedup_by(|a, b| a.path == b.path);
+
+        WriteSet { segments }
+    }
+
+    fn record(&mut self, old: &KelvinMap<K, V, A>) -> Result<(), CanonError> {
+        let mut segments = Vec::new();
+        record_rewritten(
+            old,
+            &self.map,
+            &mut Vec::new(),
+            None,
+            None,
+            &mut segments,
+        )?;
+
+        let root_rewritten = !segments.is_empty()
+            || !matches!(
+                (old, &self.map),
+                (KelvinMap::Node(..), KelvinMap::Node(..))
+                    | (KelvinMap::Empty, KelvinMap::Empty)
+            );
+
+        if root_rewritten {
+            self.write_set.push(PathSegment {
+                path: Vec::new(),
+                lower: None,
+                upper: None,
+            });
+        }
+
+        self.write_set.extend(segments);
+
+        Ok(())
+    }
+}
+
+/// Push a segment for every node of `new` under `path` that is not shared
+/// with `old`.
+///
+/// The writes copy the nodes they touch, so a shared annotation means the
+/// sub-tree was left untouched.
+fn record_rewritten<K, V, A>(
+    old: &KelvinMap<K, V, A>,
+    new: &KelvinMap<K, V, A>,
+    path: &mut Vec<bool>,
+    lower: Option<&K>,
+    upper: Option<&K>,
+    segments: &mut Vec<PathSegment<K>>,
+) -> Result<(), CanonError>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    let (l, r) = match new {
+        KelvinMap::Node(n) => n.children(),
+        _ => return Ok(()),
+    };
+
+    // The split of the old tree is also considered, so the slots cover the
+    // removed keys as well
+    let split = split_key(l);
+    let old_split = match old {
+        KelvinMap::Node(o) => split_key(o.left()),
+        _ => None,
+    };
+
+    let (split_upper, split_lower) = match (split, old_split) {
+        (Some(s), Some(o)) => (Some(cmp::max(s, o)), Some(cmp::min(s, o))),
+        (s, o) => (s.or(o), s.or(o)),
+    };
+
+    let children = [
+        (false, l, lower, split_upper),
+        (true, r, split_lower, upper),
+    ];
+
+    for (right, child, lower, upper) in children.iter() {
+        let old_child = match old {
+            KelvinMap::Node(o) if *right => Some(o.right()),
+            KelvinMap::Node(o) => Some(o.left()),
+            _ => None,
+        };
+
+        let shared = old_child
+            .map(|o| {
+                ptr::eq(o, *child)
+                    || ptr::eq(o.annotation(), child.annotation())
+            })
+            .unwrap_or(false);
+
+        if shared {
+            continue;
+        }
+
+        path.push(*right);
+        segments.push(PathSegment {
+            path: path.clone(),
+            lower: lower.cloned(),
+            upper: upper.cloned(),
+        });
+
+        // The nodes are cloned out of the store references, since the same
+        // node can be reachable from both trees at different paths
+        let new_child = child.val()?.clone();
+        let old_child = match old_child {
+            Some(o) => o.val()?.clone(),
+            None => KelvinMap::Empty,
+        };
+
+        record_rewritten(
+            &old_child, &new_child, path, *lower, *upper, segments,
+        )?;
+
+        path.pop();
+    }
+
+    Ok(())
+}
+
+impl<K, V, A> Deref for TrackedMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    type Target = KelvinMap<K, V, A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
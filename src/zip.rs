@@ -0,0 +1,323 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::{KelvinMap, Leaf, MapAnnotation};
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use canonical::{Canon, CanonError};
+
+/// In-order traversal of a map, holding the pending sub-trees in a stack.
+///
+/// Sub-trees are cheap to clone, since only the references to their children
+/// are copied.
+pub(crate) struct Cursor<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    stack: Vec<KelvinMap<K, V, A>>,
+    /// Stack height under which a run of leaves known to precede the other
+    /// map is pending
+    run: Option<usize>,
+}
+
+impl<K, V, A> Cursor<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    pub(crate) fn new(map: &KelvinMap<K, V, A>) -> Self {
+        Self {
+            stack: vec![map.clone()],
+            run: None,
+        }
+    }
+
+    /// Next pending sub-tree
+    pub(crate) fn top(&mut self) -> Option<&KelvinMap<K, V, A>> {
+        while let Some(KelvinMap::Empty) = self.stack.last() {
+            self.stack.pop();
+        }
+
+        self.stack.last()
+    }
+
+    /// Replace the next pending node with its children
+    pub(crate) fn descend(&mut self) -> Result<(), CanonError> {
+        if let Some(KelvinMap::Node(..)) = self.top() {
+            if let Some(KelvinMap::Node(n)) = self.stack.pop() {
+                let l = n.left().val()?.clone();
+                let r = n.right().val()?.clone();
+
+                self.stack.push(r);
+                self.stack.push(l);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drop the next pending sub-tree
+    pub(crate) fn pop(&mut self) -> Option<KelvinMap<K, V, A>> {
+        self.top()?;
+        self.stack.pop()
+    }
+
+    /// Flag the next pending sub-tree as a run preceding the other map
+    fn start_run(&mut self) {
+        self.run = Some(self.stack.len() - 1);
+    }
+
+    /// Check if the next leaf belongs to a pending run
+    fn in_run(&mut self) -> bool {
+        match self.run {
+            Some(height) if self.stack.len() > height => true,
+            _ => {
+                self.run = None;
+                false
+            }
+        }
+    }
+
+    /// Skip the leaves with keys lower than `key`, skipping whole sub-trees
+    /// when possible, and return the next leaf without popping it
+    pub(crate) fn seek(
+        &mut self,
+        key: &K,
+    ) -> Result<Option<&Leaf<K, V>>, CanonError> {
+        loop {
+            let skip = match self.top() {
+                None => return Ok(None),
+                Some(KelvinMap::Leaf(l)) if l._key() >= key => break,
+                Some(KelvinMap::Leaf(_)) => true,
+                Some(node) => {
+                    node.max_key().map(|max| &max < key).unwrap_or(true)
+                }
+            };
+
+            if skip {
+                self.stack.pop();
+            } else {
+                self.descend()?;
+            }
+        }
+
+        match self.stack.last() {
+            Some(KelvinMap::Leaf(l)) => Ok(Some(l)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Pop the next leaf in key order
+    pub(crate) fn next_leaf(
+        &mut self,
+    ) -> Result<Option<Leaf<K, V>>, CanonError> {
+        loop {
+            match self.top() {
+                None => return Ok(None),
+                Some(KelvinMap::Node(..)) => self.descend()?,
+                Some(_) => match self.stack.pop() {
+                    Some(KelvinMap::Leaf(l)) => return Ok(Some(l)),
+                    _ => unreachable!(),
+                },
+            }
+        }
+    }
+}
+
+/// Key with its values in both maps, if present
+type ZipEntry<K, V, V2> = (K, Option<V>, Option<V2>);
+
+/// Next step of the co-iteration, decided out of the pending sub-trees
+enum Next {
+    Left,
+    Right,
+    Both,
+    RunLeft,
+    RunRight,
+    DescendLeft,
+    DescendRight,
+    DescendBoth,
+}
+
+/// Iterator over the union of the keys of two maps, in key order, created by
+/// [`KelvinMap::zip_iter`]
+pub struct ZipIter<K, V, A, V2, A2>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    V2: Canon,
+    A2: MapAnnotation<K, V2>,
+{
+    left: Cursor<K, V, A>,
+    right: Cursor<K, V2, A2>,
+}
+
+impl<K, V, A, V2, A2> ZipIter<K, V, A, V2, A2>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    V2: Canon,
+    A2: MapAnnotation<K, V2>,
+{
+    fn left_only(&mut self) -> Result<Option<ZipEntry<K, V, V2>>, CanonError> {
+        Ok(self.left.next_leaf()?.map(|l| {
+            let (k, v) = l.into_parts();
+            (k, Some(v), None)
+        }))
+    }
+
+    fn right_only(&mut self) -> Result<Option<ZipEntry<K, V, V2>>, CanonError> {
+        Ok(self.right.next_leaf()?.map(|r| {
+            let (k, v) = r.into_parts();
+            (k, None, Some(v))
+        }))
+    }
+
+    fn next_entry(&mut self) -> Result<Option<ZipEntry<K, V, V2>>, CanonError> {
+        if self.left.in_run() {
+            return self.left_only();
+        }
+
+        if self.right.in_run() {
+            return self.right_only();
+        }
+
+        loop {
+            let next = match (self.left.top(), self.right.top()) {
+                (None, None) => return Ok(None),
+                (Some(_), None) => Next::Left,
+                (None, Some(_)) => Next::Right,
+
+                (Some(KelvinMap::Leaf(l)), Some(KelvinMap::Leaf(r))) => {
+                    match l._key().cmp(r._key()) {
+                        Ordering::Less => Next::Left,
+                        Ordering::Greater => Next::Right,
+                        Ordering::Equal => Next::Both,
+                    }
+                }
+
+                // A whole sub-tree below the next key of the other map is
+                // yielded without further comparisons
+                (Some(l), Some(KelvinMap::Leaf(r))) => match l.max_key() {
+                    Some(max) if &max < r._key() => Next::RunLeft,
+                    _ => Next::DescendLeft,
+                },
+                (Some(KelvinMap::Leaf(l)), Some(r)) => match r.max_key() {
+                    Some(max) if &max < l._key() => Next::RunRight,
+                    _ => Next::DescendRight,
+                },
+
+                (Some(_), Some(_)) => Next::DescendBoth,
+            };
+
+            match next {
+                Next::Left => return self.left_only(),
+                Next::Right => return self.right_only(),
+                Next::Both => {
+                    let l = self.left.next_leaf()?;
+                    let r = self.right.next_leaf()?;
+
+                    return Ok(l.zip(r).map(|(l, r)| {
+                        let (k, l) = l.into_parts();
+                        let (_, r) = r.into_parts();
+                        (k, Some(l), Some(r))
+                    }));
+                }
+                Next::RunLeft => {
+                    self.left.start_run();
+                    return self.left_only();
+                }
+                Next::RunRight => {
+                    self.right.start_run();
+                    return self.right_only();
+                }
+                Next::DescendLeft => self.left.descend()?,
+                Next::DescendRight => self.right.descend()?,
+                Next::DescendBoth => {
+                    self.left.descend()?;
+                    self.right.descend()?;
+                }
+            }
+        }
+    }
+}
+
+impl<K, V, A, V2, A2> Iterator for ZipIter<K, V, A, V2, A2>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+    V2: Canon,
+    A2: MapAnnotation<K, V2>,
+{
+    type Item = Result<ZipEntry<K, V, V2>, CanonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().transpose()
+    }
+}
+
+impl<K, V, A> KelvinMap<K, V, A>
+where
+    K: Canon + Ord,
+    V: Canon,
+    A: MapAnnotation<K, V>,
+{
+    /// Co-iterate `self` and `other` in key order, yielding every key of
+    /// either map with its value in `self` and in `other` respectively.
+    ///
+    /// The values are cloned out of the tree as the iteration goes. Sub-trees
+    /// entirely below the next key of the other map are yielded without
+    /// comparing their keys against it.
+    pub fn zip_iter<V2, A2>(
+        &self,
+        other: &KelvinMap<K, V2, A2>,
+    ) -> ZipIter<K, V, A, V2, A2>
+    where
+        V2: Canon,
+        A2: MapAnnotation<K, V2>,
+    {
+        ZipIter {
+            left: Cursor::new(self),
+            right: Cursor::new(other),
+        }
+    }
+
+    /// Create a map applying `f` to the values of every key of either `self`
+    /// or `other`, dropping the keys for which `f` returns `None`.
+    ///
+    /// The inputs are co-iterated with [`KelvinMap::zip_iter`], and the
+    /// output is built balanced in bulk rather than by repeated inserts.
+    pub fn combine_with<F>(
+        &self,
+        other: &Self,
+        f: F,
+    ) -> Result<Self, CanonError>
+    where
+        F: Fn(Option<&V>, Option<&V>) -> Option<V>,
+    {
+        let mut leaves = Vec::new();
+
+        for entry in self.zip_iter(other) {
+            let (k, l, r) = entry?;
+
+            if let Some(v) = f(l.as_ref(), r.as_ref()) {
+                leaves.push(Leaf::new(k, v));
+            }
+        }
+
+        let len = leaves.len();
+        Ok(Self::from_sorted_leaves(&mut leaves.into_iter(), len))
+    }
+}
@@ -0,0 +1,372 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use canonical::{Canon, EncodeToVec, Source, Store};
+use dusk_kelvin_map::{
+    domain_hash, node_hash, schema_hash, HashedMap, KelvinMap,
+    MapAnnotationHashed, MapError, MapFingerprint, MapHash, Skeleton,
+    SkeletonNode, SparseMap, SparseProof, StoreHasher, LEAF_DOMAIN,
+    SCHEMA_DOMAIN,
+};
+
+use core::borrow::Borrow;
+
+fn hashed_map(n: u64) -> HashedMap<u64, u64> {
+    let mut map = HashedMap::default();
+
+    for i in 0..n {
+        map.insert(i, i * 2).expect("Failed to insert a KV");
+    }
+
+    map
+}
+
+#[test]
+fn leaf_hash() {
+    let map = hashed_map(1);
+
+    let leaf = match &map {
+        KelvinMap::Leaf(l) => l,
+        _ => panic!("A single entry map is expected to be a leaf"),
+    };
+
+    let mut preimage = vec![LEAF_DOMAIN];
+    preimage.extend(0u64.encode_to_vec());
+    preimage.extend(0u64.encode_to_vec());
+    let expected = Store::hash(&preimage);
+
+    assert_eq!(expected, leaf.hash::<StoreHasher>());
+    assert_eq!(
+        node_hash::<StoreHasher, _>(1, &[expected]),
+        map.root_hash::<StoreHasher>()
+    );
+}
+
+#[test]
+fn root_hash() {
+    let mut map = hashed_map(64);
+    let root = map.root_hash::<StoreHasher>();
+
+    assert_eq!(root, hashed_map(64).root_hash::<StoreHasher>());
+    assert_ne!(root, hashed_map(63).root_hash::<StoreHasher>());
+
+    *map.get_mut(&17)
+        .expect("Failed to fetch a KV")
+        .expect("The KV was not found") += 1;
+    assert_ne!(root, map.root_hash::<StoreHasher>());
+
+    *map.get_mut(&17)
+        .expect("Failed to fetch a KV")
+        .expect("The KV was not found") -= 1;
+    assert_eq!(root, map.root_hash::<StoreHasher>());
+}
+
+#[test]
+fn prove_nth() {
+    let map = hashed_map(37);
+    let root = map.root_hash::<StoreHasher>();
+
+    for i in 0..37 {
+        let proof = map
+            .prove_nth::<StoreHasher>(i)
+            .expect("Failed to generate the proof")
+            .expect("The index is in bounds");
+
+        assert_eq!(&(i * 2), proof.leaf().value());
+        assert!(proof.verify::<StoreHasher>(&root, i));
+        assert!(!proof.verify::<StoreHasher>(&root, i + 1));
+    }
+
+    assert!(map
+        .prove_nth::<StoreHasher>(37)
+        .expect("Failed to generate the proof")
+        .is_none());
+}
+
+#[test]
+fn prove_range() {
+    let map = hashed_map(37);
+    let root = map.root_hash::<StoreHasher>();
+
+    let proof = map
+        .prove_range::<StoreHasher, _>(10..20)
+        .expect("Failed to generate the proof");
+    let entries = proof
+        .verify::<StoreHasher, _>(&root, &(10..20))
+        .expect("The proof is expected to be valid");
+    let values: Vec<u64> = entries.iter().map(|l| *l.value()).collect();
+    assert_eq!((10..20).map(|i| i * 2).collect::<Vec<_>>(), values);
+
+    // The proof doesn't cover a wider range
+    assert!(proof.verify::<StoreHasher, _>(&root, &(10..21)).is_none());
+    assert!(proof.verify::<StoreHasher, _>(&root, &(9..20)).is_none());
+
+    let proof = map
+        .prove_range::<StoreHasher, _>(..)
+        .expect("Failed to generate the proof");
+    let entries = proof
+        .verify::<StoreHasher, _>(&root, &(..))
+        .expect("The proof is expected to be valid");
+    assert_eq!(37, entries.len());
+
+    let proof = map
+        .prove_range::<StoreHasher, _>(100..)
+        .expect("Failed to generate the proof");
+    let entries = proof
+        .verify::<StoreHasher, _>(&root, &(100..))
+        .expect("The proof is expected to be valid");
+    assert!(entries.is_empty());
+
+    let empty = hashed_map(0);
+    let proof = empty
+        .prove_range::<StoreHasher, _>(..)
+        .expect("Failed to generate the proof");
+    assert!(proof
+        .verify::<StoreHasher, _>(&empty.root_hash::<StoreHasher>(), &(..))
+        .expect("The proof is expected to be valid")
+        .is_empty());
+}
+
+#[test]
+fn root_hash_with_domain() {
+    let map = hashed_map(16);
+    let root = map.root_hash::<StoreHasher>();
+
+    let epoch_1 = map.root_hash_with_domain::<StoreHasher, _>(&1u64);
+    let epoch_2 = map.root_hash_with_domain::<StoreHasher, _>(&2u64);
+
+    assert_ne!(root, epoch_1);
+    assert_ne!(epoch_1, epoch_2);
+    assert_eq!(epoch_1, domain_hash::<StoreHasher, _>(&root, &1u64));
+}
+
+#[test]
+fn fingerprint() {
+    let schema = (1u32, 1u32);
+
+    let mut map = hashed_map(16);
+    let fingerprint = map.fingerprint::<StoreHasher, _>(&schema);
+
+    assert_eq!(16, fingerprint.len());
+    assert_eq!(&map.root_hash::<StoreHasher>(), fingerprint.root());
+
+    let mut bytes = vec![SCHEMA_DOMAIN];
+    bytes.extend(schema.encode_to_vec());
+    assert_eq!(&Store::hash(&bytes), fingerprint.schema());
+    assert_eq!(
+        &schema_hash::<StoreHasher, _>(&schema),
+        fingerprint.schema()
+    );
+
+    assert_eq!(
+        fingerprint,
+        hashed_map(16).fingerprint::<StoreHasher, _>(&schema)
+    );
+    assert_ne!(
+        fingerprint,
+        map.fingerprint::<StoreHasher, _>(&(1u32, 2u32))
+    );
+
+    map.insert(3, 0).expect("Failed to insert value");
+    let updated = map.fingerprint::<StoreHasher, _>(&schema);
+    assert_eq!(fingerprint.len(), updated.len());
+    assert_ne!(fingerprint, updated);
+
+    let decoded =
+        MapFingerprint::decode(&mut Source::new(&updated.encode_to_vec()))
+            .expect("Failed to decode the fingerprint");
+    assert_eq!(updated, decoded);
+}
+
+#[test]
+fn sparse_map() {
+    let mut map: SparseMap<u64, StoreHasher> =
+        SparseMap::new(32).expect("Failed to create the map");
+    let empty = map.root_hash();
+
+    assert!(map.insert(1 << 32, 0).is_err());
+
+    for k in [7u64, 1 << 20, u32::MAX as u64].iter() {
+        assert!(map.insert(*k, *k * 2).expect("Failed to insert").is_none());
+    }
+
+    let root = map.root_hash();
+    assert_ne!(empty, root);
+    assert_eq!(Some(14), map.get(7).expect("Failed to get"));
+    assert_eq!(None, map.get(8).expect("Failed to get"));
+
+    let proof = map.prove(7).expect("Failed to generate the proof");
+    assert_eq!(Some(&14), proof.value());
+    assert!(proof.verify::<StoreHasher>(&root, 7, 32));
+    assert!(!proof.verify::<StoreHasher>(&root, 6, 32));
+    assert!(!proof.verify::<StoreHasher>(&root, 7, 31));
+    assert!(!proof.verify::<StoreHasher>(&root, 7 | 1 << 40, 32));
+
+    let proof = map.prove(8).expect("Failed to generate the proof");
+    assert_eq!(None, proof.value());
+    assert!(proof.verify::<StoreHasher>(&root, 8, 32));
+
+    // Forged proofs deeper than the key space are rejected
+    let mut deep = proof.siblings().to_vec();
+    deep.resize(70, [0u8; 32]);
+    let mut forged = None::<u64>.encode_to_vec();
+    forged.extend(deep.encode_to_vec());
+    let forged: SparseProof<u64> = Canon::decode(&mut Source::new(&forged))
+        .expect("Failed to decode the forged proof");
+    assert_eq!(70, forged.siblings().len());
+    assert!(!forged.verify::<StoreHasher>(&root, 8, 32));
+    assert!(!forged.verify::<StoreHasher>(&root, 8, 70));
+
+    // Maps decoded with an invalid depth are rejected
+    let mut bytes = map.encode_to_vec();
+    bytes[0] = 65;
+    assert!(
+        SparseMap::<u64, StoreHasher>::decode(&mut Source::new(&bytes))
+            .is_err()
+    );
+
+    for k in [7u64, 1 << 20, u32::MAX as u64].iter() {
+        assert!(map.remove(*k).expect("Failed to remove").is_some());
+    }
+
+    assert!(map.is_empty());
+    assert_eq!(empty, map.root_hash());
+}
+
+#[test]
+fn export_skeleton() {
+    let map = hashed_map(37);
+    let skeleton = map.export_skeleton().expect("Failed to export skeleton");
+
+    assert_eq!(37, skeleton.leaves());
+    assert_eq!(2 * 37 - 1, skeleton.nodes().len());
+    let root: &MapHash<StoreHasher> =
+        skeleton.root().expect("Failed to get the root").borrow();
+    assert_eq!(&map.root_hash::<StoreHasher>(), root.digest());
+    assert!(skeleton.depth() > 6);
+
+    // Every node carries the commitment of its sub-tree
+    let mut nodes = skeleton.nodes().iter();
+    match (nodes.next(), nodes.next()) {
+        (Some(SkeletonNode::Node(root)), Some(SkeletonNode::Node(l))) => {
+            let root: &MapHash<StoreHasher> = root.borrow();
+            let l: &MapHash<StoreHasher> = l.borrow();
+            assert!(l.cardinality() < root.cardinality());
+        }
+        _ => panic!("Failed to find the root children"),
+    }
+
+    // The skeleton decodes back
+    let bytes = skeleton.encode_to_vec();
+    let decoded: Skeleton<MapAnnotationHashed<u64, StoreHasher>> =
+        Canon::decode(&mut Source::new(&bytes))
+            .expect("Failed to decode the skeleton");
+    assert_eq!(bytes, decoded.encode_to_vec());
+
+    let empty = HashedMap::<u64, u64>::default()
+        .export_skeleton()
+        .expect("Failed to export skeleton");
+    assert!(empty.root().is_none());
+    assert_eq!(0, empty.depth());
+}
+
+/// Encoding of `map` with a byte of the digest cached for the left child of
+/// the root flipped
+fn forge_left_digest(map: &HashedMap<u64, u64>) -> Vec<u8> {
+    let l = match map {
+        KelvinMap::Node(n) => n.left(),
+        _ => panic!("The map is expected to be a node"),
+    };
+    let hash: &MapHash<StoreHasher> = (*l.annotation()).borrow();
+    let digest = *hash.digest();
+
+    let mut bytes = map.encode_to_vec();
+    let at = bytes
+        .windows(digest.len())
+        .position(|w| w == digest)
+        .expect("The digest is expected to be encoded");
+    bytes[at] ^= 1;
+
+    bytes
+}
+
+#[test]
+fn forged_hash_annotation() {
+    let map = hashed_map(16);
+    let bytes = forge_left_digest(&map);
+
+    // The cardinality and max key of the forged annotation are untouched
+    let mut forged = HashedMap::<u64, u64>::decode(&mut Source::new(&bytes))
+        .expect("Failed to decode the map");
+    assert_eq!(16, forged.len());
+    assert!(matches!(
+        forged.validate(),
+        Err(MapError::InvalidAnnotation)
+    ));
+    assert!(HashedMap::<u64, u64>::decode_verified(&bytes, 1).is_err());
+
+    let report = forged.repair().expect("Failed to repair the map");
+    assert_eq!(1, report.annotations);
+    forged
+        .validate()
+        .expect("Failed to validate the repaired map");
+    assert_eq!(
+        map.root_hash::<StoreHasher>(),
+        forged.root_hash::<StoreHasher>()
+    );
+}
+
+#[cfg(feature = "poseidon")]
+#[test]
+fn poseidon_hasher() {
+    use dusk_kelvin_map::{to_scalars, MapHasher, PoseidonHasher};
+
+    type PoseidonMap =
+        KelvinMap<u64, u64, MapAnnotationHashed<u64, PoseidonHasher>>;
+
+    // The bytes are packed with their length, in chunks of 31 bytes
+    assert_eq!(1, to_scalars(&[]).len());
+    assert_eq!(2, to_scalars(&[1; 31]).len());
+    assert_eq!(3, to_scalars(&[1; 32]).len());
+    assert_ne!(PoseidonHasher::hash(&[1]), PoseidonHasher::hash(&[1, 0]));
+
+    let mut single = PoseidonMap::default();
+    single.insert(0, 0).expect("Failed to insert a KV");
+
+    let mut preimage = vec![LEAF_DOMAIN];
+    preimage.extend(0u64.encode_to_vec());
+    preimage.extend(0u64.encode_to_vec());
+
+    match &single {
+        KelvinMap::Leaf(l) => assert_eq!(
+            PoseidonHasher::hash(&preimage),
+            l.hash::<PoseidonHasher>()
+        ),
+        _ => panic!("A single entry map is expected to be a leaf"),
+    }
+
+    let poseidon_map = |n: u64| {
+        let mut map = PoseidonMap::default();
+        for i in 0..n {
+            map.insert(i, i * 2).expect("Failed to insert a KV");
+        }
+        map
+    };
+
+    let mut map = poseidon_map(16);
+    map.validate().expect("Failed to validate the map");
+
+    // The commitment is deterministic, and depends on the hasher
+    let root = map.root_hash::<PoseidonHasher>();
+    assert_eq!(root, poseidon_map(16).root_hash::<PoseidonHasher>());
+    assert_ne!(root, poseidon_map(15).root_hash::<PoseidonHasher>());
+    assert_ne!(root, hashed_map(16).root_hash::<StoreHasher>());
+
+    *map.get_mut(&7)
+        .expect("Failed to fetch a KV")
+        .expect("The KV was not found") += 1;
+    assert_ne!(root, map.root_hash::<PoseidonHasher>());
+}
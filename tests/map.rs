@@ -4,10 +4,17 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use canonical::Canon;
+use canonical::{Canon, EncodeToVec, Id, Source};
 use canonical_derive::Canon;
-use dusk_kelvin_map::{KelvinMap, Map};
-use microkelvin::Cardinality;
+use dusk_kelvin_map::{
+    commit_all, Change, Checked, ChunkedValue, ConfiguredMap, DuplicatePolicy,
+    Entry, Fueled, HashedMap, KelvinCollection, KelvinLinkedMap, KelvinMap,
+    KelvinMultiSet, KeyAllocation, KeyDigest, KeyRange, LwwMap, Map,
+    MapAnnotationDefault, MapConfig, MapError, PathSegment, RangeRequest,
+    Saturating, Set, SimulationReport, Slot, Stamped, StoreHasher, SumMap,
+    TombstoneMap, TrackedMap, U64Map, WriteSet,
+};
+use microkelvin::{Annotated, Cardinality, MaxKey};
 use rand::rngs::StdRng;
 use rand::{RngCore, SeedableRng};
 
@@ -63,7 +70,7 @@ where
     V: Canon,
 {
     let (l, r) = match map {
-        KelvinMap::Node(l, r) => (l, r),
+        KelvinMap::Node(n) => n.children(),
         _ => panic!("Not possible to assert balance for a leaf or empty tree"),
     };
 
@@ -184,3 +191,3220 @@ fn balance_rev() {
 
     assert_balanced(&map);
 }
+
+#[cfg(feature = "testing")]
+#[test]
+fn map_macro() {
+    let empty: Map<u64, u32> = dusk_kelvin_map::map! {};
+    assert!(empty.is_empty());
+
+    let map: Map<u8, u8> = dusk_kelvin_map::map! {
+        1 => 2,
+        3 => 4,
+        5 => 6,
+        7 => 8,
+    };
+
+    assert_eq!(4, map.len());
+    assert_balanced(&map);
+
+    for k in (1..8).step_by(2) {
+        assert_eq!(
+            k + 1,
+            *map.get(&k)
+                .expect("Failed to fetch a literal KV")
+                .expect("The literal KV was not found")
+        );
+    }
+}
+
+#[test]
+fn get_if() {
+    let mut map: Map<u64, u64> = Map::default();
+
+    for i in 0..32 {
+        map.insert(i, i * 3).expect("Failed to insert a KV");
+    }
+
+    for i in 0..32 {
+        let value = map
+            .get_if(&i, |v| v % 2 == 0)
+            .expect("Failed to fetch previously inserted KV");
+
+        match value {
+            Some(v) => assert_eq!(i * 3, *v),
+            None => assert_eq!(1, (i * 3) % 2),
+        }
+    }
+
+    assert!(map
+        .get_if(&64, |_| true)
+        .expect("Failed to fetch an unexisting KV")
+        .is_none());
+}
+
+#[test]
+fn key_digest() {
+    let (data, map) = KeyValue::generate_map::<64>();
+    let (_, mut other) = KeyValue::generate_map::<64>();
+
+    let digest = map.key_digest(4).expect("Failed to compute the digest");
+    assert_eq!(16, digest.buckets().len());
+    assert_eq!(64, digest.buckets().iter().map(|b| b.count()).sum::<u64>());
+    assert_eq!(
+        digest,
+        other.key_digest(4).expect("Failed to compute the digest")
+    );
+
+    other
+        .insert(data[7].key, data[7].value.wrapping_add(1))
+        .expect("Failed to replace a KV");
+    let other_digest =
+        other.key_digest(4).expect("Failed to compute the digest");
+
+    let changed: Vec<usize> = digest
+        .buckets()
+        .iter()
+        .zip(other_digest.buckets().iter())
+        .enumerate()
+        .filter_map(|(i, (a, b))| if a != b { Some(i) } else { None })
+        .collect();
+    assert_eq!(vec![digest.bucket_of(&data[7].key)], changed);
+}
+
+#[test]
+fn reconcile() {
+    let (data, map) = KeyValue::generate_map::<64>();
+    let mut remote: Map<u64, u32> = Map::default();
+
+    for d in data.iter().skip(3) {
+        remote
+            .insert(d.key, d.value)
+            .expect("Failed to insert a KV");
+    }
+
+    let digest = remote.key_digest(6).expect("Failed to compute the digest");
+    let requests = map.reconcile(&digest).expect("Failed to reconcile");
+    assert!(!requests.is_empty() && requests.len() <= 3);
+
+    let served = map.serve_ranges(&requests).expect("Failed to serve");
+    assert!(served.len() < data.len());
+
+    for (k, v) in served {
+        remote.insert(k, v).expect("Failed to insert a served KV");
+    }
+
+    assert!(map
+        .reconcile(&remote.key_digest(6).expect("Failed to compute digest"))
+        .expect("Failed to reconcile")
+        .is_empty());
+
+    // Requests and digests of peers are checked for out-of-range buckets
+    let request = requests[0].encode_to_vec();
+    let decoded = RangeRequest::decode(&mut Source::new(&request))
+        .expect("Failed to decode a valid request");
+    assert_eq!(requests[0], decoded);
+
+    let decode_request = |bits: u8, bucket: u32| {
+        let mut bytes = bits.encode_to_vec();
+        bytes.extend(bucket.encode_to_vec());
+        RangeRequest::decode(&mut Source::new(&bytes))
+    };
+    assert!(decode_request(40, 0).is_err());
+    assert!(decode_request(4, 16).is_err());
+    assert!(decode_request(4, 15).is_ok());
+
+    let mut bytes = digest.encode_to_vec();
+    bytes[0] = 40;
+    assert!(KeyDigest::decode(&mut Source::new(&bytes)).is_err());
+    bytes[0] = 5;
+    assert!(KeyDigest::decode(&mut Source::new(&bytes)).is_err());
+}
+
+#[test]
+fn chunked_value() {
+    let bytes: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+    let mut map: Map<u64, ChunkedValue> = Map::default();
+
+    map.insert(1, ChunkedValue::new(&bytes, 128))
+        .expect("Failed to insert a chunked value");
+
+    let value = map
+        .get(&1)
+        .expect("Failed to fetch the chunked value")
+        .expect("The chunked value was not found");
+    assert_eq!(1000, value.len());
+    assert_eq!(8, value.chunks());
+    assert_eq!(bytes, value.to_bytes().expect("Failed to fetch the chunks"));
+    drop(value);
+
+    let chunk = map
+        .get_chunk(&1, 7)
+        .expect("Failed to fetch a chunk")
+        .expect("The chunk was not found");
+    assert_eq!(&bytes[896..], &chunk[..]);
+
+    assert!(map.get_chunk(&1, 8).expect("Failed to fetch").is_none());
+    assert!(map.get_chunk(&2, 0).expect("Failed to fetch").is_none());
+
+    // A length not matching the chunks is rejected without allocating it
+    let value = ChunkedValue::new(&bytes, 128).encode_to_vec();
+    let mut encoded = u64::MAX.encode_to_vec();
+    encoded.extend(&value[1000u64.encode_to_vec().len()..]);
+    let forged = ChunkedValue::decode(&mut Source::new(&encoded))
+        .expect("Failed to decode the chunked value");
+    assert_eq!(u64::MAX, forged.len());
+    assert!(forged.to_bytes().is_err());
+}
+
+#[test]
+fn configured_map() {
+    let invalid = MapConfig {
+        balance_tolerance: 0,
+        ..MapConfig::default()
+    };
+    assert!(
+        ConfiguredMap::<u8, u8, MapAnnotationDefault<u8>>::new(invalid)
+            .is_err()
+    );
+
+    let config = MapConfig {
+        balance_tolerance: 4,
+        ..MapConfig::default()
+    };
+    let mut map: ConfiguredMap<u8, u8, MapAnnotationDefault<u8>> =
+        ConfiguredMap::new(config).expect("Failed to create the map");
+
+    for v in 0..64 {
+        map.insert(v, v).expect("Failed to insert a KV");
+    }
+    assert_eq!(64, map.len());
+
+    let bytes = map.encode_to_vec();
+    let restored = ConfiguredMap::<u8, u8, MapAnnotationDefault<u8>>::decode(
+        &mut Source::new(&bytes),
+    )
+    .expect("Failed to decode the map");
+    assert_eq!(&config, restored.config());
+    assert_eq!(
+        13,
+        *restored
+            .get(&13)
+            .expect("Failed to fetch a KV")
+            .expect("The KV was not found")
+    );
+
+    let limited = MapConfig {
+        max_value_size: Some(16),
+        ..MapConfig::default()
+    };
+    let mut limited: ConfiguredMap<u8, Vec<u8>, MapAnnotationDefault<u8>> =
+        ConfiguredMap::new(limited).expect("Failed to create the map");
+
+    // The length prefix of the vector is part of its encoding
+    limited
+        .insert(1, vec![0; 8])
+        .expect("Failed to insert a KV");
+    assert!(matches!(
+        limited.insert(2, vec![0; 64]),
+        Err(MapError::ValueTooLarge)
+    ));
+    assert!(matches!(
+        limited.insert(1, vec![0; 64]),
+        Err(MapError::ValueTooLarge)
+    ));
+    assert_eq!(1, limited.len());
+    assert_eq!(
+        8,
+        limited
+            .get(&1)
+            .expect("Failed to fetch a KV")
+            .expect("The KV was not found")
+            .len()
+    );
+
+    let strict = MapConfig {
+        strict: true,
+        ..MapConfig::default()
+    };
+    let mut strict: ConfiguredMap<u8, u8, MapAnnotationDefault<u8>> =
+        ConfiguredMap::new(strict).expect("Failed to create the map");
+
+    strict.insert(1, 10).expect("Failed to insert a KV");
+    assert!(matches!(strict.insert(1, 11), Err(MapError::KeyExists)));
+    assert_eq!(
+        Some(10),
+        strict.overwrite(1, 12).expect("Failed to overwrite a KV")
+    );
+    assert_eq!(Some(12), strict.remove(&1).expect("Failed to remove a KV"));
+    strict.insert(1, 13).expect("Failed to insert a KV");
+
+    for invalid in [
+        MapConfig {
+            max_depth: 0,
+            ..MapConfig::default()
+        },
+        MapConfig {
+            inline_threshold: MapConfig::MAX_INLINE_THRESHOLD + 1,
+            ..MapConfig::default()
+        },
+    ] {
+        assert!(matches!(
+            ConfiguredMap::<u8, u8, MapAnnotationDefault<u8>>::new(invalid),
+            Err(MapError::InvalidConfig)
+        ));
+    }
+
+    let shallow = MapConfig {
+        balance_tolerance: 64,
+        max_depth: 7,
+        ..MapConfig::default()
+    };
+    let mut shallow: ConfiguredMap<u8, u8, MapAnnotationDefault<u8>> =
+        ConfiguredMap::new(shallow).expect("Failed to create the map");
+
+    for v in 0..64 {
+        shallow.insert(v, v).expect("Failed to insert a KV");
+    }
+    assert!(matches!(shallow.validate(), Err(MapError::TooDeep)));
+
+    let report = shallow.shrink().expect("Failed to shrink the map");
+    assert!(report.depth_after <= 7);
+    shallow.validate().expect("Failed to validate the map");
+    assert_eq!(64, shallow.len());
+
+    let mut corrupted = MapConfig::default().encode_to_vec();
+    corrupted[0] = 0;
+    assert!(MapConfig::decode(&mut Source::new(&corrupted))
+        .expect("Failed to decode the config")
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn u64_map() {
+    let (data, _) = KeyValue::generate_map::<64>();
+    let mut map: U64Map<u32> = U64Map::default();
+
+    data.iter().for_each(|d| {
+        assert!(map
+            .insert(d.key, d.value)
+            .expect("Failed to insert a KV")
+            .is_none());
+    });
+
+    assert_eq!(64, map.len());
+
+    data.iter().for_each(|d| {
+        assert_eq!(
+            d.value,
+            *map.get(&d.key)
+                .expect("Failed to fetch an inserted KV")
+                .expect("The inserted KV was not found")
+        );
+    });
+
+    data.iter().for_each(|d| {
+        assert_eq!(
+            Some(d.value),
+            map.remove(&d.key).expect("Failed to remove a KV")
+        );
+    });
+
+    assert!(map.is_empty());
+}
+
+#[test]
+fn min_max_key() {
+    let mut map: Map<u64, u64> = Map::default();
+
+    assert!(map.max_key().is_none());
+    assert!(map.min_key().expect("Failed to traverse the map").is_none());
+
+    for i in (5..200).rev() {
+        map.insert(i, i).expect("Failed to insert a KV");
+        assert_eq!(Some(199), map.max_key());
+        assert_eq!(Some(i), map.min_key().expect("Failed to traverse the map"));
+    }
+
+    map.remove(&199).expect("Failed to remove a KV");
+    map.remove(&5).expect("Failed to remove a KV");
+
+    assert_eq!(Some(198), map.max_key());
+    assert_eq!(Some(6), map.min_key().expect("Failed to traverse the map"));
+}
+
+fn assert_no_empty_children<K, V>(map: &Map<K, V>)
+where
+    K: Canon + Ord + Default,
+    V: Canon,
+{
+    if let KelvinMap::Node(n) = map {
+        let (l, r) = n.children();
+        let l = l.val().expect("Failed to fetch the left child");
+        let r = r.val().expect("Failed to fetch the right child");
+
+        assert!(!l.is_empty() && !r.is_empty());
+
+        assert_no_empty_children(&l);
+        assert_no_empty_children(&r);
+    }
+}
+
+#[test]
+fn no_empty_children() {
+    const L: usize = 128;
+    let (data, mut map) = KeyValue::generate_map::<L>();
+
+    assert_no_empty_children(&map);
+
+    for (i, d) in data.iter().enumerate() {
+        map.remove(&d.key).expect("Failed to remove a KV");
+        assert_no_empty_children(&map);
+        assert_eq!(L - i - 1, map.len());
+    }
+
+    assert!(map.is_empty());
+}
+
+#[test]
+fn decode_verified() {
+    let (data, map) = KeyValue::generate_map::<64>();
+    map.validate()
+        .expect("A generated map is expected to be valid");
+
+    let bytes = map.encode_to_vec();
+    let decoded = Map::<u64, u32>::decode_verified(&bytes, 3)
+        .expect("Failed to decode a valid map");
+    for d in data.iter() {
+        assert_eq!(
+            d.value,
+            *decoded
+                .get(&d.key)
+                .expect("Failed to verify the path to a KV")
+                .expect("The inserted KV was not found")
+        );
+    }
+    let decoded = decoded.validate().expect("Failed to verify the map");
+    assert_eq!(64, decoded.len());
+
+    let mut high: Map<u64, u32> = Map::default();
+    high.insert(5, 5).expect("Failed to insert a KV");
+    let mut low: Map<u64, u32> = Map::default();
+    low.insert(1, 1).expect("Failed to insert a KV");
+
+    let unordered = graft(high.clone(), low);
+    assert!(matches!(unordered.validate(), Err(MapError::InvalidOrder)));
+
+    let bytes = unordered.encode_to_vec();
+    assert!(Map::<u64, u32>::decode_verified(&bytes, 1).is_err());
+    let lazy = Map::<u64, u32>::decode_verified(&bytes, 0)
+        .expect("The root alone is expected to be valid");
+    assert!(matches!(lazy.get(&5), Err(MapError::InvalidOrder)));
+    assert!(lazy.validate().is_err());
+
+    // Nodes with an empty child can't be built, but can be decoded
+    let mut bytes = vec![2];
+    bytes.extend(Annotated::new(high).encode_to_vec());
+    bytes.extend(Annotated::new(Map::<u64, u32>::default()).encode_to_vec());
+    let empty_child = Map::<u64, u32>::decode(&mut Source::new(&bytes))
+        .expect("Failed to decode the map");
+    assert!(matches!(empty_child.validate(), Err(MapError::EmptyChild)));
+}
+
+#[test]
+fn get_ct() {
+    let (data, map) = KeyValue::generate_map::<64>();
+
+    for d in data.iter() {
+        assert_eq!(
+            d.value,
+            *map.get_ct(&d.key)
+                .expect("Failed to fetch an inserted KV")
+                .expect("The inserted KV was not found")
+        );
+
+        assert!(map
+            .get_ct(&d.key.wrapping_add(1))
+            .expect("Failed to fetch an unexisting KV")
+            .is_none());
+    }
+
+    let mut bytes: Map<[u8; 32], u8> = Map::default();
+    for i in 0..16u8 {
+        bytes.insert([i * 2; 32], i).expect("Failed to insert a KV");
+    }
+
+    for i in 0..32u8 {
+        let value = bytes.get_ct(&[i; 32]).expect("Failed to fetch a KV");
+        match value {
+            Some(v) => assert_eq!(i / 2, *v),
+            None => assert_eq!(1, i % 2),
+        }
+    }
+}
+
+#[test]
+fn estimate_union_len() {
+    let mut low: Map<u64, u64> = Map::default();
+    let mut high: Map<u64, u64> = Map::default();
+    let mut mid: Map<u64, u64> = Map::default();
+
+    for i in 0..10 {
+        low.insert(i, i).expect("Failed to insert a KV");
+        high.insert(i + 100, i).expect("Failed to insert a KV");
+        mid.insert(i + 5, i).expect("Failed to insert a KV");
+    }
+
+    let union = |a: &Map<u64, u64>, b: &Map<u64, u64>| {
+        a.estimate_union_len(b)
+            .expect("Failed to estimate the union")
+    };
+
+    assert_eq!((20, 20), union(&low, &high));
+    assert_eq!((20, 20), union(&high, &low));
+    assert_eq!((10, 20), union(&low, &mid));
+    assert_eq!((10, 10), union(&low, &Map::default()));
+}
+
+#[test]
+fn unit_values() {
+    let mut set: Set<u64> = Set::default();
+
+    for i in 0..64 {
+        assert!(set.insert_key(i * 2).expect("Failed to insert a key"));
+    }
+    assert!(!set.insert_key(10).expect("Failed to insert a key"));
+    assert_eq!(64, set.len());
+
+    for i in 0..128 {
+        assert_eq!(
+            i % 2 == 0,
+            set.contains_key(&i).expect("Failed to fetch a key")
+        );
+    }
+
+    let leaf_len = 7u64.encoded_len();
+    let mut single: Set<u64> = Set::default();
+    single.insert_key(7).expect("Failed to insert a key");
+    assert_eq!(1 + leaf_len, single.encoded_len());
+
+    assert!(set.remove_key(&10).expect("Failed to remove a key"));
+    assert!(!set.remove_key(&10).expect("Failed to remove a key"));
+    assert!(!set.contains_key(&10).expect("Failed to fetch a key"));
+}
+
+#[test]
+fn multiset() {
+    let mut set: KelvinMultiSet<u64> = KelvinMultiSet::default();
+
+    for i in 0..32 {
+        for _ in 0..=(i % 4) {
+            set.insert(i).expect("Failed to insert an element");
+        }
+    }
+
+    assert_eq!(32, set.distinct());
+    assert_eq!(80, set.len());
+    assert_eq!(4, set.count(&7).expect("Failed to count an element"));
+    assert_eq!(0, set.count(&64).expect("Failed to count an element"));
+
+    assert!(set.remove_one(&7).expect("Failed to remove an element"));
+    assert_eq!(3, set.count(&7).expect("Failed to count an element"));
+    assert_eq!(79, set.len());
+
+    assert!(set.remove_one(&4).expect("Failed to remove an element"));
+    assert!(!set.remove_one(&4).expect("Failed to remove an element"));
+    assert_eq!(31, set.distinct());
+    assert_eq!(78, set.len());
+}
+
+#[test]
+fn changed_in_range() {
+    let mut old = HashedMap::<u64, u64>::default();
+    for i in 0..64 {
+        old.insert(i, i).expect("Failed to insert value");
+    }
+
+    let mut new = old.clone();
+    new.insert(20, 0).expect("Failed to insert value");
+    new.insert(100, 100).expect("Failed to insert value");
+
+    let changed = |range| {
+        HashedMap::changed_in_range::<StoreHasher, _>(&old, &new, range)
+            .expect("Failed to diff the maps")
+    };
+
+    assert!(
+        !HashedMap::changed_in_range::<StoreHasher, _>(&old, &old, ..)
+            .expect("Failed to diff the maps")
+    );
+    assert!(changed(0..64));
+    assert!(changed(20..21));
+    assert!(!changed(0..20));
+    assert!(!changed(21..64));
+    assert!(changed(64..128));
+}
+
+#[test]
+fn for_each_change() {
+    let mut old = HashedMap::<u64, u64>::default();
+    for i in 0..64 {
+        old.insert(i, i).expect("Failed to insert value");
+    }
+
+    let mut new = old.clone();
+    new.insert(20, 0).expect("Failed to insert value");
+    new.insert(100, 100).expect("Failed to insert value");
+    new.remove(&5).expect("Failed to remove value");
+
+    let mut changes = vec![];
+    HashedMap::for_each_change::<StoreHasher, _>(&old, &new, |change| {
+        changes.push(match change {
+            Change::Added(k, v) => (*k, None, Some(*v)),
+            Change::Removed(k, v) => (*k, Some(*v), None),
+            Change::Modified(k, o, n) => (*k, Some(*o), Some(*n)),
+        })
+    })
+    .expect("Failed to diff the maps");
+
+    let expected = vec![
+        (5, Some(5), None),
+        (20, Some(20), Some(0)),
+        (100, None, Some(100)),
+    ];
+    assert_eq!(expected, changes);
+
+    // The same contents, inserted in reverse order, split the keys elsewhere
+    let mut reversed = HashedMap::<u64, u64>::default();
+    for i in (0..64).rev().filter(|i| *i != 5) {
+        reversed.insert(i, i).expect("Failed to insert value");
+    }
+    reversed.insert(20, 0).expect("Failed to insert value");
+    reversed.insert(100, 100).expect("Failed to insert value");
+
+    let mut changes = vec![];
+    HashedMap::for_each_change::<StoreHasher, _>(&old, &reversed, |change| {
+        changes.push(match change {
+            Change::Added(k, v) => (*k, None, Some(*v)),
+            Change::Removed(k, v) => (*k, Some(*v), None),
+            Change::Modified(k, o, n) => (*k, Some(*o), Some(*n)),
+        })
+    })
+    .expect("Failed to diff the maps");
+
+    assert_eq!(expected, changes);
+}
+
+#[test]
+fn sum_overflow() {
+    let mut checked: SumMap<u64, u64, Checked> = SumMap::default();
+    let mut saturating: SumMap<u64, u64, Saturating> = SumMap::default();
+
+    for (k, v) in [(0, u64::MAX - 1), (1, 1)].iter() {
+        checked.insert(*k, *v).expect("Failed to insert value");
+        saturating.insert(*k, *v).expect("Failed to insert value");
+    }
+
+    assert_eq!(u64::MAX, checked.sum::<Checked>().expect("No overflow"));
+    assert_eq!(u64::MAX, saturating.sum::<Saturating>().expect("Saturated"));
+
+    checked.insert(2, 1).expect("Failed to insert value");
+    saturating.insert(2, 1).expect("Failed to insert value");
+
+    assert!(matches!(checked.sum::<Checked>(), Err(MapError::Overflow)));
+    assert_eq!(u64::MAX, saturating.sum::<Saturating>().expect("Saturated"));
+
+    checked.remove(&2).expect("Failed to remove value");
+    assert_eq!(u64::MAX, checked.sum::<Checked>().expect("No overflow"));
+}
+
+#[test]
+fn open_range() {
+    let mut map: Map<u64, u64> = Map::default();
+    for i in 0..64 {
+        map.insert(i, i * 2).expect("Failed to insert value");
+    }
+
+    let root = Id::new(&map);
+    let view = Map::<u64, u64>::open_range(&root, 10..20)
+        .expect("Failed to open the map");
+
+    assert_eq!(
+        30,
+        *view
+            .get(&15)
+            .expect("Failed to fetch value")
+            .expect("The key is in the map")
+    );
+    assert!(view.get(&100).is_err());
+
+    let entries = view.entries().expect("Failed to fetch the entries");
+    assert_eq!(10, entries.len());
+    assert_eq!(&20, entries[0].value());
+}
+
+#[test]
+fn alias_range() {
+    let mut map: Map<u64, u64> = Map::default();
+    for i in 0..64 {
+        map.insert(i, i * 2).expect("Failed to insert value");
+    }
+
+    let mut alias = map.alias_range(10..40).expect("Failed to alias range");
+    assert_eq!(30, alias.len());
+    assert!(alias.get(&9).expect("Failed to get value").is_none());
+    assert!(alias.get(&40).expect("Failed to get value").is_none());
+    assert_eq!(
+        20,
+        *alias
+            .get(&10)
+            .expect("Failed to get value")
+            .expect("The key is in the alias")
+    );
+
+    // Writes to the alias don't affect the original map
+    alias.insert(20, 0).expect("Failed to insert value");
+    alias.remove(&30).expect("Failed to remove value");
+    assert_eq!(
+        40,
+        *map.get(&20)
+            .expect("Failed to get value")
+            .expect("The key is in the map")
+    );
+    assert!(map.contains_key(&30).expect("Failed to get value"));
+    assert_eq!(64, map.len());
+
+    assert_eq!(64, map.alias_range(..).expect("Failed to alias").len());
+    assert!(map.alias_range(100..).expect("Failed to alias").is_empty());
+}
+
+#[cfg(feature = "persistence")]
+#[test]
+fn node_ids() {
+    let mut map: Map<u64, u64> = Map::default();
+    assert!(map.root_id().is_none());
+
+    for i in 0..64 {
+        map.insert(i, i).expect("Failed to insert value");
+    }
+
+    let root = map.root_id().expect("The map is not empty");
+    let children = map.child_ids().expect("Failed to fetch the children");
+    assert_eq!(2, children.len());
+
+    let decoded: Map<u64, u64> = root.reify().expect("Failed to reify root");
+    assert_eq!(
+        children,
+        decoded.child_ids().expect("Failed to fetch the children")
+    );
+
+    *map.get_mut(&0)
+        .expect("Failed to fetch value")
+        .expect("The key is in the map") = 1;
+    let updated = map.child_ids().expect("Failed to fetch the children");
+    assert_ne!(root, map.root_id().expect("The map is not empty"));
+    assert_ne!(children[0], updated[0]);
+    assert_eq!(children[1], updated[1]);
+}
+
+#[test]
+fn shrink() {
+    // Build a degenerate tree, with every leaf on the right of a new node
+    let mut map: Map<u64, u64> = Map::default();
+    map.insert(0, 0).expect("Failed to insert value");
+
+    for i in 1..64 {
+        let mut leaf = Map::default();
+        leaf.insert(i, i).expect("Failed to insert value");
+
+        map = graft(map, leaf);
+    }
+
+    let report = map.shrink().expect("Failed to shrink the map");
+    assert_eq!(63, report.depth_before);
+    assert_eq!(6, report.depth_after);
+    assert!(report.rewritten > 0);
+
+    map.validate().expect("The shrunk map is valid");
+    assert_eq!(64, map.len());
+    assert_balanced(&map);
+
+    let report = map.shrink().expect("Failed to shrink the map");
+    assert_eq!(0, report.rewritten);
+}
+
+#[test]
+fn write_set() {
+    let mut map: Map<u64, u64> = Map::default();
+    for i in 0..64 {
+        map.insert(i, i).expect("Failed to insert value");
+    }
+
+    let mut map = TrackedMap::new(map);
+    assert!(map.take_write_set().is_empty());
+
+    map.insert(3, 0).expect("Failed to insert value");
+    let write_set = map.take_write_set();
+
+    // The root and the path to the updated leaf are rewritten
+    let write_set = write_set.segments();
+    assert!(write_set[0].path().is_empty());
+    assert!(write_set.len() > 1);
+
+    let covers = |s: &PathSegment<u64>, k: u64| {
+        s.lower().map(|l| *l < k).unwrap_or(true)
+            && s.upper().map(|u| *u >= k).unwrap_or(true)
+    };
+    let deepest = write_set
+        .iter()
+        .filter(|s| covers(s, 3))
+        .max_by_key(|s| s.path().len())
+        .expect("The updated leaf is covered");
+    assert_eq!(
+        Some(&3),
+        deepest.upper(),
+        "The path to the leaf is expected to be rewritten"
+    );
+
+    assert!(map.take_write_set().is_empty());
+}
+
+/// Build a node out of two sub-trees regardless of their keys, by decoding
+/// its encoding as a forged store would provide it
+fn graft<V: Canon>(left: Map<u64, V>, right: Map<u64, V>) -> Map<u64, V> {
+    let mut bytes = vec![2u8];
+    bytes.extend(Annotated::new(left).encode_to_vec());
+    bytes.extend(Annotated::new(right).encode_to_vec());
+
+    Map::decode(&mut Source::new(&bytes)).expect("Failed to decode the node")
+}
+
+/// Build a perfectly balanced map out of a range of keys
+fn balanced_map(keys: core::ops::Range<u64>) -> Map<u64, u64> {
+    Map::from_sorted_iter(keys.map(|k| (k, k)))
+        .expect("Failed to build the map")
+}
+
+#[test]
+fn write_set_conflicts() {
+    // Updating a key of a balanced map doesn't move entries around
+    let map = balanced_map(0..64);
+
+    let write = |k: u64| {
+        let mut tracked = TrackedMap::new(map.clone());
+        tracked.insert(k, 0).expect("Failed to insert value");
+        tracked.take_write_set()
+    };
+
+    let (a, b, c) = (write(3), write(4), write(40));
+
+    assert!(a.conflicts_with(&a));
+    assert!(!a.conflicts_with(&b));
+    assert!(!a.conflicts_with(&c));
+    assert!(!c.conflicts_with(&a));
+    assert!(!a.conflicts_with(&WriteSet::default()));
+
+    let mut tracked = TrackedMap::new(map.clone());
+    tracked.remove(&3).expect("Failed to remove value");
+    let removal = tracked.take_write_set();
+
+    assert!(removal.conflicts_with(&a));
+    assert!(!removal.conflicts_with(&c));
+}
+
+#[test]
+fn latest() {
+    let mut map: Map<u64, u64> = Map::default();
+
+    assert!(map.latest().expect("Failed to traverse the map").is_none());
+    assert!(map
+        .latest_before(&100)
+        .expect("Failed to traverse the map")
+        .is_none());
+
+    // Block heights multiple of 10
+    for height in (10..=500).step_by(10) {
+        map.insert(height, height * 2)
+            .expect("Failed to insert a KV");
+    }
+
+    let latest = map
+        .latest()
+        .expect("Failed to traverse the map")
+        .expect("Failed to find the latest entry");
+    assert_eq!(1000, *latest.value());
+
+    for k in 0..520 {
+        let expected = match k {
+            0..=9 => None,
+            _ => Some(k.min(500) / 10 * 20),
+        };
+        let found = map
+            .latest_before(&k)
+            .expect("Failed to traverse the map")
+            .map(|leaf| *leaf.value());
+
+        assert_eq!(expected, found);
+    }
+}
+
+#[test]
+fn zip_iter() {
+    let mut left: Map<u64, u64> = Map::default();
+    let mut right: Map<u64, u32> = Map::default();
+
+    // Disjoint runs on both sides around an overlapping interval
+    for k in (0..100).chain(150..200) {
+        left.insert(k, k).expect("Failed to insert value");
+    }
+    for k in (50..160).chain(300..320) {
+        right
+            .insert(k, k as u32 * 2)
+            .expect("Failed to insert value");
+    }
+
+    let zipped: Vec<(u64, Option<u64>, Option<u32>)> = left
+        .zip_iter(&right)
+        .collect::<Result<_, _>>()
+        .expect("Failed to co-iterate the maps");
+
+    let expected: Vec<(u64, Option<u64>, Option<u32>)> = (0..320)
+        .filter_map(|k| {
+            let l = (k < 100 || (150..200).contains(&k)).then_some(k);
+            let r =
+                ((50..160).contains(&k) || k >= 300).then_some(k as u32 * 2);
+
+            (l.is_some() || r.is_some()).then_some((k, l, r))
+        })
+        .collect();
+
+    assert_eq!(expected, zipped);
+
+    let empty: Map<u64, u32> = Map::default();
+    assert_eq!(100 + 50, left.zip_iter(&empty).count());
+    assert_eq!(130, empty.zip_iter(&right).count());
+}
+
+#[test]
+fn combine_with() {
+    let mut balances: Map<u64, u64> = Map::default();
+    let mut debits: Map<u64, u64> = Map::default();
+
+    for k in 0..50 {
+        balances.insert(k, 100).expect("Failed to insert value");
+    }
+    for k in 25..75 {
+        debits
+            .insert(k, if k < 50 { 100 } else { 10 })
+            .expect("Failed to insert value");
+    }
+
+    // Settle the debits, dropping the emptied accounts and the debts of
+    // unknown accounts
+    let settled = balances
+        .combine_with(&debits, |b, d| match (b, d) {
+            (Some(b), Some(d)) if b > d => Some(b - d),
+            (Some(b), None) => Some(*b),
+            _ => None,
+        })
+        .expect("Failed to combine the maps");
+
+    assert_eq!(25, settled.len());
+    assert_no_empty_children(&settled);
+
+    for k in 0..75 {
+        let expected = if k < 25 { Some(100) } else { None };
+        let found = settled.get(&k).expect("Failed to get value").map(|v| *v);
+
+        assert_eq!(expected, found);
+    }
+}
+
+#[test]
+fn histogram() {
+    let mut map: Map<u64, u64> = Map::default();
+
+    let empty = map
+        .histogram(|k| k / 10)
+        .expect("Failed to build histogram");
+    assert!(empty.is_empty());
+
+    for k in 0..95 {
+        map.insert(k, k).expect("Failed to insert value");
+    }
+
+    // Range-aligned buckets
+    let decades = map
+        .histogram(|k| k / 10)
+        .expect("Failed to build histogram");
+    assert_eq!(10, decades.len());
+    for b in 0..9 {
+        assert_eq!(
+            Some(10),
+            decades.get(&b).expect("Failed to get value").map(|c| *c)
+        );
+    }
+    assert_eq!(
+        Some(5),
+        decades.get(&9).expect("Failed to get value").map(|c| *c)
+    );
+
+    // Interleaved buckets
+    let parity = map.histogram(|k| k % 2).expect("Failed to build histogram");
+    assert_eq!(
+        Some(48),
+        parity.get(&0).expect("Failed to get value").map(|c| *c)
+    );
+    assert_eq!(
+        Some(47),
+        parity.get(&1).expect("Failed to get value").map(|c| *c)
+    );
+}
+
+#[test]
+fn remove_many() {
+    let mut map: Map<u64, u64> = Map::default();
+
+    assert_eq!(
+        0,
+        map.remove_many(&[1, 2, 3]).expect("Failed to remove keys")
+    );
+
+    for k in 0..100 {
+        map.insert(k, k).expect("Failed to insert value");
+    }
+
+    // Unsorted, with duplicates and absent keys
+    let keys: Vec<u64> = (0..150)
+        .rev()
+        .filter(|k| k % 3 == 0)
+        .chain([9, 12])
+        .collect();
+    let removed = map.remove_many(&keys).expect("Failed to remove keys");
+
+    assert_eq!(34, removed);
+    assert_eq!(66, map.len());
+    assert_no_empty_children(&map);
+
+    for k in 0..100 {
+        let found = map.get(&k).expect("Failed to get value").map(|v| *v);
+        let expected = if k % 3 == 0 { None } else { Some(k) };
+
+        assert_eq!(expected, found);
+    }
+
+    let all: Vec<u64> = (0..100).collect();
+    assert_eq!(66, map.remove_many(&all).expect("Failed to remove keys"));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn len_cached() {
+    let mut map: Map<u64, u64> = Map::default();
+
+    for k in 0..200 {
+        map.insert(k % 150, k).expect("Failed to insert value");
+        assert_eq!(k.min(149) as usize + 1, map.len());
+    }
+
+    for k in (0..150).step_by(7) {
+        map.remove(&k).expect("Failed to remove value");
+    }
+    map.remove_many(&[1, 2, 3]).expect("Failed to remove keys");
+    map.shrink().expect("Failed to shrink the map");
+
+    assert_eq!(150 - 22 - 3, map.len());
+    map.validate().expect("Failed to validate the map");
+
+    let bytes = map.encode_to_vec();
+    let decoded = Map::<u64, u64>::decode(&mut Source::new(&bytes))
+        .expect("Failed to decode the map");
+    assert_eq!(map.len(), decoded.len());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn map_error_std() {
+    fn bubble(map: &Map<u64, u64>) -> Result<(), Box<dyn std::error::Error>> {
+        map.validate()?;
+        Err(MapError::OutOfRange)?
+    }
+
+    let map: Map<u64, u64> = Map::default();
+    let e = bubble(&map).expect_err("Failed to bubble the error");
+
+    assert_eq!("key out of range", e.to_string());
+}
+
+#[test]
+fn convert_annotation() {
+    let mut map: Map<u64, u64> = Map::default();
+    let mut hashed: HashedMap<u64, u64> = HashedMap::default();
+
+    for k in (0..100).rev() {
+        map.insert(k * 3, k).expect("Failed to insert value");
+        hashed.insert(k * 3, k).expect("Failed to insert value");
+    }
+
+    // Same shape, so same commitment as a map built as hashed
+    let converted: HashedMap<u64, u64> =
+        map.convert_annotation().expect("Failed to convert the map");
+    assert_eq!(
+        hashed.root_hash::<StoreHasher>(),
+        converted.root_hash::<StoreHasher>()
+    );
+    converted.validate().expect("Failed to validate the map");
+
+    let summed: SumMap<u64, u64> =
+        map.convert_annotation().expect("Failed to convert the map");
+    assert_eq!(4950, summed.sum::<Checked>().expect("No overflow"));
+    assert_eq!(map.len(), summed.len());
+
+    let empty: Map<u64, u64> = Map::default();
+    let empty: HashedMap<u64, u64> = empty
+        .convert_annotation()
+        .expect("Failed to convert the map");
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn repair() {
+    let keyed = |keys: core::ops::Range<u64>| {
+        let mut map: Map<u64, u64> = Map::default();
+        keys.for_each(|k| {
+            map.insert(k, k).expect("Failed to insert value");
+        });
+        map
+    };
+
+    let mut map = keyed(0..32);
+    assert!(map.repair().expect("Failed to repair the map").is_clean());
+
+    // Node whose left child is stored along the annotation of another tree
+    let (left, stale, right) = (keyed(0..10), keyed(0..11), keyed(20..30));
+
+    let left = Annotated::<_, MapAnnotationDefault<u64>>::new(left);
+    let stale = Annotated::<_, MapAnnotationDefault<u64>>::new(stale);
+    let right = Annotated::<_, MapAnnotationDefault<u64>>::new(right);
+
+    let left_bytes = left.encode_to_vec();
+    let repr_len = left_bytes.len() - left.annotation().encoded_len();
+    let stale_bytes = stale.encode_to_vec();
+
+    let mut bytes = vec![2u8];
+    bytes.extend_from_slice(&left_bytes[..repr_len]);
+    bytes.extend_from_slice(
+        &stale_bytes[stale_bytes.len() - stale.annotation().encoded_len()..],
+    );
+    bytes.extend(right.encode_to_vec());
+
+    let mut corrupt = Map::<u64, u64>::decode(&mut Source::new(&bytes))
+        .expect("Failed to decode the map");
+    assert_eq!(21, corrupt.len());
+    assert!(matches!(
+        corrupt.validate(),
+        Err(MapError::InvalidAnnotation)
+    ));
+
+    let report = corrupt.repair().expect("Failed to repair the map");
+    assert_eq!(1, report.annotations);
+    assert!(report.checked > 1);
+
+    assert_eq!(20, corrupt.len());
+    corrupt.validate().expect("Failed to validate the map");
+    assert!(corrupt
+        .repair()
+        .expect("Failed to repair the map")
+        .is_clean());
+
+    // Keys out of order are not repairable
+    let mut unordered: Map<u64, u64> = graft(keyed(5..6), keyed(1..2));
+    assert!(matches!(unordered.repair(), Err(MapError::InvalidOrder)));
+}
+
+#[test]
+fn top_k_by() {
+    fn max_key(a: &MapAnnotationDefault<u64>) -> &MaxKey<u64> {
+        a.borrow()
+    }
+
+    let mut map: Map<u64, u64> = Map::default();
+    assert!(map.top_k_by(3, max_key).expect("Failed to rank").is_empty());
+
+    map.insert(7, 0).expect("Failed to insert value");
+    let top = map.top_k_by(3, max_key).expect("Failed to rank");
+    assert_eq!(1, top.len());
+
+    let mut rng = StdRng::seed_from_u64(2321u64);
+    let mut keys: Vec<u64> = (0..200).map(|_| rng.next_u64() >> 1).collect();
+    keys.iter().for_each(|k| {
+        map.insert(*k, *k / 2).expect("Failed to insert value");
+    });
+    keys.push(7);
+    keys.sort_unstable_by(|a, b| b.cmp(a));
+
+    let top = map.top_k_by(10, max_key).expect("Failed to rank");
+    let top: Vec<u64> = top.iter().map(|l| *l.value() * 2).collect();
+    let expected: Vec<u64> = keys[..10].iter().map(|k| *k / 2 * 2).collect();
+
+    assert_eq!(expected, top);
+    assert_eq!(
+        201,
+        map.top_k_by(500, max_key).expect("Failed to rank").len()
+    );
+    assert!(map.top_k_by(0, max_key).expect("Failed to rank").is_empty());
+}
+
+#[test]
+fn threshold_key() {
+    let mut stakes: SumMap<u64, u64> = SumMap::default();
+    assert_eq!(
+        None,
+        stakes.threshold_key::<Checked>(0).expect("No overflow")
+    );
+
+    // Stake `k` for every key, prefix sums are the triangular numbers
+    for k in 1..=100 {
+        stakes.insert(k, k).expect("Failed to insert value");
+    }
+
+    let prefix = |k: u64| k * (k + 1) / 2;
+    for target in [0, 1, 2, 3, 4, 2500, 5049, 5050].iter() {
+        let key = stakes
+            .threshold_key::<Checked>(*target)
+            .expect("No overflow")
+            .expect("Failed to reach the target");
+
+        assert!(prefix(key) >= *target);
+        assert!(key == 1 || prefix(key - 1) < *target);
+    }
+
+    assert_eq!(
+        None,
+        stakes.threshold_key::<Checked>(5051).expect("No overflow")
+    );
+}
+
+#[test]
+fn snapshot_iter() {
+    let mut map: Map<u64, u64> = Map::default();
+    for k in 0..64 {
+        map.insert(k, k).expect("Failed to insert value");
+    }
+
+    let root = Id::new(&map);
+    let mut snapshot = map.snapshot_iter();
+
+    // Interleave the export with live writes
+    let mut exported = Vec::new();
+    for k in 0..64 {
+        let (key, value) = snapshot
+            .next()
+            .expect("Failed to reach the end")
+            .expect("Failed to fetch the entry");
+        exported.push((key, value));
+
+        map.remove(&k).expect("Failed to remove value");
+        map.insert(k + 100, 0).expect("Failed to insert value");
+        if let Some(mut v) = map.get_mut(&63).expect("Failed to get value") {
+            *v = 0;
+        }
+    }
+    assert!(snapshot.next().is_none());
+
+    let expected: Vec<(u64, u64)> = (0..64).map(|k| (k, k)).collect();
+    assert_eq!(expected, exported);
+
+    let persisted: Vec<(u64, u64)> = Map::<u64, u64>::snapshot_iter_at(&root)
+        .expect("Failed to open the map")
+        .collect::<Result<_, _>>()
+        .expect("Failed to fetch the entries");
+    assert_eq!(expected, persisted);
+
+    assert_eq!(0, Map::<u64, u64>::default().snapshot_iter().count());
+}
+
+#[test]
+fn shard() {
+    let mut map: Map<u64, u64> = Map::default();
+    for k in 0..103 {
+        map.insert(k * 2, k).expect("Failed to insert value");
+    }
+
+    let shards = map.shard(4).expect("Failed to shard the map");
+    let lens: Vec<usize> = shards.iter().map(|s| s.len()).collect();
+    assert_eq!(vec![25, 26, 26, 26], lens);
+
+    let mut next = 0;
+    for shard in shards.iter() {
+        shard.validate().expect("Failed to validate the shard");
+        assert_eq!(
+            Some(next * 2),
+            shard.min_key().expect("Failed to traverse")
+        );
+        next += shard.len() as u64;
+    }
+
+    let joined = Map::unshard(shards.clone()).expect("Failed to unshard");
+    joined.validate().expect("Failed to validate the map");
+    assert_eq!(map.len(), joined.len());
+    for k in 0..103 {
+        let v = joined
+            .get(&(k * 2))
+            .expect("Failed to get value")
+            .map(|v| *v);
+        assert_eq!(Some(k), v);
+    }
+
+    // More shards than entries
+    let small = map.shard(200).expect("Failed to shard the map");
+    assert_eq!(200, small.len());
+    assert_eq!(103, small.iter().filter(|s| !s.is_empty()).count());
+    assert_eq!(103, Map::unshard(small).expect("Failed to unshard").len());
+
+    let reversed = shards.into_iter().rev();
+    assert!(matches!(
+        Map::unshard(reversed),
+        Err(MapError::InvalidOrder)
+    ));
+}
+
+#[test]
+fn join_on_value() {
+    let mut owners: Map<u64, u32> = Map::default();
+    for owner in (0..100).step_by(3) {
+        owners
+            .insert(owner, owner as u32 + 1000)
+            .expect("Failed to insert value");
+    }
+
+    let join = |accounts: &Map<u64, u64>| -> Vec<(u64, u64, u32)> {
+        accounts
+            .join_on_value(&owners, |owner| *owner)
+            .map(|entry| {
+                entry.map(|((id, owner), (_, name))| (id, owner, name))
+            })
+            .collect::<Result<_, _>>()
+            .expect("Failed to join the maps")
+    };
+
+    let expected = |accounts: &Map<u64, u64>, range: core::ops::Range<u64>| {
+        range
+            .filter_map(|id| {
+                let owner = *accounts.get(&id).expect("Failed to get value")?;
+                (owner % 3 == 0 && owner < 100).then_some((
+                    id,
+                    owner,
+                    owner as u32 + 1000,
+                ))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Monotone extractor, co-traversed in a single pass
+    let mut monotone: Map<u64, u64> = Map::default();
+    for id in 0..60 {
+        monotone.insert(id, id * 2).expect("Failed to insert value");
+    }
+    assert_eq!(expected(&monotone, 0..60), join(&monotone));
+
+    // Extracted keys out of order
+    let mut shuffled: Map<u64, u64> = Map::default();
+    for id in 0..60 {
+        shuffled
+            .insert(id, (id * 37) % 120)
+            .expect("Failed to insert value");
+    }
+    assert_eq!(expected(&shuffled, 0..60), join(&shuffled));
+}
+
+#[cfg(feature = "profiling")]
+#[test]
+fn hottest_ranges() {
+    use dusk_kelvin_map::ProfiledMap;
+
+    let mut map: Map<u64, u64> = Map::default();
+    for k in 0..40 {
+        map.insert(k, k).expect("Failed to insert value");
+    }
+
+    let profiled = ProfiledMap::new(map);
+    for k in 0..40 {
+        // Skewed towards the third quarter of the key space
+        let reads = if (20..30).contains(&k) { 5 } else { 1 };
+        for _ in 0..reads {
+            profiled.get(&k).expect("Failed to get value");
+        }
+    }
+    profiled.get(&100).expect("Failed to get value");
+
+    assert_eq!(5, profiled.reads(&25));
+    assert_eq!(1, profiled.reads(&100));
+
+    let ranges = profiled.hottest_ranges(4).expect("Failed to rank ranges");
+    assert_eq!(4, ranges.len());
+    assert_eq!(
+        (20, 29, 50),
+        (ranges[0].first, ranges[0].last, ranges[0].reads)
+    );
+    assert!(ranges[1..].iter().all(|r| r.reads == 10));
+
+    profiled.reset();
+    assert_eq!(0, profiled.reads(&25));
+    assert_eq!(40, profiled.into_inner().len());
+}
+
+#[test]
+fn walk_with_fuel() {
+    let map = balanced_map(0..64);
+
+    // A balanced tree of 64 leaves is 6 nodes deep
+    let found = map.get_with_fuel(&17, 6).expect("Failed to walk the map");
+    assert!(matches!(found, Fueled::Complete(Some(17))));
+
+    let mut fueled = map.get_with_fuel(&17, 2).expect("Failed to walk the map");
+    let mut calls = 1;
+    let value = loop {
+        match fueled {
+            Fueled::Complete(value) => break value,
+            Fueled::Exhausted { resume_token } => {
+                calls += 1;
+                fueled = resume_token.resume(&17, 2).expect("Failed to resume");
+            }
+        }
+    };
+    assert_eq!((Some(17), 3), (value, calls));
+
+    let missing = map.get_with_fuel(&100, 10).expect("Failed to walk the map");
+    assert!(matches!(missing, Fueled::Complete(None)));
+
+    let mut entries = Vec::new();
+    let mut fueled = map
+        .iter_with_fuel(10, |k, v| entries.push((k, v)))
+        .expect("Failed to walk the map");
+    let mut calls = 1;
+    while let Fueled::Exhausted { resume_token } = fueled {
+        calls += 1;
+        fueled = resume_token
+            .resume(&map, 10, |k, v| entries.push((k, v)))
+            .expect("Failed to resume");
+    }
+
+    // 63 nodes descended with 10 units of fuel per call, plus the boundary
+    // paths descended again on every resumption
+    assert_eq!(8, calls);
+    assert_eq!((0..64).map(|k| (k, k)).collect::<Vec<_>>(), entries);
+}
+
+#[test]
+fn iter_with_fuel_mutations() {
+    let mut map: Map<u64, u64> = Map::default();
+    for k in 0..128 {
+        map.insert(k * 2, k).expect("Failed to insert value");
+    }
+    let mut seen: Vec<u64> = Vec::new();
+    let mut removed = Vec::new();
+
+    let mut fueled = map
+        .iter_with_fuel(32, |k, _| seen.push(k))
+        .expect("Failed to walk the map");
+    let mut page = 0u64;
+    while let Fueled::Exhausted { resume_token } = fueled {
+        let after = *resume_token.after().expect("Failed to visit a key");
+
+        // Write on both sides of the boundary between the first pages
+        if page < 8 {
+            map.insert(after + 1, 0).expect("Failed to insert value");
+            map.insert(page * 3 + 1, 0).expect("Failed to insert value");
+            let gone = after + 40 + page * 2;
+            if map.remove(&gone).expect("Failed to remove value").is_some() {
+                removed.push(gone);
+            }
+        }
+        page += 1;
+
+        // The token survives an encoding roundtrip
+        let token = resume_token.encode_to_vec();
+        let resume_token: dusk_kelvin_map::IterToken<u64> =
+            Canon::decode(&mut Source::new(&token))
+                .expect("Failed to decode the token");
+
+        fueled = resume_token
+            .resume(&map, 32, |k, _| seen.push(k))
+            .expect("Failed to resume");
+    }
+    assert!(page > 1);
+
+    // Strictly ascending: no entry visited twice
+    assert!(seen.windows(2).all(|w| w[0] < w[1]));
+
+    // Every entry present all along was visited
+    for k in (0..128).map(|k| k * 2).filter(|k| !removed.contains(k)) {
+        assert!(seen.contains(&k));
+    }
+    for k in removed {
+        assert!(!seen.contains(&k));
+    }
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn corrupt() {
+    use dusk_kelvin_map::corrupt;
+
+    let mut leaf = Map::default();
+    leaf.insert(1u64, 1u64).expect("Failed to insert value");
+    assert!(!corrupt::flip_annotation(&mut leaf).expect("Failed to corrupt"));
+    leaf.validate().expect("Failed to validate the map");
+
+    let mut map = balanced_map(0..16);
+    assert!(corrupt::flip_annotation(&mut map).expect("Failed to corrupt"));
+    assert!(matches!(map.validate(), Err(MapError::InvalidAnnotation)));
+    assert_eq!(1, map.repair().expect("Failed to repair").annotations);
+    map.validate().expect("Failed to validate the map");
+
+    let mut map = balanced_map(0..16);
+    assert!(corrupt::swap_children(&mut map).expect("Failed to corrupt"));
+    assert!(matches!(map.validate(), Err(MapError::InvalidOrder)));
+
+    let mut map = balanced_map(0..16);
+    assert!(corrupt::duplicate_key(&mut map).expect("Failed to corrupt"));
+    assert_eq!(17, map.len());
+    assert!(matches!(map.validate(), Err(MapError::InvalidOrder)));
+}
+
+#[test]
+fn iter() {
+    let mut map: Map<u64, u64> = Map::default();
+    assert_eq!(0, map.iter().expect("Failed to start the walk").count());
+
+    for k in (0..100).rev() {
+        map.insert(k * 3, k).expect("Failed to insert value");
+    }
+
+    let entries: Vec<(u64, u64)> = map
+        .iter()
+        .expect("Failed to start the walk")
+        .map(|entry| entry.map(|(k, v)| (*k, *v)))
+        .collect::<Result<_, _>>()
+        .expect("Failed to fetch the entries");
+
+    let expected: Vec<(u64, u64)> = (0..100).map(|k| (k * 3, k)).collect();
+    assert_eq!(expected, entries);
+
+    let (k, v) = map
+        .iter()
+        .expect("Failed to start the walk")
+        .nth(10)
+        .expect("Failed to reach the entry")
+        .expect("Failed to fetch the entry");
+    assert_eq!((30, 10), (*k, *v));
+}
+
+#[test]
+fn iter_mut() {
+    let mut map: SumMap<u64, u64> = SumMap::default();
+    for k in 0..100 {
+        map.insert(k, k).expect("Failed to insert value");
+    }
+
+    for entry in map.iter_mut().expect("Failed to start the walk") {
+        let (k, v) = entry.expect("Failed to fetch the entry");
+        *v += k % 3;
+    }
+
+    for k in 0..100 {
+        assert_eq!(
+            k + k % 3,
+            *map.get(&k)
+                .expect("Failed to get value")
+                .expect("Failed to find value")
+        );
+    }
+
+    let sum: u64 = (0..100).map(|k| k + k % 3).sum();
+    assert_eq!(sum, map.sum::<Checked>().expect("No overflow"));
+    map.validate().expect("Failed to validate the map");
+
+    // Stopping early keeps the annotations consistent
+    if let Some(entry) = map.iter_mut().expect("Failed to start").next() {
+        *entry.expect("Failed to fetch the entry").1 = 1000;
+    }
+    assert_eq!(sum + 1000, map.sum::<Checked>().expect("No overflow"));
+    map.validate().expect("Failed to validate the map");
+
+    let mut empty: Map<u64, u64> = Map::default();
+    assert_eq!(0, empty.iter_mut().expect("Failed to start").count());
+}
+
+#[cfg(feature = "host")]
+#[test]
+fn map_registry() {
+    use dusk_kelvin_map::MapRegistry;
+
+    let mut a: Map<u64, u64> = Map::default();
+    a.insert(1, 1).expect("Failed to insert value");
+    let a0 = Id::new(&a);
+    a.insert(2, 2).expect("Failed to insert value");
+    let a1 = Id::new(&a);
+    let b0 = Id::new(&Map::<u64, u64>::default());
+
+    let mut registry: MapRegistry<u32> = MapRegistry::default();
+    registry
+        .compare_and_swap(7, None, Some(a0))
+        .expect("Failed to register the contract");
+    assert_eq!(Some(a0), registry.root(&7).expect("Failed to get root"));
+
+    // A writer racing on the registration is rejected
+    assert!(matches!(
+        registry.compare_and_swap(7, None, Some(a1)),
+        Err(MapError::StaleRoot)
+    ));
+    registry
+        .compare_and_swap(7, Some(a0), Some(a1))
+        .expect("Failed to update the root");
+
+    // A stale update rolls back the whole batch
+    assert!(matches!(
+        registry.commit(vec![(9, None, Some(b0)), (7, Some(a0), None)]),
+        Err(MapError::StaleRoot)
+    ));
+    assert_eq!(None, registry.root(&9).expect("Failed to get root"));
+    assert_eq!(1, registry.len());
+
+    registry
+        .commit(vec![(9, None, Some(b0)), (7, Some(a1), None)])
+        .expect("Failed to commit the batch");
+    assert_eq!(None, registry.root(&7).expect("Failed to get root"));
+    assert_eq!(Some(b0), registry.root(&9).expect("Failed to get root"));
+
+    // The registry persists along with the maps
+    let restored: MapRegistry<u32> = Id::new(&registry)
+        .reify()
+        .expect("Failed to restore the registry");
+    assert_eq!(Some(b0), restored.root(&9).expect("Failed to get root"));
+}
+
+#[test]
+fn get_bytes() {
+    let mut map: Map<u64, (u32, u64)> = Map::default();
+    for k in 0..32 {
+        map.insert(k, (k as u32, k * 7))
+            .expect("Failed to insert value");
+    }
+
+    let bytes = map
+        .get_bytes(&5)
+        .expect("Failed to get value")
+        .expect("Failed to find value");
+    assert_eq!((5u32, 35u64).encode_to_vec(), &bytes[..]);
+
+    let raw =
+        unsafe { core::slice::from_raw_parts(bytes.as_ptr(), bytes.len()) };
+    let mut source = Source::new(raw);
+    assert_eq!(
+        (5, 35),
+        <(u32, u64)>::decode(&mut source).expect("Failed to decode value")
+    );
+
+    assert!(map.get_bytes(&32).expect("Failed to get value").is_none());
+}
+
+#[test]
+fn values() {
+    let mut map: Map<u64, u64> = Map::default();
+    assert_eq!(0, map.values().expect("Failed to start the walk").count());
+
+    for k in (0..50).rev() {
+        map.insert(k, k * k).expect("Failed to insert value");
+    }
+
+    let values: Vec<u64> = map
+        .values()
+        .expect("Failed to start the walk")
+        .map(|v| v.copied())
+        .collect::<Result<_, _>>()
+        .expect("Failed to fetch the values");
+    assert_eq!((0..50).map(|k| k * k).collect::<Vec<_>>(), values);
+
+    let checksum = map
+        .values()
+        .expect("Failed to start the walk")
+        .try_fold(0u64, |acc, v| v.map(|v| acc.wrapping_mul(31) ^ v))
+        .expect("Failed to fetch the values");
+    let expected = (0..50).fold(0u64, |acc, k| acc.wrapping_mul(31) ^ (k * k));
+    assert_eq!(expected, checksum);
+}
+
+#[test]
+fn range() {
+    use std::collections::BTreeMap;
+    use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+    let mut rng = StdRng::seed_from_u64(2321u64);
+    let mut map: Map<u64, u64> = Map::default();
+    let mut reference = BTreeMap::new();
+    for _ in 0..200 {
+        let k = rng.next_u64() % 1000;
+        map.insert(k, k * 2).expect("Failed to insert value");
+        reference.insert(k, k * 2);
+    }
+
+    let bound = |rng: &mut StdRng| -> Bound<u64> {
+        let k = rng.next_u64() % 1100;
+        match rng.next_u64() % 3 {
+            0 => Included(k),
+            1 => Excluded(k),
+            _ => Unbounded,
+        }
+    };
+
+    for _ in 0..200 {
+        let (lower, upper) = (bound(&mut rng), bound(&mut rng));
+
+        let expected: Vec<(u64, u64)> = match (lower, upper) {
+            (Included(s), Included(e)) | (Included(s), Excluded(e))
+                if s > e =>
+            {
+                vec![]
+            }
+            (Excluded(s), Included(e)) | (Excluded(s), Excluded(e))
+                if s >= e =>
+            {
+                vec![]
+            }
+            _ => reference
+                .range((lower, upper))
+                .map(|(k, v)| (*k, *v))
+                .collect(),
+        };
+
+        let entries: Vec<(u64, u64)> = map
+            .range((lower, upper))
+            .expect("Failed to start the walk")
+            .map(|entry| entry.map(|(k, v)| (*k, *v)))
+            .collect::<Result<_, _>>()
+            .expect("Failed to fetch the entries");
+        assert_eq!(expected, entries);
+
+        let count = map
+            .count_range((lower, upper))
+            .expect("Failed to count the entries");
+        assert_eq!(expected.len() as u64, count);
+    }
+
+    let empty: Map<u64, u64> = Map::default();
+    assert_eq!(0, empty.range(..).expect("Failed to start").count());
+    assert_eq!(0, empty.count_range(..).expect("Failed to count"));
+}
+
+#[test]
+fn values_mut() {
+    let mut map: Map<u64, (u32, u64)> = Map::default();
+    for k in 0..80 {
+        map.insert(k, (1, k)).expect("Failed to insert value");
+    }
+    let root = Id::new(&map);
+
+    // Migrate every value to the next version
+    for value in map.values_mut().expect("Failed to start the walk") {
+        let value = value.expect("Failed to fetch the value");
+        *value = (value.0 + 1, value.1 * 10);
+    }
+
+    let values: Vec<(u32, u64)> = map
+        .values()
+        .expect("Failed to start the walk")
+        .map(|v| v.copied())
+        .collect::<Result<_, _>>()
+        .expect("Failed to fetch the values");
+    assert_eq!((0..80).map(|k| (2, k * 10)).collect::<Vec<_>>(), values);
+
+    map.validate().expect("Failed to validate the map");
+    assert_ne!(root, Id::new(&map));
+}
+
+#[test]
+fn into_iter() {
+    use std::collections::BTreeMap;
+
+    let mut map: Map<u64, u64> = Map::default();
+    for k in (0..70).rev() {
+        map.insert(k, k + 1).expect("Failed to insert value");
+    }
+
+    let drained: BTreeMap<u64, u64> = map
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .expect("Failed to fetch the entries");
+    assert_eq!(70, drained.len());
+    assert!(drained.iter().all(|(k, v)| *v == k + 1));
+
+    let mut entries = Vec::new();
+    for entry in balanced_map(0..8) {
+        entries.push(entry.expect("Failed to fetch the entry"));
+    }
+    assert_eq!((0..8).map(|k| (k, k)).collect::<Vec<_>>(), entries);
+}
+
+#[test]
+fn invert() {
+    let mut map: Map<u64, u32> = Map::default();
+    for k in 0..60 {
+        map.insert(k, (k / 3) as u32)
+            .expect("Failed to insert value");
+    }
+
+    assert!(matches!(
+        map.invert::<MapAnnotationDefault<u32>>(DuplicatePolicy::Reject),
+        Err(MapError::DuplicateValue)
+    ));
+
+    let first: Map<u32, u64> = map
+        .invert(DuplicatePolicy::KeepFirst)
+        .expect("Failed to invert the map");
+    let last: Map<u32, u64> = map
+        .invert(DuplicatePolicy::KeepLast)
+        .expect("Failed to invert the map");
+
+    first.validate().expect("Failed to validate the map");
+    last.validate().expect("Failed to validate the map");
+    assert_eq!(20, first.len());
+
+    for v in 0..20u32 {
+        let k = first
+            .get(&v)
+            .expect("Failed to get value")
+            .expect("Failed to find value");
+        assert_eq!(v as u64 * 3, *k);
+
+        let k = last
+            .get(&v)
+            .expect("Failed to get value")
+            .expect("Failed to find value");
+        assert_eq!(v as u64 * 3 + 2, *k);
+    }
+
+    let mut unique: Map<u64, u32> = Map::default();
+    for k in 0..10 {
+        unique
+            .insert(k, 100 - k as u32)
+            .expect("Failed to insert value");
+    }
+    let inverted: Map<u32, u64> = unique
+        .invert(DuplicatePolicy::Reject)
+        .expect("Failed to invert the map");
+    assert_eq!(
+        Some(9),
+        inverted.get(&91).expect("Failed to get").map(|k| *k)
+    );
+}
+
+#[test]
+fn linked_map() {
+    let mut map: KelvinLinkedMap<u64, u64> = KelvinLinkedMap::default();
+    let keys = [40u64, 7, 93, 12, 55, 3];
+    for (i, k) in keys.iter().enumerate() {
+        assert!(map
+            .insert(*k, i as u64)
+            .expect("Failed to insert")
+            .is_none());
+    }
+
+    // Overwriting keeps the position in the insertion order
+    assert_eq!(Some(2), map.insert(93, 20).expect("Failed to insert"));
+    if let Some(mut v) = map.get_mut(&12).expect("Failed to get value") {
+        *v = 30;
+    }
+    assert_eq!(Some(2), map.sequence(&93).expect("Failed to get sequence"));
+
+    let by_key: Vec<(u64, u64)> = map
+        .iter()
+        .expect("Failed to start the walk")
+        .map(|entry| entry.map(|(k, v)| (*k, *v)))
+        .collect::<Result<_, _>>()
+        .expect("Failed to fetch the entries");
+    assert_eq!(
+        vec![(3, 5), (7, 1), (12, 30), (40, 0), (55, 4), (93, 20)],
+        by_key
+    );
+
+    let by_insertion: Vec<(u64, u64)> = map
+        .iter_by_insertion()
+        .expect("Failed to start the walk")
+        .map(|entry| entry.map(|(k, v)| (*k, v)))
+        .collect::<Result<_, _>>()
+        .expect("Failed to fetch the entries");
+    assert_eq!(
+        vec![(40, 0), (7, 1), (93, 20), (12, 30), (55, 4), (3, 5)],
+        by_insertion
+    );
+
+    // Used as a queue with random access
+    assert_eq!(Some(1), map.remove(&7).expect("Failed to remove"));
+    assert_eq!(Some((40, 0)), map.pop_front().expect("Failed to pop"));
+    assert_eq!(Some((93, 20)), map.pop_front().expect("Failed to pop"));
+    map.insert(7, 8).expect("Failed to insert");
+    assert_eq!(Some(6), map.sequence(&7).expect("Failed to get sequence"));
+    assert_eq!(4, map.len());
+
+    let mut popped = Vec::new();
+    while let Some((k, _)) = map.pop_front().expect("Failed to pop") {
+        popped.push(k);
+    }
+    assert_eq!(vec![12, 55, 3, 7], popped);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn range_mut() {
+    let mut map: SumMap<u64, u64> = SumMap::default();
+    for k in 0..100 {
+        map.insert(k, 1).expect("Failed to insert value");
+    }
+
+    for entry in map.range_mut(20..40).expect("Failed to start the walk") {
+        let (k, v) = entry.expect("Failed to fetch the entry");
+        assert!((20..40).contains(k));
+        *v += 9;
+    }
+    assert_eq!(100 + 20 * 9, map.sum::<Checked>().expect("No overflow"));
+
+    let mut updated = 0;
+    for entry in map.range_mut(95..).expect("Failed to start the walk") {
+        *entry.expect("Failed to fetch the entry").1 = 0;
+        updated += 1;
+    }
+    assert_eq!(5, updated);
+    assert_eq!(95 + 20 * 9, map.sum::<Checked>().expect("No overflow"));
+
+    assert_eq!(0, map.range_mut(200..).expect("Failed to start").count());
+    map.validate().expect("Failed to validate the map");
+}
+
+#[test]
+fn kelvin_collection() {
+    fn fill<C>(collection: &mut C) -> Result<Vec<(u64, u64)>, C::Error>
+    where
+        C: KelvinCollection<u64, u64>,
+    {
+        for k in [5u64, 1, 9, 3, 7] {
+            collection.insert(k, k * 10)?;
+        }
+        assert_eq!(Some(90), collection.insert(9, 91)?);
+        assert_eq!(Some(30), collection.remove(&3)?);
+        assert_eq!(None, collection.remove(&3)?);
+        assert_eq!(Some(10), collection.get(&1)?);
+        assert_eq!(None, collection.get(&2)?);
+        assert_eq!(4, collection.len());
+        assert!(!collection.is_empty());
+
+        collection.iter()?.collect()
+    }
+
+    let by_key = vec![(1, 10), (5, 50), (7, 70), (9, 91)];
+
+    let mut map: Map<u64, u64> = Map::default();
+    assert_eq!(by_key, fill(&mut map).expect("Failed to fill the map"));
+
+    let mut configured: ConfiguredMap<u64, u64, MapAnnotationDefault<u64>> =
+        ConfiguredMap::new(MapConfig::default())
+            .expect("Failed to create the map");
+    assert_eq!(
+        by_key,
+        fill(&mut configured).expect("Failed to fill the map")
+    );
+
+    let mut linked: KelvinLinkedMap<u64, u64> = KelvinLinkedMap::default();
+    assert_eq!(
+        vec![(5, 50), (1, 10), (9, 91), (7, 70)],
+        fill(&mut linked).expect("Failed to fill the map")
+    );
+}
+
+#[test]
+fn entry() {
+    let mut map: SumMap<u64, u64> = SumMap::default();
+
+    // Word count style upsert
+    for k in [3u64, 1, 3, 2, 3, 1] {
+        map.entry(k)
+            .expect("Failed to get the entry")
+            .and_modify(|v| *v += 1)
+            .or_insert(1)
+            .expect("Failed to insert the entry");
+    }
+    for (k, count) in [(1, 2), (2, 1), (3, 3)] {
+        assert_eq!(
+            count,
+            *map.get(&k)
+                .expect("Failed to get value")
+                .expect("Failed to find value")
+        );
+    }
+    assert_eq!(6, map.sum::<Checked>().expect("No overflow"));
+
+    *map.entry(10)
+        .expect("Failed to get the entry")
+        .or_default()
+        .expect("Failed to insert the entry") += 4;
+    assert_eq!(10, map.sum::<Checked>().expect("No overflow"));
+
+    match map.entry(3).expect("Failed to get the entry") {
+        Entry::Occupied(mut e) => {
+            assert_eq!(3, *e.get());
+            assert_eq!(3, e.insert(30));
+            assert_eq!(30, *e.get());
+        }
+        Entry::Vacant(_) => panic!("The key is expected to be occupied"),
+    }
+    assert_eq!(Some(30), map.remove(&3).expect("Failed to remove a KV"));
+
+    match map.entry(4).expect("Failed to get the entry") {
+        Entry::Vacant(e) => {
+            assert_eq!(&4, e.key());
+            *e.insert(5).expect("Failed to insert the entry") *= 2;
+        }
+        Entry::Occupied(_) => panic!("The key is expected to be vacant"),
+    }
+
+    assert_eq!(4, map.len());
+    assert_eq!(17, map.sum::<Checked>().expect("No overflow"));
+    map.validate().expect("Failed to validate the map");
+}
+
+#[test]
+fn split_sizes() {
+    let mut map: Map<u64, u64> = Map::default();
+    assert_eq!(None, map.split_sizes());
+
+    map.insert(0, 0).expect("Failed to insert value");
+    assert_eq!(None, map.split_sizes());
+
+    for k in 1..41 {
+        map.insert(k, k).expect("Failed to insert value");
+    }
+    let (l, r) = map.split_sizes().expect("Failed to split the map");
+    assert_eq!(41, l + r);
+
+    assert_eq!(Some((32, 32)), balanced_map(0..64).split_sizes());
+}
+
+#[test]
+fn first_last_key_value() {
+    let mut map: Map<u64, u64> = Map::default();
+    assert!(map.first_key_value().expect("Failed to walk").is_none());
+    assert!(map.last_key_value().expect("Failed to walk").is_none());
+
+    map.insert(7, 70).expect("Failed to insert value");
+    assert_eq!(
+        Some((&7, &70)),
+        map.first_key_value().expect("Failed to walk")
+    );
+    assert_eq!(
+        Some((&7, &70)),
+        map.last_key_value().expect("Failed to walk")
+    );
+
+    let mut rng = StdRng::seed_from_u64(2321u64);
+    let mut keys = vec![7];
+    for _ in 0..100 {
+        let k = rng.next_u64() % 10_000;
+        map.insert(k, k * 10).expect("Failed to insert value");
+        keys.push(k);
+
+        let min = *keys.iter().min().expect("Failed to find min");
+        let max = *keys.iter().max().expect("Failed to find max");
+        assert_eq!(
+            Some((&min, &(min * 10))),
+            map.first_key_value().expect("Failed to walk")
+        );
+        assert_eq!(
+            Some((&max, &(max * 10))),
+            map.last_key_value().expect("Failed to walk")
+        );
+    }
+}
+
+#[test]
+fn key_range() {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    let mut map: Map<u64, u64> = Map::default();
+    for k in 0..50 {
+        map.insert(k, k).expect("Failed to insert value");
+    }
+
+    assert!(matches!(
+        KeyRange::new(Included(10), Included(9)),
+        Err(MapError::InvalidRange)
+    ));
+    assert!(matches!(
+        KeyRange::new(Excluded(10), Excluded(10)),
+        Err(MapError::InvalidRange)
+    ));
+    assert!(matches!(
+        KeyRange::from_bounds(&(Included(30), Excluded(20))),
+        Err(MapError::InvalidRange)
+    ));
+
+    let empty = KeyRange::from_bounds(&(10..10)).expect("Valid range");
+    assert!(empty.is_empty());
+    assert_eq!(0, map.count_range(empty).expect("Failed to count"));
+
+    let range = KeyRange::new(Excluded(9), Included(19)).expect("Valid range");
+    assert!(!range.is_empty());
+
+    let encoded = range.encode_to_vec();
+    let decoded = KeyRange::<u64>::decode(&mut Source::new(&encoded))
+        .expect("Failed to decode the range");
+    assert_eq!(range, decoded);
+
+    let keys: Vec<u64> = map
+        .range(decoded.clone())
+        .expect("Failed to start the walk")
+        .map(|e| *e.expect("Failed to fetch the entry").0)
+        .collect();
+    assert_eq!((10..20).collect::<Vec<_>>(), keys);
+    assert_eq!(10, map.count_range(decoded).expect("Failed to count"));
+    assert_eq!(
+        50,
+        map.count_range(KeyRange::full()).expect("Failed to count")
+    );
+
+    let tail = KeyRange::new(Included(40), Unbounded).expect("Valid range");
+    assert_eq!(10, map.count_range(tail).expect("Failed to count"));
+
+    // An inverted range can't be smuggled through its encoding
+    let high = KeyRange::new(Included(20), Included(20)).expect("Valid range");
+    let low = KeyRange::new(Included(10), Included(10)).expect("Valid range");
+    let (high, low) = (high.encode_to_vec(), low.encode_to_vec());
+    let half = high.len() / 2;
+    let encoded = [&high[..half], &low[half..]].concat();
+    assert!(KeyRange::<u64>::decode(&mut Source::new(&encoded)).is_err());
+}
+
+#[test]
+fn floor_ceiling() {
+    use std::collections::BTreeMap;
+
+    let mut rng = StdRng::seed_from_u64(763u64);
+    let mut map: Map<u64, u64> = Map::default();
+    let mut reference = BTreeMap::new();
+
+    assert!(map.get_floor(&10).expect("Failed to walk").is_none());
+    assert!(map.get_ceiling(&10).expect("Failed to walk").is_none());
+
+    for _ in 0..150 {
+        let k = rng.next_u64() % 1000;
+        map.insert(k, k + 1).expect("Failed to insert value");
+        reference.insert(k, k + 1);
+    }
+
+    for k in 0..1010 {
+        let floor = map.get_floor(&k).expect("Failed to walk the floor");
+        assert_eq!(reference.range(..=k).next_back(), floor);
+
+        let ceiling = map.get_ceiling(&k).expect("Failed to walk the ceiling");
+        assert_eq!(reference.range(k..).next(), ceiling);
+    }
+}
+
+#[test]
+fn tombstones() {
+    let mut map: TombstoneMap<u64, u64> = TombstoneMap::default();
+    for k in 0..20 {
+        map.insert(k, k * 10).expect("Failed to insert value");
+    }
+
+    assert_eq!(Some(30), map.remove(&3, 100).expect("Failed to remove"));
+    assert_eq!(Some(40), map.remove(&4, 200).expect("Failed to remove"));
+    assert_eq!(Some(50), map.remove(&5, 300).expect("Failed to remove"));
+    assert_eq!(None, map.remove(&5, 400).expect("Failed to remove"));
+    assert_eq!(None, map.remove(&50, 400).expect("Failed to remove"));
+
+    // Deletions stay observable, along with their stamps
+    assert_eq!(None, map.get(&3).expect("Failed to fetch"));
+    assert!(!map.contains_key(&4).expect("Failed to fetch"));
+    let slot = map.slot(&5).expect("Failed to fetch").map(|s| s.clone());
+    assert_eq!(Some(Slot::Tombstone(300)), slot);
+    assert_eq!(20, map.slots().len());
+    assert_eq!(17, map.iter().expect("Failed to iterate").count());
+
+    // A key inserted again is live once more
+    assert_eq!(None, map.insert(4, 41).expect("Failed to insert value"));
+    assert_eq!(Some(41), map.get(&4).expect("Failed to fetch"));
+
+    assert_eq!(
+        1,
+        map.purge_tombstones_before(300).expect("Failed to purge")
+    );
+    assert!(map.slot(&3).expect("Failed to fetch").is_none());
+    assert!(map.slot(&5).expect("Failed to fetch").is_some());
+    assert_eq!(19, map.slots().len());
+    map.slots().validate().expect("Failed to validate the map");
+}
+
+#[test]
+fn lww_merge() {
+    let mut a: LwwMap<u64, u64> = LwwMap::default();
+    let mut b: LwwMap<u64, u64> = LwwMap::default();
+
+    for k in 0..10 {
+        a.insert(k, Stamped::new(k, 1, 1)).expect("Failed to write");
+        b.insert(k + 5, Stamped::new(k + 50, 2, 2))
+            .expect("Failed to write");
+    }
+
+    // Stale and tied writes lose against the current one
+    assert!(!a.insert(0, Stamped::new(9, 0, 9)).expect("Failed to write"));
+    assert!(a.insert(0, Stamped::new(9, 1, 3)).expect("Failed to write"));
+    assert!(!b
+        .insert(7, Stamped::new(70, 2, 1))
+        .expect("Failed to write"));
+    assert!(a
+        .insert(7, Stamped::new(71, 2, 1))
+        .expect("Failed to write"));
+
+    let mut ab = a.clone();
+    let mut ba = b.clone();
+    assert_eq!(10, ab.merge(&b).expect("Failed to merge"));
+    assert_eq!(5, ba.merge(&a).expect("Failed to merge"));
+
+    let entries = |map: &LwwMap<u64, u64>| -> Vec<(u64, Stamped<u64>)> {
+        map.iter()
+            .expect("Failed to iterate")
+            .map(|e| e.map(|(k, v)| (*k, v.clone())))
+            .collect::<Result<_, _>>()
+            .expect("Failed to fetch the entries")
+    };
+
+    assert_eq!(entries(&ab), entries(&ba));
+    assert_eq!(15, ab.len());
+
+    let winner = ab.get(&7).expect("Failed to fetch").map(|v| v.clone());
+    assert_eq!(Some(Stamped::new(52, 2, 2)), winner);
+    let own = ab.get(&0).expect("Failed to fetch").map(|v| *v.value());
+    assert_eq!(Some(9), own);
+
+    assert_eq!(0, ab.merge(&ba).expect("Failed to merge"));
+}
+
+#[test]
+fn get_key_value() {
+    /// Key carrying context ignored by its ordering
+    #[derive(Debug, Default, Clone, Canon)]
+    struct Tagged {
+        id: u64,
+        tag: u32,
+    }
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.id.cmp(&other.id)
+        }
+    }
+
+    let mut map: Map<Tagged, u64> = Map::default();
+    for id in 0..30 {
+        let tag = id as u32 * 7;
+        map.insert(Tagged { id, tag }, id * 2)
+            .expect("Failed to insert value");
+    }
+
+    let query = Tagged { id: 12, tag: 0 };
+    let (k, v) = map
+        .get_key_value(&query)
+        .expect("Failed to fetch")
+        .expect("Missing key");
+    assert_eq!(84, k.tag);
+    assert_eq!(24, *v);
+
+    let missing = Tagged { id: 30, tag: 0 };
+    assert!(map
+        .get_key_value(&missing)
+        .expect("Failed to fetch")
+        .is_none());
+}
+
+#[test]
+fn finger_search() {
+    let mut rng = StdRng::seed_from_u64(766u64);
+    let mut map: Map<u64, u64> = Map::default();
+    for k in 0..300 {
+        map.insert(k * 3, k).expect("Failed to insert value");
+    }
+
+    // Nearly sorted batch, with some jumps back and misses
+    let mut finger = map.finger();
+    for i in 0..1000u64 {
+        let k = match rng.next_u64() % 10 {
+            0 => rng.next_u64() % 1000,
+            _ => i - (rng.next_u64() % 4).min(i),
+        };
+
+        let expected = map.get(&k).expect("Failed to fetch").map(|v| *v);
+        let found = map.get_from(&mut finger, &k).expect("Failed to fetch");
+        assert_eq!(expected, found);
+        assert!(finger.depth() > 0);
+    }
+
+    // A finger of another map is reset to the root of this one
+    let mut other: Map<u64, u64> = Map::default();
+    other.insert(3, 30).expect("Failed to insert value");
+    let mut finger = other.finger();
+    assert_eq!(Some(30), other.get_from(&mut finger, &3).expect("Fetch"));
+
+    let copy = other.clone();
+    assert_eq!(Some(30), copy.get_from(&mut finger, &3).expect("Fetch"));
+    assert_eq!(None, copy.get_from(&mut finger, &4).expect("Fetch"));
+}
+
+#[test]
+fn nth() {
+    let mut rng = StdRng::seed_from_u64(7662u64);
+    let mut map: Map<u64, u64> = Map::default();
+    let mut keys = Vec::new();
+    for _ in 0..200 {
+        let k = rng.next_u64() % 10_000;
+        map.insert(k, k + 1).expect("Failed to insert value");
+        keys.push(k);
+    }
+    keys.sort_unstable();
+    keys.dedup();
+
+    for (n, k) in keys.iter().enumerate() {
+        let entry = map.nth(n).expect("Failed to walk").map(|(k, v)| (*k, *v));
+        assert_eq!(Some((*k, k + 1)), entry);
+    }
+    assert!(map.nth(keys.len()).expect("Failed to walk").is_none());
+    assert!(Map::<u64, u64>::default().nth(0).expect("Walk").is_none());
+}
+
+#[test]
+fn take_range() {
+    use std::collections::BTreeMap;
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    let mut rng = StdRng::seed_from_u64(767u64);
+    let mut map: Map<u64, u64> = Map::default();
+    let mut reference = BTreeMap::new();
+    for _ in 0..300 {
+        let k = rng.next_u64() % 2000;
+        map.insert(k, k * 2).expect("Failed to insert value");
+        reference.insert(k, k * 2);
+    }
+
+    let ranges = [
+        (Included(100), Excluded(300)),
+        (Excluded(250), Included(600)),
+        (Included(1900), Unbounded),
+        (Unbounded, Included(50)),
+        (Included(700), Included(700)),
+        (Included(1000), Excluded(1000)),
+    ];
+
+    for range in ranges.iter() {
+        let expected: Vec<(u64, u64)> =
+            reference.range(*range).map(|(k, v)| (*k, *v)).collect();
+        for (k, _) in expected.iter() {
+            reference.remove(k);
+        }
+
+        let taken = map.take_range(*range).expect("Failed to take the range");
+        assert_eq!(expected, taken);
+        assert_eq!(reference.len(), map.len());
+        map.validate().expect("Failed to validate the map");
+    }
+
+    let rest: Vec<(u64, u64)> = reference.into_iter().collect();
+    assert_eq!(rest, map.take_range(..).expect("Failed to take the range"));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn rank() {
+    let mut map: Map<u64, u64> = Map::default();
+    assert_eq!(0, map.rank(&10).expect("Failed to rank"));
+
+    for k in 0..150 {
+        map.insert(k * 2, k).expect("Failed to insert value");
+    }
+
+    for k in 0..310u64 {
+        let expected = k.div_ceil(2).min(150) as usize;
+        assert_eq!(expected, map.rank(&k).expect("Failed to rank"));
+    }
+
+    let rank = map.rank(&84).expect("Failed to rank");
+    let entry = map
+        .nth(rank)
+        .expect("Failed to walk")
+        .map(|(k, v)| (*k, *v));
+    assert_eq!(Some((84, 42)), entry);
+}
+
+#[test]
+fn const_new() {
+    const GENESIS: (Map<u64, u64>, U64Map<u32>) = (Map::new(), U64Map::new());
+
+    let (mut map, other) = GENESIS;
+    assert!(map.is_empty() && other.is_empty());
+
+    map.insert(1, 10).expect("Failed to insert value");
+    assert_eq!(1, map.len());
+    assert!(GENESIS.0.is_empty());
+}
+
+#[test]
+fn retain() {
+    let mut map: SumMap<u64, u64> = SumMap::default();
+    for k in 0..200 {
+        map.insert(k, k).expect("Failed to insert value");
+    }
+
+    let mut visited = Vec::new();
+    let removed = map
+        .retain(|k, v| {
+            visited.push(*k);
+            *v += 1;
+            k % 3 == 0
+        })
+        .expect("Failed to retain");
+
+    assert_eq!((0..200).collect::<Vec<_>>(), visited);
+    assert_eq!(133, removed);
+    assert_eq!(67, map.len());
+
+    let entries: Vec<(u64, u64)> = map
+        .iter()
+        .expect("Failed to iterate")
+        .map(|e| e.map(|(k, v)| (*k, *v)))
+        .collect::<Result<_, _>>()
+        .expect("Failed to fetch the entries");
+    let expected: Vec<(u64, u64)> = (0..200)
+        .filter(|k| k % 3 == 0)
+        .map(|k| (k, k + 1))
+        .collect();
+    assert_eq!(expected, entries);
+    assert_eq!(
+        expected.iter().map(|(_, v)| v).sum::<u64>(),
+        map.sum::<Checked>().expect("No overflow")
+    );
+    map.validate().expect("Failed to validate the map");
+
+    assert_eq!(67, map.retain(|_, _| false).expect("Failed to retain"));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn simulate_writes() {
+    let mut map: HashedMap<u64, u64> = HashedMap::default();
+
+    let report = map.simulate_insert(&5, &50).expect("Failed to simulate");
+    let expected = SimulationReport {
+        would_overwrite: false,
+        new_depth_estimate: 0,
+        nodes_touched: 1,
+    };
+    assert_eq!(expected, report);
+
+    for k in 0..64 {
+        map.insert(k * 2, k).expect("Failed to insert value");
+    }
+    let root = map.root_hash::<StoreHasher>();
+
+    let overwrite = map.simulate_insert(&10, &0).expect("Failed to simulate");
+    let new = map.simulate_insert(&11, &0).expect("Failed to simulate");
+    assert!(overwrite.would_overwrite && !new.would_overwrite);
+    assert_eq!(overwrite.nodes_touched, overwrite.new_depth_estimate + 1);
+    assert_eq!(new.nodes_touched, new.new_depth_estimate);
+    assert!(new.new_depth_estimate >= 6);
+
+    let remove = map.simulate_remove(&10).expect("Failed to simulate");
+    let missing = map.simulate_remove(&11).expect("Failed to simulate");
+    assert!(remove.would_overwrite && !missing.would_overwrite);
+    assert_eq!(overwrite.nodes_touched - 1, remove.nodes_touched);
+    assert_eq!(0, missing.nodes_touched);
+
+    // Nothing was written
+    assert_eq!(root, map.root_hash::<StoreHasher>());
+    assert_eq!(64, map.len());
+}
+
+#[test]
+fn clear() {
+    let mut map: HashedMap<u64, u64> = HashedMap::default();
+    for k in 0..50 {
+        map.insert(k, k).expect("Failed to insert value");
+    }
+    let root = map.root_hash::<StoreHasher>();
+
+    let detached = map.clear();
+    assert!(map.is_empty());
+    assert_eq!(
+        HashedMap::<u64, u64>::default().root_hash::<StoreHasher>(),
+        map.root_hash::<StoreHasher>()
+    );
+    assert_eq!(root, detached.root_hash::<StoreHasher>());
+    assert_eq!(50, detached.len());
+
+    map.insert(1, 1).expect("Failed to insert value");
+    assert_eq!(1, map.len());
+}
+
+#[test]
+fn key_gaps() {
+    use std::collections::BTreeSet;
+    use std::ops::RangeInclusive;
+
+    fn expected(
+        keys: &BTreeSet<u64>,
+        range: RangeInclusive<u64>,
+        min_gap: u64,
+    ) -> Vec<RangeInclusive<u64>> {
+        let mut gaps = Vec::new();
+        let mut start = None;
+        for k in range.clone() {
+            match (keys.contains(&k), start) {
+                (false, None) => start = Some(k),
+                (true, Some(s)) => {
+                    gaps.push(s..=k - 1);
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+        if let Some(s) = start {
+            gaps.push(s..=*range.end());
+        }
+
+        gaps.retain(|g| g.end() - g.start() >= min_gap);
+        gaps
+    }
+
+    let mut rng = StdRng::seed_from_u64(770u64);
+    let mut map: U64Map<()> = U64Map::default();
+    let mut keys = BTreeSet::new();
+
+    // Dense runs of keys, separated by gaps of random sizes
+    let mut k = 10;
+    while k < 800 {
+        for _ in 0..rng.next_u64() % 20 {
+            map.insert(k, ()).expect("Failed to insert value");
+            keys.insert(k);
+            k += 1;
+        }
+        k += rng.next_u64() % 12;
+    }
+
+    for _ in 0..100 {
+        let start = rng.next_u64() % 900;
+        let end = start + rng.next_u64() % 300;
+        let min_gap = rng.next_u64() % 8;
+
+        assert_eq!(
+            expected(&keys, start..=end, min_gap),
+            map.key_gaps(start..=end, min_gap).expect("Failed to scan")
+        );
+    }
+
+    let last = *keys.iter().next_back().expect("No keys inserted");
+    assert_eq!(
+        vec![last + 1..=u64::MAX],
+        map.key_gaps(.., 2000).expect("Failed to scan")
+    );
+    assert!(map.key_gaps(20..20, 0).expect("Failed to scan").is_empty());
+    assert_eq!(
+        vec![0..=u64::MAX],
+        U64Map::<()>::default()
+            .key_gaps(.., 0)
+            .expect("Failed to scan")
+    );
+}
+
+#[test]
+fn drain() {
+    let mut map: Map<u64, Vec<u8>> = Map::default();
+    for k in 0..100u64 {
+        map.insert(k, k.to_le_bytes().to_vec())
+            .expect("Failed to insert value");
+    }
+
+    let mut target: U64Map<Vec<u8>> = U64Map::default();
+    let mut drained = 0;
+    for entry in map.drain() {
+        let (k, v) = entry.expect("Failed to fetch the entry");
+        assert_eq!(drained, k);
+        target.insert(k, v).expect("Failed to insert value");
+        drained += 1;
+    }
+
+    assert_eq!(100, drained);
+    assert!(map.is_empty());
+    assert_eq!(100, target.len());
+
+    let v = target.get(&42).expect("Failed to fetch").map(|v| v.clone());
+    assert_eq!(Some(42u64.to_le_bytes().to_vec()), v);
+}
+
+#[test]
+fn insert_next() {
+    let mut map: U64Map<u32> = U64Map::default();
+
+    for i in 0..40 {
+        assert_eq!(i as u64, map.insert_next(i).expect("Failed to insert"));
+    }
+
+    map.remove(&3).expect("Failed to remove value");
+    map.remove(&17).expect("Failed to remove value");
+    map.remove(&39).expect("Failed to remove value");
+    assert_eq!(39, map.insert_next(39).expect("Failed to insert"));
+
+    let first = KeyAllocation::FirstFree;
+    assert_eq!(3, map.insert_next_with(3, first).expect("Failed to insert"));
+    assert_eq!(
+        17,
+        map.insert_next_with(17, first).expect("Failed to insert")
+    );
+    assert_eq!(
+        40,
+        map.insert_next_with(40, first).expect("Failed to insert")
+    );
+    assert_eq!(41, map.len());
+
+    map.insert(u64::MAX, 0).expect("Failed to insert value");
+    assert!(matches!(map.insert_next(0), Err(MapError::KeysExhausted)));
+    assert_eq!(
+        41,
+        map.insert_next_with(41, first).expect("Failed to insert")
+    );
+}
+
+#[test]
+fn append() {
+    use std::collections::BTreeMap;
+
+    fn filled(keys: std::ops::Range<u64>) -> HashedMap<u64, u64> {
+        let mut map = HashedMap::default();
+        for k in keys {
+            map.insert(k, k * 10).expect("Failed to insert value");
+        }
+        map
+    }
+
+    let check = |map: &HashedMap<u64, u64>, reference: &BTreeMap<u64, u64>| {
+        let entries: BTreeMap<u64, u64> = map
+            .iter()
+            .expect("Failed to iterate")
+            .map(|e| e.map(|(k, v)| (*k, *v)))
+            .collect::<Result<_, _>>()
+            .expect("Failed to fetch the entries");
+        assert_eq!(reference, &entries);
+        assert_eq!(reference.len(), map.len());
+        map.validate().expect("Failed to validate the map");
+    };
+
+    // Disjoint ranges, on either side
+    let mut map = filled(0..40);
+    let mut high = filled(100..130);
+    let high_root = high.root_hash::<StoreHasher>();
+
+    map.append(&mut high).expect("Failed to append");
+    assert!(high.is_empty());
+    let mut reference: BTreeMap<u64, u64> =
+        (0..40).chain(100..130).map(|k| (k, k * 10)).collect();
+    check(&map, &reference);
+
+    // The grafted tree is kept as a whole
+    let mut grafted = HashedMap::default();
+    grafted
+        .append(&mut filled(100..130))
+        .expect("Failed to append");
+    assert_eq!(high_root, grafted.root_hash::<StoreHasher>());
+
+    // Overlapping ranges, replacing the shared keys
+    let mut overlapping: HashedMap<u64, u64> = HashedMap::default();
+    for k in 20..60 {
+        overlapping.insert(k, k).expect("Failed to insert value");
+        reference.insert(k, k);
+    }
+    map.append(&mut overlapping).expect("Failed to append");
+    check(&map, &reference);
+
+    let mut low = filled(50..60);
+    low.append(&mut HashedMap::default())
+        .expect("Failed to append");
+    assert_eq!(10, low.len());
+
+    // Disjoint maps of equal lengths are grafted, others are rebuilt
+    let mut halves = filled(0..30);
+    let halves_root = halves.root_hash::<StoreHasher>();
+    halves
+        .append(&mut filled(30..60))
+        .expect("Failed to append");
+    assert_eq!(Some((30, 30)), halves.split_sizes());
+    match &halves {
+        KelvinMap::Node(n) => assert_eq!(
+            halves_root,
+            n.left()
+                .val()
+                .expect("Failed to fetch the child")
+                .root_hash::<StoreHasher>()
+        ),
+        _ => panic!("The map is expected to be a node"),
+    }
+
+    let mut uneven: HashedMap<u64, u64> =
+        HashedMap::from_sorted_iter((0..1000).map(|k| (k, k * 10)))
+            .expect("Failed to build the map");
+    uneven
+        .append(&mut filled(1000..1001))
+        .expect("Failed to append");
+    assert_eq!(Some((501, 500)), uneven.split_sizes());
+    check(
+        &uneven,
+        &(0..1001).map(|k| (k, k * 10)).collect::<BTreeMap<_, _>>(),
+    );
+
+    // Large interleaved ranges are merged into a balanced tree
+    let mut even: Map<u64, u64> =
+        Map::from_sorted_iter((0..8000).map(|k| (k * 2, 0)))
+            .expect("Failed to build the map");
+    let mut odd: Map<u64, u64> =
+        Map::from_sorted_iter((0..8000).map(|k| (k * 2 + 1, 1)))
+            .expect("Failed to build the map");
+
+    even.append(&mut odd).expect("Failed to append");
+    assert_eq!(16000, even.len());
+    assert_eq!(Some((8000, 8000)), even.split_sizes());
+    assert_eq!(
+        Some(1),
+        even.get(&9999).expect("Failed to fetch").map(|v| *v)
+    );
+    even.validate().expect("Failed to validate the map");
+}
+
+#[test]
+fn two_phase_commit() {
+    let mut balances: Map<u64, u64> = Map::default();
+    let mut log: U64Map<u64> = U64Map::default();
+    balances.insert(1, 100).expect("Failed to insert value");
+
+    // Both maps are committed together
+    {
+        let mut staged_balances = balances.stage();
+        let mut staged_log = log.stage();
+
+        staged_balances
+            .insert(2, 50)
+            .expect("Failed to insert value");
+        staged_log.insert(0, 2).expect("Failed to insert value");
+
+        commit_all(&mut [&mut staged_balances, &mut staged_log])
+            .expect("Failed to commit");
+    }
+    assert_eq!(2, balances.len());
+    assert_eq!(1, log.len());
+
+    // A failed check rolls both maps back
+    {
+        let mut staged_balances = balances.stage().with_check(|map| {
+            if map.len() > 2 {
+                Err(MapError::OutOfRange)
+            } else {
+                Ok(())
+            }
+        });
+        let mut staged_log = log.stage();
+
+        staged_balances
+            .insert(3, 10)
+            .expect("Failed to insert value");
+        staged_log.insert(1, 3).expect("Failed to insert value");
+        assert_eq!(3, staged_balances.len());
+
+        let committed =
+            commit_all(&mut [&mut staged_balances, &mut staged_log]);
+        assert!(matches!(committed, Err(MapError::OutOfRange)));
+        assert_eq!(2, staged_balances.len());
+    }
+    assert_eq!(2, balances.len());
+    assert_eq!(1, log.len());
+
+    // Dropped changes are discarded, and a single map can be committed alone
+    balances
+        .stage()
+        .insert(4, 1)
+        .expect("Failed to insert value");
+    assert!(!balances.contains_key(&4).expect("Failed to fetch"));
+
+    let mut staged = balances.stage();
+    staged.remove(&1).expect("Failed to remove value");
+    staged.commit().expect("Failed to commit");
+    assert_eq!(1, balances.len());
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn spot_check() {
+    use dusk_kelvin_map::corrupt;
+
+    let mut rng = StdRng::seed_from_u64(773u64);
+
+    let map = balanced_map(0..64);
+    map.spot_check(&mut rng, 32)
+        .expect("Failed to check the map");
+    Map::<u64, u64>::default()
+        .spot_check(&mut rng, 32)
+        .expect("Failed to check the map");
+
+    let mut flipped = map.clone();
+    assert!(corrupt::flip_annotation(&mut flipped).expect("Corrupt"));
+    assert!(matches!(
+        flipped.spot_check(&mut rng, 1),
+        Err(MapError::InvalidAnnotation)
+    ));
+
+    let mut swapped = map.clone();
+    assert!(corrupt::swap_children(&mut swapped).expect("Corrupt"));
+    assert!(matches!(
+        swapped.spot_check(&mut rng, 1),
+        Err(MapError::InvalidOrder)
+    ));
+}
+
+#[test]
+fn extend() {
+    let mut map: Map<u64, u64> = Map::default();
+    map.extend((0..50).map(|k| (k, k * 2)));
+    map.extend(vec![(10, 0), (60, 1)]);
+
+    assert_eq!(51, map.len());
+    assert_eq!(Some(0), map.get(&10).expect("Failed to fetch").map(|v| *v));
+    assert_eq!(Some(98), map.get(&49).expect("Failed to fetch").map(|v| *v));
+
+    map.try_extend((100..110).map(|k| (k, k)))
+        .expect("Failed to extend the map");
+    assert_eq!(61, map.len());
+    map.validate().expect("Failed to validate the map");
+}
+
+#[test]
+fn from_iter() {
+    let sorted: Map<u64, u64> = (0..200).map(|k| (k, k + 1)).collect();
+
+    assert_eq!(200, sorted.len());
+    assert_eq!(Some((100, 100)), sorted.split_sizes());
+    sorted.validate().expect("Failed to validate the map");
+
+    let shuffled: Map<u64, u64> = vec![(5, 0), (1, 1), (5, 2), (3, 3), (1, 4)]
+        .into_iter()
+        .collect();
+
+    assert_eq!(3, shuffled.len());
+    assert_eq!(
+        Some(4),
+        shuffled.get(&1).expect("Failed to fetch").map(|v| *v)
+    );
+    assert_eq!(
+        Some(2),
+        shuffled.get(&5).expect("Failed to fetch").map(|v| *v)
+    );
+    shuffled.validate().expect("Failed to validate the map");
+
+    let empty: Map<u64, u64> = core::iter::empty().collect();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn contains_key_fast() {
+    let mut map: Map<u64, u64> = Map::default();
+    assert!(!map.contains_key_fast(&0).expect("Failed to check the key"));
+
+    map.insert(7, 0).expect("Failed to insert value");
+    assert!(map.contains_key_fast(&7).expect("Failed to check the key"));
+    assert!(!map.contains_key_fast(&8).expect("Failed to check the key"));
+
+    for k in (0..400).step_by(3) {
+        map.insert(k, k).expect("Failed to insert value");
+    }
+
+    for k in 0..410 {
+        assert_eq!(
+            map.contains_key(&k).expect("Failed to check the key"),
+            map.contains_key_fast(&k).expect("Failed to check the key")
+        );
+    }
+}
+
+#[test]
+fn insert_checked() {
+    let mut checked: SumMap<u64, u64, Checked> = SumMap::default();
+
+    checked
+        .insert_checked(0, u64::MAX - 1)
+        .expect("Failed to insert value");
+    checked
+        .insert_checked(1, 1)
+        .expect("Failed to insert value");
+
+    assert!(matches!(
+        checked.insert_checked(2, 1),
+        Err(MapError::Overflow)
+    ));
+    assert!(matches!(
+        checked.insert_checked(1, 2),
+        Err(MapError::Overflow)
+    ));
+
+    // The failed writes are rolled back
+    assert_eq!(2, checked.len());
+    assert_eq!(
+        Some(1),
+        checked.get(&1).expect("Failed to fetch").map(|v| *v)
+    );
+    checked.check_annotation().expect("No overflow");
+
+    checked.insert(2, 1).expect("Failed to insert value");
+    assert!(matches!(
+        checked.check_annotation(),
+        Err(MapError::Overflow)
+    ));
+
+    assert_eq!(
+        Some(1),
+        checked.remove_checked(&2).expect("Failed to remove value")
+    );
+    checked.check_annotation().expect("No overflow");
+
+    let mut map: Map<u64, u64> = Map::default();
+    map.insert_checked(0, u64::MAX)
+        .expect("Failed to insert value");
+    map.insert_checked(1, u64::MAX)
+        .expect("Failed to insert value");
+    assert_eq!(
+        Some(u64::MAX),
+        map.remove_checked(&0).expect("Failed to remove value")
+    );
+}
+
+#[test]
+fn merge() {
+    let mut base: Map<u64, u64> = (0..100).map(|k| (k, k)).collect();
+    let delta: Map<u64, u64> = (90..110).map(|k| (k, 1)).collect();
+
+    let mut conflicts = vec![];
+    base.merge(&delta, |k, v, d| {
+        conflicts.push(*k);
+        v + d
+    })
+    .expect("Failed to merge the maps");
+
+    assert_eq!((90..100).collect::<Vec<_>>(), conflicts);
+    assert_eq!(110, base.len());
+    assert_eq!(
+        Some(95),
+        base.get(&94).expect("Failed to fetch").map(|v| *v)
+    );
+    assert_eq!(
+        Some(1),
+        base.get(&105).expect("Failed to fetch").map(|v| *v)
+    );
+    base.validate().expect("Failed to validate the map");
+
+    let mut empty: Map<u64, u64> = Map::default();
+    empty
+        .merge(&delta, |_, _, _| unreachable!())
+        .expect("Failed to merge the maps");
+    assert_eq!(20, empty.len());
+
+    // A large sorted delta within the key range of the map keeps it balanced
+    let mut base: Map<u64, u64> =
+        Map::from_sorted_iter((0..8000).map(|k| (k * 2, k)))
+            .expect("Failed to build the map");
+    let delta: Map<u64, u64> =
+        Map::from_sorted_iter((1000..9000).map(|k| (k, 1)))
+            .expect("Failed to build the map");
+
+    base.merge(&delta, |_, v, d| v + d)
+        .expect("Failed to merge the maps");
+    assert_eq!(12000, base.len());
+    assert_eq!(Some((6000, 6000)), base.split_sizes());
+    assert_eq!(
+        Some(1001),
+        base.get(&2000).expect("Failed to fetch").map(|v| *v)
+    );
+    assert_eq!(
+        Some(1),
+        base.get(&2001).expect("Failed to fetch").map(|v| *v)
+    );
+    base.validate().expect("Failed to validate the map");
+}
+
+#[test]
+fn from_sorted_iter() {
+    let map: Map<u64, u64> =
+        Map::from_sorted_iter((0..1000).map(|k| (k * 2, k)))
+            .expect("Failed to build the map");
+
+    assert_eq!(1000, map.len());
+    assert_eq!(Some((500, 500)), map.split_sizes());
+    assert_eq!(
+        Some(250),
+        map.get(&500).expect("Failed to fetch").map(|v| *v)
+    );
+    map.validate().expect("Failed to validate the map");
+
+    assert!(matches!(
+        Map::<u64, u64>::from_sorted_iter(vec![(1, 0), (3, 0), (2, 0)]),
+        Err(MapError::InvalidOrder)
+    ));
+    assert!(matches!(
+        Map::<u64, u64>::from_sorted_iter(vec![(1, 0), (1, 0)]),
+        Err(MapError::InvalidOrder)
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn export_with() {
+    use dusk_kelvin_map::ExportError;
+    use std::io::Write;
+
+    let map: Map<u64, u64> = (0..5).rev().map(|k| (k, k * 10)).collect();
+
+    let mut csv = vec![];
+    let exported = map
+        .export_with(|k, v| writeln!(csv, "{},{}", k, v))
+        .expect("Failed to export the map");
+
+    assert_eq!(5, exported);
+    assert_eq!(
+        "0,0\n1,10\n2,20\n3,30\n4,40\n",
+        String::from_utf8(csv).expect("Invalid UTF-8")
+    );
+
+    let mut seen = 0;
+    let failed = map.export_with(|k, _| {
+        seen += 1;
+        if *k == 2 {
+            Err("sink full")
+        } else {
+            Ok(())
+        }
+    });
+
+    assert!(matches!(failed, Err(ExportError::Sink("sink full"))));
+    assert_eq!(3, seen);
+}
+
+#[test]
+fn insert_batch() {
+    let mut map: Map<u64, u64> = Map::default();
+
+    let displaced = map
+        .insert_batch((0..700).map(|k| (k, k)))
+        .expect("Failed to insert the batch");
+    assert!(displaced.iter().all(Option::is_none));
+
+    let displaced = map
+        .insert_batch(vec![(10, 0), (700, 0), (10, 1)])
+        .expect("Failed to insert the batch");
+    assert_eq!(vec![Some(10), None, Some(0)], displaced);
+
+    assert_eq!(701, map.len());
+    assert_eq!(Some(1), map.get(&10).expect("Failed to fetch").map(|v| *v));
+    map.validate().expect("Failed to validate the map");
+
+    // The batch is built balanced
+    let report = map.shrink().expect("Failed to shrink the map");
+    assert_eq!(0, report.rewritten);
+    assert!(report.depth_after <= 20);
+
+    // Several thousand sorted keys, interleaved with the existing ones
+    let mut map: Map<u64, u64> =
+        Map::from_sorted_iter((0..5000).map(|k| (k * 2, 0)))
+            .expect("Failed to build the map");
+    let displaced = map
+        .insert_batch((0..10000).map(|k| (k, 1)))
+        .expect("Failed to insert the batch");
+
+    assert_eq!(5000, displaced.iter().filter(|v| v.is_some()).count());
+    assert_eq!(Some(0), displaced[4]);
+    assert_eq!(None, displaced[5]);
+    assert_eq!(10000, map.len());
+    assert_eq!(Some((5000, 5000)), map.split_sizes());
+    map.validate().expect("Failed to validate the map");
+}
+
+#[test]
+fn cipher_map() {
+    use canonical::CanonError;
+    use dusk_kelvin_map::{CipherMap, Decrypted, ValueCipher};
+
+    struct XorCipher(u8);
+
+    impl XorCipher {
+        fn tag(&self, key: &[u8]) -> u8 {
+            key.iter().fold(self.0, |t, b| t.rotate_left(3) ^ b)
+        }
+    }
+
+    impl ValueCipher for XorCipher {
+        fn encrypt(&self, key: &[u8], plaintext: &[u8]) -> Vec<u8> {
+            let mut sealed = vec![self.tag(key)];
+            sealed.extend(plaintext.iter().map(|b| b ^ self.0));
+            sealed
+        }
+
+        fn decrypt(
+            &self,
+            key: &[u8],
+            ciphertext: &[u8],
+        ) -> Result<Vec<u8>, CanonError> {
+            match ciphertext.split_first() {
+                Some((tag, body)) if *tag == self.tag(key) => {
+                    Ok(body.iter().map(|b| b ^ self.0).collect())
+                }
+                _ => Err(CanonError::InvalidEncoding),
+            }
+        }
+    }
+
+    let mut map: CipherMap<u64, u64, _> = CipherMap::new(XorCipher(0x5a));
+    for k in 0..20 {
+        assert_eq!(None, map.insert(k, k * 7).expect("Failed to insert value"));
+    }
+
+    let value = map.get(&3).expect("Failed to decrypt the value");
+    assert_eq!(21, *value.expect("Value not found"));
+    assert_eq!(Some(21), map.insert(3, 1).expect("Failed to insert value"));
+    assert_eq!(Some(1), map.remove(&3).expect("Failed to remove value"));
+    assert!(!map.contains_key(&3).expect("Failed to check the key"));
+    assert_eq!(19, map.len());
+
+    // The persisted values are not the plain encodings
+    let sealed = map.into_sealed();
+    let ciphertext =
+        sealed.get(&4).expect("Failed to fetch").map(|s| s.clone());
+    let ciphertext = ciphertext.expect("Value not found");
+    assert_ne!(&28u64.to_le_bytes()[..], &ciphertext.ciphertext()[1..]);
+
+    // Opening with another cipher key fails
+    let other: CipherMap<u64, u64, _> =
+        CipherMap::from_sealed(sealed.clone(), XorCipher(0x17));
+    assert!(other.get(&4).is_err());
+
+    let reopened: CipherMap<u64, u64, _> =
+        CipherMap::from_sealed(sealed, XorCipher(0x5a));
+    assert_eq!(
+        Some(28),
+        reopened
+            .get(&4)
+            .expect("Failed to decrypt")
+            .map(Decrypted::into_inner)
+    );
+}
+
+#[test]
+fn bytes32_map() {
+    use std::collections::BTreeMap;
+
+    use dusk_kelvin_map::Bytes32Map;
+
+    // Keys sharing their first bytes, as the hashes of a common namespace
+    let mut rng = StdRng::seed_from_u64(2321u64);
+    let keys: Vec<[u8; 32]> = (0..512)
+        .map(|i| {
+            let mut key = [0xab; 32];
+            rng.fill_bytes(&mut key[20..]);
+            key[19] = (i % 4) as u8;
+            key
+        })
+        .collect();
+
+    let mut map: Bytes32Map<u64> = Bytes32Map::default();
+    let mut reference = BTreeMap::new();
+
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(
+            reference.insert(*key, i as u64),
+            map.insert(*key, i as u64)
+        );
+    }
+    assert_eq!(reference.len(), map.len());
+
+    // Every key is looked up, and iterated in order
+    for (key, value) in reference.iter() {
+        assert_eq!(Some(value), map.get(key));
+    }
+    assert!(!map.contains_key(&[0xab; 32]));
+    assert!(map
+        .iter()
+        .map(|(k, v)| (k, *v))
+        .eq(reference.iter().map(|(k, v)| (*k, *v))));
+
+    // The shared prefixes are persisted once, so the encoding is less than
+    // half the size of the plain entries
+    let bytes = map.encode_to_vec();
+    assert_eq!(bytes.len(), map.encoded_len());
+    assert!(bytes.len() < keys.len() * (32 + 8) / 2);
+
+    let decoded = Bytes32Map::<u64>::decode(&mut Source::new(&bytes))
+        .expect("Failed to decode the map");
+    assert_eq!(map.len(), decoded.len());
+    assert!(decoded.iter().eq(map.iter()));
+
+    // Removals merge the branches left with a single child
+    for (i, key) in keys.iter().enumerate().filter(|(i, _)| i % 3 != 0) {
+        assert_eq!(reference.remove(key), map.remove(key));
+        assert_eq!(None, map.remove(key));
+
+        if i % 7 == 0 {
+            *map.get_mut(&keys[0]).expect("Value not found") += 1;
+            *reference.get_mut(&keys[0]).expect("Value not found") += 1;
+        }
+    }
+    assert_eq!(reference.len(), map.len());
+    assert!(map
+        .iter()
+        .map(|(k, v)| (k, *v))
+        .eq(reference.iter().map(|(k, v)| (*k, *v))));
+    assert!(map.encode_to_vec().len() < bytes.len());
+
+    for key in reference.keys() {
+        map.remove(key);
+    }
+    assert!(map.is_empty());
+    assert_eq!(None, map.iter().next());
+
+    // Encodings of keys that are not 32 bytes long are rejected
+    let mut single: Bytes32Map<u64> = Bytes32Map::new();
+    single.insert([1; 32], 1);
+    let mut forged = single.encode_to_vec();
+    forged[2] = 31;
+    assert!(Bytes32Map::<u64>::decode(&mut Source::new(&forged)).is_err());
+
+    // As are the branches with children out of order
+    single.insert([2; 32], 2);
+    let mut forged = single.encode_to_vec();
+    let second = forged.len() - (1 + 1 + 1 + 31 + 8);
+    forged.swap(3 + 2, second);
+    assert!(Bytes32Map::<u64>::decode(&mut Source::new(&forged)).is_err());
+}